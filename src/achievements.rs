@@ -0,0 +1,218 @@
+// Milestones unlocked by practicing, kept in achievements.txt as one
+// "ID\tTIMESTAMP" line per unlocked achievement. Progress is worked out from
+// the scores and the history, except for perfect sessions, which the history
+// doesn't delimit and are checked at the end of each session.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::days;
+use crate::events::{self, Event};
+use crate::stats::{self, daily_stats, DayStats};
+use crate::{unix_time, GameState, GameUI, History, Scores};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Measure {
+    Reviews,
+    Streak,
+    MatureCards,
+    PerfectSession,
+}
+
+pub struct Achievement {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub measure: Measure,
+    pub goal: usize,
+}
+
+pub const ACHIEVEMENTS: &[Achievement] = &[
+    Achievement {
+        id: "reviews-100",
+        name: "First 100 reviews",
+        measure: Measure::Reviews,
+        goal: 100,
+    },
+    Achievement {
+        id: "reviews-1000",
+        name: "1000 reviews",
+        measure: Measure::Reviews,
+        goal: 1000,
+    },
+    Achievement {
+        id: "streak-7",
+        name: "7-day streak",
+        measure: Measure::Streak,
+        goal: 7,
+    },
+    Achievement {
+        id: "streak-30",
+        name: "30-day streak",
+        measure: Measure::Streak,
+        goal: 30,
+    },
+    Achievement {
+        id: "mature-100",
+        name: "100 mature cards",
+        measure: Measure::MatureCards,
+        goal: 100,
+    },
+    Achievement {
+        id: "perfect-session-50",
+        name: "Perfect 50-question session",
+        measure: Measure::PerfectSession,
+        goal: 50,
+    },
+];
+
+pub struct Progress {
+    reviews: usize,
+    streak: usize,
+    mature_cards: usize,
+    // The length of the session just finished if every answer was perfect.
+    perfect_session: usize,
+}
+
+impl Progress {
+    // `compacted` are the days compacted out of the history by prune.
+    pub fn new(
+        scores: &Scores,
+        history: &History,
+        compacted: &[DayStats],
+        perfect_session: usize,
+    ) -> Self {
+        let days = daily_stats(history, compacted);
+        Self {
+            reviews: days.iter().map(|day| day.reviews).sum(),
+            streak: stats::streak(&days, days::today()),
+            mature_cards: scores
+                .values()
+                .filter(|score| score.schedule.is_mature())
+                .count(),
+            perfect_session,
+        }
+    }
+
+    fn value(&self, measure: Measure) -> usize {
+        match measure {
+            Measure::Reviews => self.reviews,
+            Measure::Streak => self.streak,
+            Measure::MatureCards => self.mature_cards,
+            Measure::PerfectSession => self.perfect_session,
+        }
+    }
+}
+
+// Unlocked achievements by their id, with the time they were unlocked.
+pub type Unlocked = HashMap<String, u64>;
+
+pub fn load(path: &Path) -> io::Result<Unlocked> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let unlocked = fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| {
+            let (id, timestamp) = line.split_once('\t')?;
+            Some((id.to_owned(), timestamp.parse().ok()?))
+        })
+        .collect();
+    Ok(unlocked)
+}
+
+pub fn save(path: &Path, unlocked: &Unlocked) -> io::Result<()> {
+    let mut contents = String::new();
+    for achievement in ACHIEVEMENTS {
+        if let Some(timestamp) = unlocked.get(achievement.id) {
+            contents.push_str(&format!("{}\t{}\n", achievement.id, timestamp));
+        }
+    }
+    fs::write(path, contents)
+}
+
+// Records the achievements reached with `progress` and returns the new ones.
+pub fn unlock(unlocked: &mut Unlocked, progress: &Progress) -> Vec<&'static Achievement> {
+    let now = unix_time();
+    let mut new = Vec::new();
+    for achievement in ACHIEVEMENTS {
+        if !unlocked.contains_key(achievement.id)
+            && progress.value(achievement.measure) >= achievement.goal
+        {
+            unlocked.insert(achievement.id.to_owned(), now);
+            new.push(achievement);
+        }
+    }
+    new
+}
+
+pub fn print(unlocked: &Unlocked, progress: &Progress) {
+    for achievement in ACHIEVEMENTS {
+        match unlocked.get(achievement.id) {
+            Some(timestamp) => println!(
+                "{}\u{2713}{} {}  {}{}{}",
+                termion::color::Fg(termion::color::LightGreen),
+                termion::style::Reset,
+                achievement.name,
+                termion::color::Fg(termion::color::LightBlack),
+                stats::format_date(days::day(*timestamp)),
+                termion::style::Reset,
+            ),
+            // A perfect session is all or nothing.
+            None if achievement.measure == Measure::PerfectSession => {
+                println!("  {}", achievement.name)
+            }
+            None => println!(
+                "  {}  {}{}/{}{}",
+                achievement.name,
+                termion::color::Fg(termion::color::LightBlack),
+                progress.value(achievement.measure),
+                achievement.goal,
+                termion::style::Reset,
+            ),
+        }
+    }
+}
+
+// Unlocks the achievements reached by the end of each session with answers.
+pub struct Unlocker {
+    path: PathBuf,
+    compacted: Vec<DayStats>,
+}
+
+impl Unlocker {
+    pub fn new(path: PathBuf, compacted: Vec<DayStats>) -> Self {
+        Self { path, compacted }
+    }
+
+    fn unlock_reached(&self, state: &GameState, ui: &mut GameUI) -> io::Result<()> {
+        let mut unlocked = load(&self.path)?;
+        let answered = state.new_attempts.len();
+        let perfect_session = if state.perfect_count() == answered {
+            answered
+        } else {
+            0
+        };
+        let progress = state
+            .scores
+            .read(|scores| Progress::new(scores, &state.history, &self.compacted, perfect_session));
+        let new = unlock(&mut unlocked, &progress);
+        if !new.is_empty() {
+            ui.notify_achievements(&new);
+            save(&self.path, &unlocked)?;
+        }
+        Ok(())
+    }
+}
+
+impl events::Observer for Unlocker {
+    fn notify(&mut self, event: &Event, state: &GameState, ui: &mut GameUI) {
+        if !matches!(event, Event::SessionEnded(_)) || state.new_attempts.is_empty() {
+            return;
+        }
+        if let Err(error) = self.unlock_reached(state, ui) {
+            eprintln!("achievements: {}", error);
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, Write as _};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::UNIX_EPOCH;
+
+use flate2::read::GzDecoder;
+use sha2::{Digest as _, Sha256};
+use zip::write::{FileOptions, ZipWriter};
+
+use crate::hash_term;
+
+// Lists the SHA-256 checksum and the path of every other file in a bundle.
+const MANIFEST_NAME: &str = "MANIFEST";
+
+// Written into each extracted archive, with the stamp of the archive it was
+// extracted from.
+const STAMP_NAME: &str = ".stamp";
+
+// Returns the path of the deck file to load. Archives are extracted into the
+// cache directory on first use, and again whenever the archive changes. They
+// are kept apart by their full path, and extracted next to where they go so
+// that only a whole, verified extraction is ever put in place.
+pub fn resolve_deck(path: &Path, cache_dir: &Path) -> io::Result<PathBuf> {
+    let (Some(file_name), Some(kind)) = (
+        path.file_name().and_then(|name| name.to_str()),
+        archive_kind(path),
+    ) else {
+        return Ok(path.to_path_buf());
+    };
+    let stem = file_name
+        .trim_end_matches(".zip")
+        .trim_end_matches(".vtb")
+        .trim_end_matches(".tgz")
+        .trim_end_matches(".gz")
+        .trim_end_matches(".tar");
+    let path = fs::canonicalize(path)?;
+    let name = format!("{}-{}", file_name, hash_term(&path.to_string_lossy()));
+    let target = cache_dir.join("decks").join(&name);
+    let stamp = modification_stamp(&path)?;
+    if fs::read_to_string(target.join(STAMP_NAME)).ok().as_deref() != Some(stamp.as_str()) {
+        let temp = cache_dir
+            .join("decks")
+            .join(format!("{}.{}.tmp", name, process::id()));
+        if temp.exists() {
+            fs::remove_dir_all(&temp)?;
+        }
+        fs::create_dir_all(&temp)?;
+        let result = extract(kind, &path, &temp, stem)
+            .and_then(|()| verify_manifest(&temp))
+            .and_then(|()| fs::write(temp.join(STAMP_NAME), &stamp));
+        if let Err(error) = result {
+            fs::remove_dir_all(&temp)?;
+            return Err(error);
+        }
+        if target.exists() {
+            fs::remove_dir_all(&target)?;
+        }
+        fs::rename(&temp, &target)?;
+    }
+    find_deck(&target, stem)
+}
+
+// The size of a file and the time it was modified, to the nanosecond where
+// the file system keeps it, which tell whether it changed since it was read.
+pub fn modification_stamp(path: &Path) -> io::Result<String> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok(format!(
+        "{} {}.{:09}",
+        metadata.len(),
+        modified.as_secs(),
+        modified.subsec_nanos()
+    ))
+}
+
+#[derive(Clone, Copy)]
+enum ArchiveKind {
+    Zip,
+    TarGz,
+    Gz,
+}
+
+fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let file_name = path.file_name()?.to_str()?;
+    if file_name.ends_with(".zip") || file_name.ends_with(".vtb") {
+        Some(ArchiveKind::Zip)
+    } else if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+        Some(ArchiveKind::TarGz)
+    } else if file_name.ends_with(".gz") {
+        Some(ArchiveKind::Gz)
+    } else {
+        None
+    }
+}
+
+// Whether the deck is read out of a bundle or a compressed file, which can't
+// be added to by appending lines.
+pub fn is_archive(path: &Path) -> bool {
+    archive_kind(path).is_some()
+}
+
+fn extract(kind: ArchiveKind, path: &Path, target: &Path, stem: &str) -> io::Result<()> {
+    let reader = BufReader::new(File::open(path)?);
+    match kind {
+        ArchiveKind::Zip => zip::ZipArchive::new(reader)
+            .and_then(|mut archive| archive.extract(target))
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string())),
+        ArchiveKind::TarGz => tar::Archive::new(GzDecoder::new(reader)).unpack(target),
+        ArchiveKind::Gz => {
+            let mut file = File::create(target.join(stem).with_extension("txt"))?;
+            io::copy(&mut GzDecoder::new(reader), &mut file).map(|_| ())
+        }
+    }
+}
+
+// A bundle holds one deck file along with its media. If there are several,
+// the one named after the archive wins.
+fn find_deck(dir: &Path, stem: &str) -> io::Result<PathBuf> {
+    let mut decks = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "txt") {
+            decks.push(path);
+        }
+    }
+    decks.sort();
+    let preferred = decks
+        .iter()
+        .position(|deck| deck.file_stem().is_some_and(|deck_stem| deck_stem == stem));
+    match preferred {
+        Some(index) => Ok(decks.swap_remove(index)),
+        None if decks.len() == 1 => Ok(decks.remove(0)),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("no deck file found in {}", dir.display()),
+        )),
+    }
+}
+
+// Bundles the deck in `dir` together with its media into a zip archive. The
+// deck must be found the same way as when the bundle is loaded.
+pub fn pack(dir: &Path, output: &Path) -> io::Result<usize> {
+    let stem = output
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    find_deck(dir, stem)?;
+
+    let mut files = Vec::new();
+    collect_files(dir, String::new(), &mut files)?;
+    files.retain(|name| name != MANIFEST_NAME);
+    files.sort();
+
+    let to_io_error = |error: zip::result::ZipError| io::Error::other(error.to_string());
+    let mut writer = ZipWriter::new(File::create(output)?);
+    let mut manifest = String::new();
+    for name in &files {
+        let contents = fs::read(dir.join(name))?;
+        manifest.push_str(&format!("{}\t{}\n", checksum(&contents), name));
+        writer
+            .start_file(name.as_str(), FileOptions::default())
+            .map_err(to_io_error)?;
+        writer.write_all(&contents)?;
+    }
+    writer
+        .start_file(MANIFEST_NAME, FileOptions::default())
+        .map_err(to_io_error)?;
+    writer.write_all(manifest.as_bytes())?;
+    writer.finish().map_err(to_io_error)?;
+    Ok(files.len())
+}
+
+fn collect_files(dir: &Path, prefix: String, files: &mut Vec<String>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        let name = prefix.clone() + &name;
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), name + "/", files)?;
+        } else {
+            files.push(name);
+        }
+    }
+    Ok(())
+}
+
+// Archives without a manifest are accepted as is.
+fn verify_manifest(dir: &Path) -> io::Result<()> {
+    let manifest = match fs::read_to_string(dir.join(MANIFEST_NAME)) {
+        Ok(manifest) => manifest,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(error),
+    };
+    for (i, line) in manifest.lines().enumerate() {
+        let (expected, name) = line.split_once('\t').ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("malformed manifest at line {}", i + 1),
+            )
+        })?;
+        if checksum(&fs::read(dir.join(name))?) != expected {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("checksum mismatch for {}", name),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn checksum(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
@@ -0,0 +1,146 @@
+// Audio drills for reviewing without looking at the screen, such as while
+// commuting:
+//
+//   vocab-trainer DECK --audio [--pause SECS] [--self-grade]
+//
+// The phrases of each question are read out, then after a pause to answer in
+// one's head, the term. Nothing is recorded unless `--self-grade` is given, in
+// which case a key is read after each term: Space, Enter or y if it was
+// remembered, n if it wasn't, and q to stop. Everything is read out with TTS
+// commands, which take the text on their standard input:
+//
+//   [audio]
+//   tts_command = "espeak-ng -v de"
+//   phrase_tts_command = "espeak-ng -v en"
+//   pause = 4
+//
+// The commands default to `[accessibility] tts_command`, and the one for
+// phrases to the one for terms.
+
+use std::io::{self, Write as _};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use termion::event::Key;
+use termion::raw::IntoRawMode as _;
+
+use crate::settings::Settings;
+use crate::{AnswerInput, GameState};
+
+const DEFAULT_PAUSE_SECS: u64 = 4;
+
+// The gap between a term and the next question, so they don't run together.
+const GAP_AFTER_TERM: Duration = Duration::from_millis(1500);
+
+pub struct Options {
+    pub pause: Option<u64>,
+    pub self_grade: bool,
+}
+
+// Returns whether the drill was stopped before the end.
+pub fn run(
+    state: &mut GameState,
+    settings: &Settings,
+    options: &Options,
+    input: &mut AnswerInput,
+) -> io::Result<bool> {
+    let term_command = settings
+        .get_str("audio", "tts_command")
+        .or_else(|| settings.get_str("accessibility", "tts_command"))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "audio drills need a TTS command in [audio] tts_command",
+            )
+        })?;
+    let phrase_command = settings
+        .get_str("audio", "phrase_tts_command")
+        .unwrap_or(term_command);
+    let pause = options.pause.unwrap_or_else(|| {
+        settings
+            .get_integer("audio", "pause")
+            .map_or(DEFAULT_PAUSE_SECS, |pause| pause.max(0) as u64)
+    });
+    if options.self_grade {
+        println!("After each answer: Space if you remembered it, n if not, q to stop");
+    }
+
+    let mut asked = 0;
+    let mut remembered = 0;
+    while let Some(question) = state.next_question() {
+        let phrases: Vec<&str> = state
+            .shown_phrases(&question)
+            .map(|phrase| phrase.body.as_str())
+            .collect();
+        println!();
+        speak(phrase_command, &phrases.join(", "))?;
+        thread::sleep(Duration::from_secs(pause));
+        let term = state.entry(&question).term.clone();
+        speak(term_command, &term)?;
+        asked += 1;
+        if options.self_grade {
+            match read_grade(input)? {
+                Some(true) => {
+                    remembered += 1;
+                    state.grade_self(&question, true);
+                }
+                Some(false) => state.grade_self(&question, false),
+                None => {
+                    print_summary(asked, remembered, options.self_grade);
+                    return Ok(true);
+                }
+            }
+        } else {
+            thread::sleep(GAP_AFTER_TERM);
+        }
+    }
+    print_summary(asked, remembered, options.self_grade);
+    Ok(false)
+}
+
+// Prints the text and waits for it to be read out.
+fn speak(command: &str, text: &str) -> io::Result<()> {
+    println!("{}", text);
+    io::stdout().flush()?;
+    let mut words = command.split_whitespace();
+    let Some(program) = words.next() else {
+        return Ok(());
+    };
+    let mut child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    child.wait()?;
+    Ok(())
+}
+
+// Returns whether the term was remembered, or `None` to stop.
+fn read_grade(input: &mut AnswerInput) -> io::Result<Option<bool>> {
+    // Raw mode makes the key count without Enter. Without a terminal the keys
+    // are still read from the standard input, one character each.
+    let _raw = io::stdout().into_raw_mode().ok();
+    for key in input.keys() {
+        match key? {
+            Key::Char(' ' | '\n' | 'y') => return Ok(Some(true)),
+            Key::Char('n') => return Ok(Some(false)),
+            Key::Char('q') | Key::Ctrl('c' | 'd') | Key::Esc => return Ok(None),
+            _ => {}
+        }
+    }
+    Ok(None)
+}
+
+fn print_summary(asked: usize, remembered: usize, self_grade: bool) {
+    println!();
+    if self_grade {
+        println!("{} of {} remembered", remembered, asked);
+    } else {
+        println!("{} questions read out", asked);
+    }
+}
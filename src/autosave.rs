@@ -0,0 +1,138 @@
+use std::io;
+use std::mem;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::store::ScoreStore;
+use crate::{save_scores, Attempt, HistoryWriter};
+
+#[derive(Default)]
+struct Pending {
+    attempts: Vec<(String, Attempt)>,
+    answers: usize,
+    finished: bool,
+}
+
+// Writes the scores and the new history records on a background thread,
+// every `answers` answers or every `interval`, whichever comes first. Either
+// trigger is disabled by setting it to zero. The scores are taken from the
+// store when they are written, and only if they changed since the last time.
+// Whatever fails to be written is tried again with the next save.
+pub struct Autosave {
+    shared: Arc<(Mutex<Pending>, Condvar)>,
+    answers: usize,
+    handle: JoinHandle<io::Result<()>>,
+}
+
+impl Autosave {
+    pub fn start(
+        store: ScoreStore,
+        score_path: PathBuf,
+        history_path: PathBuf,
+        answers: usize,
+        interval: Duration,
+    ) -> Self {
+        let shared = Arc::new((Mutex::new(Pending::default()), Condvar::new()));
+        let handle = {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                let (pending, condvar) = &*shared;
+                let mut saved_version = store.version();
+                let mut history_writer = HistoryWriter::default();
+                loop {
+                    let mut guard = pending.lock().unwrap();
+                    let is_waiting =
+                        |pending: &mut Pending| !pending.finished && !is_due(pending, answers);
+                    guard = if interval.is_zero() {
+                        condvar.wait_while(guard, is_waiting).unwrap()
+                    } else {
+                        condvar
+                            .wait_timeout_while(guard, interval, is_waiting)
+                            .unwrap()
+                            .0
+                    };
+                    let finished = guard.finished;
+                    let attempts = mem::take(&mut guard.attempts);
+                    guard.answers = 0;
+                    drop(guard);
+                    let mut result = Ok(());
+                    if store.version() != saved_version {
+                        let (scores, version) = store.snapshot();
+                        result = save_scores(&score_path, scores);
+                        if result.is_ok() {
+                            saved_version = version;
+                        }
+                    }
+                    if !attempts.is_empty() {
+                        if let Err(error) = history_writer.append(&history_path, &attempts) {
+                            // They go with the next save, which skips those
+                            // that were written before the failure.
+                            pending.lock().unwrap().attempts.splice(0..0, attempts);
+                            result = Err(error);
+                        }
+                    }
+                    if finished {
+                        return result;
+                    }
+                }
+            })
+        };
+        Self {
+            shared,
+            answers,
+            handle,
+        }
+    }
+
+    pub fn update(&self, attempts: &[(String, Attempt)]) {
+        let (pending, condvar) = &*self.shared;
+        let mut pending = pending.lock().unwrap();
+        pending.attempts.extend_from_slice(attempts);
+        pending.answers += 1;
+        if is_due(&pending, self.answers) {
+            condvar.notify_one();
+        }
+    }
+
+    // Takes back the latest attempt if it is on the term and wasn't written
+    // yet.
+    pub fn retract(&self, term: &str) -> bool {
+        let mut pending = self.shared.0.lock().unwrap();
+        if pending
+            .attempts
+            .last()
+            .is_none_or(|(other, _)| other != term)
+        {
+            return false;
+        }
+        pending.attempts.pop();
+        pending.answers = pending.answers.saturating_sub(1);
+        true
+    }
+
+    // The number of answers left until the next save, if there are unsaved
+    // ones and saving is triggered by answers at all.
+    pub fn remaining_answers(&self) -> Option<usize> {
+        let pending = self.shared.0.lock().unwrap();
+        if self.answers == 0 || pending.answers == 0 {
+            return None;
+        }
+        Some(self.answers.saturating_sub(pending.answers))
+    }
+
+    // Saves whatever is still pending and waits for the thread to finish.
+    pub fn finish(self) -> io::Result<()> {
+        let (pending, condvar) = &*self.shared;
+        pending.lock().unwrap().finished = true;
+        condvar.notify_one();
+        self.handle
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("autosave thread panicked")))
+    }
+}
+
+fn is_due(pending: &Pending, answers: usize) -> bool {
+    answers > 0 && pending.answers >= answers
+}
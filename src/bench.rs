@@ -0,0 +1,101 @@
+// Benchmarks over a synthetic deck, run with `cargo run --release --features
+// bench -- bench [FILTER]`. They live in the binary because the functions they
+// measure are not exported from a library.
+
+use std::collections::HashMap;
+
+use criterion::{black_box, BatchSize, Criterion};
+
+use crate::normalize::Normalizers;
+use crate::scheduler::Schedule;
+use crate::{parse_entries, Entry, GameState, Order, Score, Scores, ADAPTIVE_WINDOW};
+
+const DECK_SIZE: usize = 100_000;
+
+fn synthetic_deck(size: usize) -> String {
+    let mut deck = String::new();
+    for i in 0..size {
+        deck.push_str(&format!(
+            "term {} /meaning {};comment/other meaning/ #tag{}\n",
+            i,
+            i,
+            i % 10
+        ));
+    }
+    deck
+}
+
+fn synthetic_scores(entries: &[Entry]) -> Scores {
+    let mut scores = HashMap::new();
+    for (i, entry) in entries.iter().enumerate().filter(|(i, _)| i % 2 == 0) {
+        let score = Score {
+            correct: (i % 7) as u32,
+            incorrect: (i % 3) as u32,
+            schedule: Schedule {
+                due: (i * 7919 % DECK_SIZE) as u64,
+                ..Schedule::default()
+            },
+            ..Score::default()
+        };
+        scores.insert(entry.key().to_owned(), score);
+    }
+    scores
+}
+
+fn load(deck: &str) -> Vec<Entry> {
+    let lines: Vec<&str> = deck.lines().collect();
+    parse_entries(&lines, 0).unwrap()
+}
+
+pub fn run(filter: Option<&str>) {
+    let mut criterion = Criterion::default().sample_size(10);
+    if let Some(filter) = filter {
+        criterion = criterion.with_filter(filter);
+    }
+    let deck = synthetic_deck(DECK_SIZE);
+
+    criterion.bench_function("parse", |b| {
+        b.iter(|| {
+            deck.lines()
+                .filter_map(|line| Entry::parse(black_box(line).chars().peekable()).ok()?)
+                .count()
+        })
+    });
+    criterion.bench_function("load", |b| b.iter(|| load(black_box(&deck))));
+
+    let entries = load(&deck);
+    let normalizers = Normalizers::default();
+    criterion.bench_function("match", |b| {
+        b.iter(|| {
+            entries
+                .iter()
+                .filter(|entry| entry.matches(black_box(" Term  42 "), true, &normalizers))
+                .count()
+        })
+    });
+
+    let scores = synthetic_scores(&entries);
+    let new_state =
+        |adaptive| GameState::new(load(&deck), scores.clone(), HashMap::new(), adaptive);
+    criterion.bench_function("arrange by due date", |b| {
+        b.iter_batched(
+            || new_state(false),
+            |mut state| state.arrange(Order::Due, None),
+            BatchSize::LargeInput,
+        )
+    });
+    criterion.bench_function("adaptive selection", |b| {
+        b.iter_batched(
+            || {
+                let mut state = new_state(true);
+                // Enough perfect answers to make the next pick the hardest
+                // pending entry.
+                state.recent_results.extend([true; ADAPTIVE_WINDOW]);
+                state
+            },
+            |mut state| state.next_question(),
+            BatchSize::LargeInput,
+        )
+    });
+    criterion.final_summary();
+}
@@ -0,0 +1,56 @@
+use std::borrow::Cow;
+
+// Hebrew, Arabic, Syriac, Thaana and N'Ko, plus their presentation forms.
+fn is_rtl_char(c: char) -> bool {
+    matches!(c,
+        '\u{0590}'..='\u{08FF}'
+        | '\u{FB1D}'..='\u{FDFF}'
+        | '\u{FE70}'..='\u{FEFF}')
+        && c.is_alphabetic()
+}
+
+fn is_ltr_char(c: char) -> bool {
+    c.is_alphabetic() && !is_rtl_char(c)
+}
+
+// Follows the first strong character, like the paragraph direction in the
+// Unicode bidirectional algorithm.
+pub fn is_rtl(text: &str) -> bool {
+    text.chars()
+        .find(|&c| is_rtl_char(c) || is_ltr_char(c))
+        .is_some_and(is_rtl_char)
+}
+
+// Wraps right-to-left text in a directional isolate, so that the separators
+// and colors around it keep their left-to-right order.
+pub fn isolate(text: &str) -> Cow<'_, str> {
+    isolate_as(text, is_rtl(text))
+}
+
+// The same with the direction known beforehand, as for the terms of a
+// right-to-left deck that start with a Latin abbreviation or a number.
+pub fn isolate_as(text: &str, rtl: bool) -> Cow<'_, str> {
+    if rtl {
+        Cow::Owned(format!("\u{2067}{}\u{2069}", text))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+// Vowel points and cantillation marks, which combine with the preceding
+// letter and must be hidden along with it.
+pub fn is_combining_mark(c: char) -> bool {
+    matches!(c,
+        '\u{0591}'..='\u{05BD}'
+        | '\u{05BF}'
+        | '\u{05C1}'..='\u{05C2}'
+        | '\u{05C4}'..='\u{05C5}'
+        | '\u{05C7}'
+        | '\u{0610}'..='\u{061A}'
+        | '\u{064B}'..='\u{065F}'
+        | '\u{0670}'
+        | '\u{06D6}'..='\u{06DC}'
+        | '\u{06DF}'..='\u{06E4}'
+        | '\u{06E7}'..='\u{06E8}'
+        | '\u{06EA}'..='\u{06ED}')
+}
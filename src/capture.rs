@@ -0,0 +1,153 @@
+// Watches the clipboard and adds every word copied to the inbox, for
+// collecting words while reading:
+//
+//   vocab-trainer capture [--interval MS]
+//
+// As whatever is copied is read, capturing has to be turned on in
+// config.toml first:
+//
+//   [capture]
+//   enabled = true
+//
+// The clipboard is read with wl-paste under Wayland and xclip under X11,
+// which have to be installed. Only short single-line texts count as words,
+// so copying a paragraph doesn't fill the inbox. Texts that password managers
+// mark as secret, and ones that look like passwords or tokens, are skipped.
+
+use std::env;
+use std::io;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use crate::settings::Settings;
+use crate::{detect_config_directory, inbox};
+
+const DEFAULT_INTERVAL_MS: u64 = 500;
+
+const MAX_WORD_CHARS: usize = 64;
+
+// Words without spaces shorter than this are taken as words whatever their
+// letters.
+const MIN_SECRET_CHARS: usize = 12;
+
+// The entropy of a text as a share of the most a text of its length can
+// have, beyond which it is too random for a word. Words repeat their letters
+// more than this, even long ones.
+const SECRET_RANDOMNESS: f64 = 0.9;
+
+// The clipboard types that password managers set on the secrets they copy.
+const PASSWORD_MANAGER_HINTS: &[&str] = &[
+    "x-kde-passwordManagerHint",
+    "application/x-nspasteboard-concealed-type",
+];
+
+pub fn run(args: &[String]) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let mut interval = DEFAULT_INTERVAL_MS;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--interval" => {
+                let value = args.next().map(String::as_str).unwrap_or_default();
+                interval = value
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid interval: {}", value)))?;
+            }
+            _ => return Err(invalid(format!("unknown option: {}", arg))),
+        }
+    }
+    let config_dir = detect_config_directory();
+    let settings = Settings::load(config_dir.join("config.toml"))?;
+    if !settings.get_bool("capture", "enabled").unwrap_or(false) {
+        return Err(invalid(
+            "set enabled = true under [capture] in config.toml to watch the clipboard".to_owned(),
+        ));
+    }
+    let inbox_path = config_dir.join("inbox.txt");
+    // What was on the clipboard before is not captured.
+    let mut last = read_clipboard()?;
+    println!(
+        "Capturing copied words into {}. Press Ctrl-C to stop.",
+        inbox_path.display()
+    );
+    loop {
+        thread::sleep(Duration::from_millis(interval));
+        let text = read_clipboard()?;
+        if text == last {
+            continue;
+        }
+        let word = text.trim();
+        if !word.is_empty()
+            && !word.contains('\n')
+            && word.chars().count() <= MAX_WORD_CHARS
+            && !looks_secret(word)
+            && !marked_secret()?
+            && inbox::append(&inbox_path, word, None)?
+        {
+            println!("Captured {}", word);
+        }
+        last = text;
+    }
+}
+
+// Passwords and tokens: long texts without spaces that mix digits, symbols
+// or capitals into the letters, or hardly repeat any of their letters.
+fn looks_secret(text: &str) -> bool {
+    let length = text.chars().count();
+    if length < MIN_SECRET_CHARS || text.contains(char::is_whitespace) {
+        return false;
+    }
+    let classes = [
+        text.chars().skip(1).any(char::is_uppercase),
+        text.chars().any(|c| c.is_ascii_digit()),
+        text.chars()
+            .any(|c| !c.is_alphanumeric() && !"-'.".contains(c)),
+    ];
+    if classes.iter().any(|&class| class) {
+        return true;
+    }
+    let mut counts = std::collections::HashMap::new();
+    for c in text.chars() {
+        *counts.entry(c).or_insert(0) += 1;
+    }
+    let entropy: f64 = counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / length as f64;
+            -p * p.log2()
+        })
+        .sum();
+    entropy >= SECRET_RANDOMNESS * (length as f64).log2()
+}
+
+// Whether the text on the clipboard is one a password manager copied.
+fn marked_secret() -> io::Result<bool> {
+    let types = read_clipboard_with(&["--list-types"], &["-target", "TARGETS"])?;
+    Ok(types
+        .lines()
+        .any(|name| PASSWORD_MANAGER_HINTS.contains(&name.trim())))
+}
+
+fn read_clipboard() -> io::Result<String> {
+    read_clipboard_with(&["--no-newline"], &[])
+}
+
+fn read_clipboard_with(wl_paste_args: &[&str], xclip_args: &[&str]) -> io::Result<String> {
+    let mut command = if env::var_os("WAYLAND_DISPLAY").is_some() {
+        let mut command = Command::new("wl-paste");
+        command.args(wl_paste_args);
+        command
+    } else {
+        let mut command = Command::new("xclip");
+        command.args(["-out", "-selection", "clipboard"]);
+        command.args(xclip_args);
+        command
+    };
+    let output = command.output()?;
+    // An empty clipboard is an error for both.
+    if !output.status.success() {
+        return Ok(String::new());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,478 @@
+// A teacher's session pushing questions to students on the local network:
+//
+//   vocab-trainer host DECK [--port N] [--count N] [--order ORDER]
+//   vocab-trainer join HOST:PORT --code CODE [--name NAME]
+//
+// The teacher sends each question by pressing Enter and sees how many of the
+// students answered it, and how many got it right at the first try without
+// a hint, as the answers come in. Students keep answering until they get it
+// right, so nearly all of them get there in the end.
+//
+// Anyone on the network can reach the port, so students join with the code
+// the host prints for the session, and are turned away without it.
+//
+// Messages are JSON objects on single lines, with string values only. A
+// student introduces itself and receives every question of the session up
+// front, in the format of duels, then one message per question:
+//
+//   {"type": "join", "name": "alice", "code": "kqmzrt"}
+//                                                    student to host
+//   {"type": "deck", "entries": "..."}               host to student
+//   {"type": "refused"}                              host to student, for a
+//                                                    wrong code
+//   {"type": "question", "index": "0"}               host to students
+//   {"type": "answer", "index": "0", "correct": "true", "mistakes": "1",
+//    "ms": "5210"}                                   student to host
+//   {"type": "end"}                                  host to students
+//
+// Students record the answers in their own scores. Questions the teacher
+// moves on from while a student is still answering are skipped, and answers
+// to them aren't counted.
+
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, BufRead as _, BufReader, Write as _};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::duel::{ask, decode_entries, encode_entries, receive};
+use crate::events::{self, EventBus};
+use crate::language::Language;
+use crate::notes::Notes;
+use crate::settings::Settings;
+use crate::stats::{self, json_string};
+use crate::{
+    detect_config_directory, load_history, load_scores, load_session_deck, GameState, GameUI,
+    Order, SessionEnd, SoundTheme, Xorshift,
+};
+
+const DEFAULT_PORT: u16 = 7879;
+
+// Letters of the session code, leaving out those easily read as another.
+const CODE_LETTERS: &[u8] = b"abcdefghjkmnpqrstuvwxyz";
+
+const CODE_LENGTH: usize = 6;
+
+// Joining takes one message each way, which comes at once or not at all.
+const JOIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+enum Event {
+    Joined(usize, TcpStream, String),
+    Message(usize, HashMap<String, String>),
+    Left(usize),
+    Next,
+    Quit,
+}
+
+struct Student {
+    name: String,
+    writer: TcpStream,
+    answered: usize,
+    correct: usize,
+}
+
+pub fn host(args: &[String]) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let mut deck_path = None;
+    let mut port = DEFAULT_PORT;
+    let mut count = None;
+    let mut order = Order::Deck;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => {
+                let value = args.next().map(String::as_str).unwrap_or_default();
+                port = value
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid port: {}", value)))?;
+            }
+            "--count" => {
+                let value = args.next().map(String::as_str).unwrap_or_default();
+                count = Some(
+                    value
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid count: {}", value)))?,
+                );
+            }
+            "--order" => {
+                order = Order::parse(args.next().map(String::as_str).unwrap_or_default())
+                    .map_err(invalid)?
+            }
+            _ if arg.starts_with('-') => return Err(invalid(format!("unknown option: {}", arg))),
+            _ => deck_path = Some(PathBuf::from(arg)),
+        }
+    }
+    let deck_path = deck_path.ok_or_else(|| {
+        invalid("usage: host DECK [--port N] [--count N] [--order ORDER]".to_owned())
+    })?;
+    // The teacher's scores can pick the questions, but nothing is recorded.
+    let config_dir = detect_config_directory();
+    let scores = load_scores(config_dir.join("scores.txt"))?;
+    // Students are sent the cards with the overrides of their tags.
+    let mut settings = Settings::load(config_dir.join("config.toml"))?;
+    let entries = load_session_deck(&deck_path, &mut settings)?;
+    let mut state = GameState::new(entries, scores, HashMap::new(), false);
+    state.arrange(order, count);
+    let questions: Vec<usize> = state.pending.iter().copied().collect();
+    let deck_message = format!(
+        "{{\"type\": \"deck\", \"entries\": {}}}",
+        json_string(&encode_entries(questions.iter().map(|&i| &state.deck[i])))
+    );
+
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    let mut random = Xorshift::from_clock();
+    let code: String = (0..CODE_LENGTH)
+        .map(|_| CODE_LETTERS[(random.next() % CODE_LETTERS.len() as u64) as usize] as char)
+        .collect();
+    let (sender, events) = mpsc::channel();
+    {
+        let sender = sender.clone();
+        let code = code.clone();
+        thread::spawn(move || {
+            for (id, stream) in listener.incoming().flatten().enumerate() {
+                listen(id, stream, code.clone(), sender.clone());
+            }
+        });
+    }
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let event = match line {
+                Ok(line) if line.trim() == ":quit" => Event::Quit,
+                Ok(_) => Event::Next,
+                Err(_) => break,
+            };
+            if sender.send(event).is_err() {
+                return;
+            }
+        }
+        sender.send(Event::Quit).ok();
+    });
+    println!(
+        "Listening on port {}. Students join with: vocab-trainer join HOST:{} --code {}",
+        port, port, code
+    );
+    println!(
+        "Press Enter to send each of the {} questions, or type :quit to stop.",
+        questions.len()
+    );
+
+    let mut students: HashMap<usize, Student> = HashMap::new();
+    let mut current: Option<usize> = None;
+    // Whether each student answered the current question right at the first
+    // try.
+    let mut answers: HashMap<usize, bool> = HashMap::new();
+    let mut results: Vec<(usize, usize)> = Vec::new();
+    for event in events {
+        match event {
+            Event::Joined(id, mut writer, name) => {
+                let mut welcome = deck_message.clone();
+                if let Some(index) = current {
+                    welcome.push_str(&format!(
+                        "\n{{\"type\": \"question\", \"index\": \"{}\"}}",
+                        index
+                    ));
+                }
+                if writeln!(writer, "{}", welcome).is_ok() {
+                    let name = if name.is_empty() {
+                        format!("student {}", id + 1)
+                    } else {
+                        name
+                    };
+                    print_status(&format!("{} joined\n", name));
+                    students.insert(
+                        id,
+                        Student {
+                            name,
+                            writer,
+                            answered: 0,
+                            correct: 0,
+                        },
+                    );
+                }
+            }
+            Event::Message(id, message) => {
+                let Some(student) = students.get_mut(&id) else {
+                    continue;
+                };
+                if message.get("type").map(String::as_str) != Some("answer") {
+                    continue;
+                }
+                let index = message.get("index").and_then(|index| index.parse().ok());
+                if index.is_none() || index != current || answers.contains_key(&id) {
+                    continue;
+                }
+                let correct = message.get("correct").map(String::as_str) == Some("true")
+                    && message.get("mistakes").map(String::as_str) == Some("0");
+                answers.insert(id, correct);
+                student.answered += 1;
+                student.correct += correct as usize;
+            }
+            Event::Left(id) => {
+                if let Some(student) = students.remove(&id) {
+                    print_status(&format!("{} left\n", student.name));
+                }
+            }
+            Event::Next => {
+                if current.is_some() {
+                    results.push(tally(&answers));
+                    println!();
+                }
+                let index = current.map_or(0, |index| index + 1);
+                if index >= questions.len() {
+                    break;
+                }
+                current = Some(index);
+                answers.clear();
+                let entry = &state.deck[questions[index]];
+                let phrases: Vec<&str> = entry
+                    .phrases
+                    .iter()
+                    .map(|phrase| phrase.body.as_str())
+                    .collect();
+                println!(
+                    "Q{}/{} /{}/ ({})",
+                    index + 1,
+                    questions.len(),
+                    phrases.join("/"),
+                    entry.term
+                );
+                let message = format!("{{\"type\": \"question\", \"index\": \"{}\"}}", index);
+                students.retain(|_, student| writeln!(student.writer, "{}", message).is_ok());
+            }
+            Event::Quit => {
+                if current.is_some() {
+                    results.push(tally(&answers));
+                    println!();
+                }
+                break;
+            }
+        }
+        if current.is_some() {
+            let (answered, correct) = tally(&answers);
+            print_status(&format!(
+                "{} of {} answered, {} at the first try ({:.0}%)",
+                answered,
+                students.len(),
+                correct,
+                stats::percentage(correct, answered)
+            ));
+        }
+    }
+
+    for student in students.values_mut() {
+        writeln!(student.writer, "{{\"type\": \"end\"}}").ok();
+    }
+    let answered: usize = results.iter().map(|&(answered, _)| answered).sum();
+    let correct: usize = results.iter().map(|&(_, correct)| correct).sum();
+    println!(
+        "Session: {} questions, {} answers, {:.0}% at the first try",
+        results.len(),
+        answered,
+        stats::percentage(correct, answered)
+    );
+    let mut students: Vec<&Student> = students.values().collect();
+    students.sort_by(|a, b| b.correct.cmp(&a.correct).then_with(|| a.name.cmp(&b.name)));
+    for student in students {
+        println!(
+            "  {:>3} at the first try of {:<3} {}",
+            student.correct, student.answered, student.name
+        );
+    }
+    Ok(())
+}
+
+// Admits the student once it sends the code, and passes on its messages until
+// it leaves.
+fn listen(id: usize, stream: TcpStream, code: String, sender: Sender<Event>) {
+    thread::spawn(move || {
+        let Ok(mut writer) = stream.try_clone() else {
+            return;
+        };
+        if stream.set_read_timeout(Some(JOIN_TIMEOUT)).is_err() {
+            return;
+        }
+        let mut reader = BufReader::new(stream);
+        let name = match receive(&mut reader) {
+            Ok(message) if message.get("type").map(String::as_str) == Some("join") => {
+                if message.get("code") != Some(&code) {
+                    writeln!(writer, "{{\"type\": \"refused\"}}").ok();
+                    return;
+                }
+                message.get("name").cloned().unwrap_or_default()
+            }
+            _ => return,
+        };
+        // Students are silent for as long as the teacher takes to move on.
+        if reader.get_ref().set_read_timeout(None).is_err()
+            || sender.send(Event::Joined(id, writer, name)).is_err()
+        {
+            return;
+        }
+        loop {
+            match receive(&mut reader) {
+                Ok(message) if message.get("type").map(String::as_str) != Some("quit") => {
+                    if sender.send(Event::Message(id, message)).is_err() {
+                        return;
+                    }
+                }
+                _ => break,
+            }
+        }
+        sender.send(Event::Left(id)).ok();
+    });
+}
+
+// The index of the question sent last, waiting for one if none has been sent
+// since the last was answered, or None once the session is over.
+fn latest_question(
+    messages: &Receiver<io::Result<HashMap<String, String>>>,
+    deck_len: usize,
+) -> io::Result<Option<usize>> {
+    let mut latest = None;
+    loop {
+        let message = match messages.try_recv() {
+            Ok(message) => message?,
+            Err(_) if latest.is_some() => return Ok(latest),
+            Err(_) => match messages.recv() {
+                Ok(message) => message?,
+                Err(_) => return Ok(None),
+            },
+        };
+        if message.get("type").map(String::as_str) != Some("question") {
+            return Ok(None);
+        }
+        if let Some(index) = message
+            .get("index")
+            .and_then(|index| index.parse().ok())
+            .filter(|&index| index < deck_len)
+        {
+            latest = Some(index);
+        }
+    }
+}
+
+// The number of answers and of those right at the first try.
+fn tally(answers: &HashMap<usize, bool>) -> (usize, usize) {
+    (
+        answers.len(),
+        answers.values().filter(|&&correct| correct).count(),
+    )
+}
+
+// Overwrites the line below the question, so the counts update in place.
+fn print_status(text: &str) {
+    print!("\r{}{}", termion::clear::CurrentLine, text);
+    io::stdout().flush().ok();
+}
+
+pub fn join(args: &[String]) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let mut address = None;
+    let mut name = env::var("USER").unwrap_or_default();
+    let mut code = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--name" => name = args.next().cloned().unwrap_or_default(),
+            "--code" => code = args.next().cloned(),
+            _ if arg.starts_with('-') => return Err(invalid(format!("unknown option: {}", arg))),
+            _ => address = Some(arg.clone()),
+        }
+    }
+    let (Some(address), Some(code)) = (address, code) else {
+        return Err(invalid(
+            "usage: join HOST:PORT --code CODE [--name NAME]".to_owned(),
+        ));
+    };
+    let stream = TcpStream::connect(&address)?;
+    stream.set_read_timeout(Some(JOIN_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    writeln!(
+        writer,
+        "{{\"type\": \"join\", \"name\": {}, \"code\": {}}}",
+        json_string(&name),
+        json_string(&code)
+    )?;
+    let message = receive(&mut reader)?;
+    if message.get("type").map(String::as_str) == Some("refused") {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "wrong session code",
+        ));
+    }
+    reader.get_ref().set_read_timeout(None)?;
+    let entries = message
+        .get("entries")
+        .filter(|_| message.get("type").map(String::as_str) == Some("deck"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "expected a deck"))?;
+
+    let config_dir = detect_config_directory();
+    let score_path = config_dir.join("scores.txt");
+    let history_path = config_dir.join("history.txt");
+    let scores = load_scores(&score_path)?;
+    let history = load_history(&history_path)?;
+    let mut state = GameState::new(decode_entries(entries)?, scores, history, false);
+    state.pending.clear();
+    state.notes = Some(Notes::load(config_dir.join("notes.txt"))?);
+    let settings = Settings::load(config_dir.join("config.toml"))?;
+    let prompt = settings.get_str("ui", "prompt").unwrap_or("> ");
+    let mut ui = GameUI::new(
+        SoundTheme::from_settings(&settings),
+        prompt.to_owned(),
+        false,
+        true,
+    );
+    ui.bind_keys(&settings).map_err(invalid)?;
+    let language = Language::from_settings(&settings, &state.deck).map_err(invalid)?;
+    state.set_language(&language, &settings).map_err(invalid)?;
+    ui.rtl_terms = language.rtl;
+    let mut bus = EventBus::for_session(&settings, &config_dir)?;
+    println!("Joined {}. Waiting for the first question...", address);
+
+    // The host's messages are read on their own, so that the questions sent
+    // while the student answers are there to be skipped once answered.
+    let (sender, messages) = mpsc::channel();
+    thread::spawn(move || loop {
+        let message = receive(&mut reader);
+        let done = !message
+            .as_ref()
+            .is_ok_and(|message| message.get("type").map(String::as_str) == Some("question"));
+        if sender.send(message).is_err() || done {
+            return;
+        }
+    });
+    let deck_len = state.deck.len();
+    let result: io::Result<SessionEnd> = (|| loop {
+        let Some(index) = latest_question(&messages, deck_len)? else {
+            return Ok(SessionEnd::Completed);
+        };
+        // Every question is asked, whatever the student's scores say.
+        state.pending.push_back(index);
+        let Some(question) = state.next_question() else {
+            continue;
+        };
+        let Some(answer) =
+            ask(&mut ui, &mut state, &mut bus, &question).map_err(io::Error::other)?
+        else {
+            return Ok(SessionEnd::Quit);
+        };
+        writeln!(
+            writer,
+            "{{\"type\": \"answer\", \"index\": \"{}\", \"correct\": \"{}\", \
+             \"mistakes\": \"{}\", \"ms\": \"{}\"}}",
+            index, answer.correct, state.mistakes, answer.ms
+        )?;
+        println!("Waiting for the next question...");
+    })();
+    state.save(&score_path, &history_path)?;
+    let end = match &result {
+        Ok(end) => *end,
+        Err(_) => SessionEnd::Quit,
+    };
+    bus.dispatch(&events::Event::SessionEnded(end), &state, &mut ui);
+    result.map(|_| ())
+}
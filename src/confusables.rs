@@ -0,0 +1,131 @@
+// Letters of different scripts that look the same, such as the Cyrillic а and
+// the Latin a, or full-width letters, which get typed after switching
+// keyboard layouts and make answers that look right wrong. Answers that only
+// differ from the term in them are taken in the term's letters, with a
+// warning, unless the settings say otherwise:
+//
+//   [matching]
+//   confusables = "warn"
+//
+// "accept" takes them without the warning, and "exact" counts them wrong.
+
+use crate::language::Script;
+
+// The Latin letters that Cyrillic and Greek ones are written like.
+const LOOKALIKES: &[(char, char)] = &[
+    ('а', 'a'),
+    ('в', 'B'),
+    ('е', 'e'),
+    ('к', 'k'),
+    ('м', 'M'),
+    ('н', 'H'),
+    ('о', 'o'),
+    ('р', 'p'),
+    ('с', 'c'),
+    ('т', 'T'),
+    ('у', 'y'),
+    ('х', 'x'),
+    ('і', 'i'),
+    ('ј', 'j'),
+    ('ѕ', 's'),
+    ('ԁ', 'd'),
+    ('һ', 'h'),
+    ('ԛ', 'q'),
+    ('ԝ', 'w'),
+    ('А', 'A'),
+    ('В', 'B'),
+    ('Е', 'E'),
+    ('З', '3'),
+    ('К', 'K'),
+    ('М', 'M'),
+    ('Н', 'H'),
+    ('О', 'O'),
+    ('Р', 'P'),
+    ('С', 'C'),
+    ('Т', 'T'),
+    ('У', 'Y'),
+    ('Х', 'X'),
+    ('І', 'I'),
+    ('Ј', 'J'),
+    ('Ѕ', 'S'),
+    ('Α', 'A'),
+    ('Β', 'B'),
+    ('Ε', 'E'),
+    ('Ζ', 'Z'),
+    ('Η', 'H'),
+    ('Ι', 'I'),
+    ('Κ', 'K'),
+    ('Μ', 'M'),
+    ('Ν', 'N'),
+    ('Ο', 'O'),
+    ('Ρ', 'P'),
+    ('Τ', 'T'),
+    ('Υ', 'Y'),
+    ('Χ', 'X'),
+    ('ο', 'o'),
+    ('ν', 'v'),
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Policy {
+    #[default]
+    Warn,
+    Accept,
+    Exact,
+}
+
+impl Policy {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "warn" => Ok(Policy::Warn),
+            "accept" => Ok(Policy::Accept),
+            "exact" => Ok(Policy::Exact),
+            _ => Err(format!("unknown policy for confusables: {}", s)),
+        }
+    }
+}
+
+// The ASCII letter or digit a character looks like, or itself.
+fn skeleton(c: char) -> char {
+    match c {
+        // Full-width forms of the ASCII characters, and the ideographic space.
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        '\u{3000}' => ' ',
+        _ => LOOKALIKES
+            .iter()
+            .find(|&&(lookalike, _)| lookalike == c)
+            .map_or(c, |&(_, latin)| latin),
+    }
+}
+
+// The answer with the letters the term doesn't have replaced with ones of the
+// term that look the same, and the replacements, if there were any. Only
+// letters of another script than the term's are replaced, as the lookalikes
+// of a script, such as the Cyrillic в and В, are told apart by its readers.
+pub fn in_letters_of(answer: &str, term: &str) -> Option<(String, Vec<(char, char)>)> {
+    let mut replaced: Vec<(char, char)> = Vec::new();
+    let fixed = answer
+        .chars()
+        .map(|c| {
+            let script = Script::of(c);
+            if term.contains(c) || script.is_some() && term.chars().any(|t| Script::of(t) == script)
+            {
+                return c;
+            }
+            let lookalike = term
+                .chars()
+                .find(|&t| skeleton(t) == skeleton(c))
+                .or_else(|| Some(skeleton(c)).filter(|&s| s != c && s.is_ascii()));
+            match lookalike {
+                Some(lookalike) => {
+                    if !replaced.contains(&(c, lookalike)) {
+                        replaced.push((c, lookalike));
+                    }
+                    lookalike
+                }
+                None => c,
+            }
+        })
+        .collect();
+    (!replaced.is_empty()).then_some((fixed, replaced))
+}
@@ -0,0 +1,155 @@
+// Example sentences from a corpus in the format of Tatoeba's exports, with one
+// sentence per line and its text in the last tab-separated column:
+//
+//   [corpus]
+//   path = "/path/to/sentences.tsv"
+//
+// The corpus is indexed into the cache directory on first use, and again
+// whenever it is modified. The index holds the byte offsets of the sentences
+// containing each word, so that looking up a term reads only those. It is
+// kept for the corpus's full path, and records the time the corpus was
+// modified as it was read, so that a corpus of the same name elsewhere or
+// one put back in place of another isn't looked up through the wrong index.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead as _, BufReader, BufWriter, Seek as _, SeekFrom, Write as _};
+use std::path::{Path, PathBuf};
+
+use crate::archive::modification_stamp;
+use crate::{hash_term, shuffle};
+
+// The most sentences read for a single term before giving up.
+const MAX_CANDIDATES: usize = 200;
+
+pub struct Corpus {
+    path: PathBuf,
+    index: HashMap<String, Vec<u64>>,
+}
+
+impl Corpus {
+    pub fn open(path: &Path, cache_dir: &Path) -> io::Result<Self> {
+        let path = fs::canonicalize(path)?;
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let index_path = cache_dir.join("corpus").join(format!(
+            "{}-{}.index",
+            file_name,
+            hash_term(&path.to_string_lossy())
+        ));
+        let stamp = modification_stamp(&path)?;
+        let index = match load_index(&index_path, &stamp)? {
+            Some(index) => index,
+            None => {
+                let index = build_index(&path)?;
+                save_index(&index_path, &stamp, &index)?;
+                index
+            }
+        };
+        Ok(Self { path, index })
+    }
+
+    // A random sentence containing the term, ignoring case.
+    pub fn example(&self, term: &str) -> io::Result<Option<String>> {
+        let term = term.to_lowercase();
+        // Sentences containing the term contain its rarest word.
+        let offsets = words(&term)
+            .map(|word| self.index.get(word).map_or(&[][..], Vec::as_slice))
+            .min_by_key(|offsets| offsets.len());
+        let mut offsets = match offsets {
+            Some(offsets) if !offsets.is_empty() => offsets.to_vec(),
+            _ => return Ok(None),
+        };
+        shuffle(&mut offsets);
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let mut line = String::new();
+        for offset in offsets.into_iter().take(MAX_CANDIDATES) {
+            reader.seek(SeekFrom::Start(offset))?;
+            line.clear();
+            reader.read_line(&mut line)?;
+            let text = sentence(&line);
+            if text.to_lowercase().contains(&term) {
+                return Ok(Some(text.to_owned()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn sentence(line: &str) -> &str {
+    let line = line.trim_end_matches(['\r', '\n']);
+    line.rsplit('\t').next().unwrap_or(line)
+}
+
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+}
+
+fn build_index(path: &Path) -> io::Result<HashMap<String, Vec<u64>>> {
+    let mut index: HashMap<String, Vec<u64>> = HashMap::new();
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut line = String::new();
+    let mut offset = 0;
+    loop {
+        line.clear();
+        let len = reader.read_line(&mut line)?;
+        if len == 0 {
+            break;
+        }
+        let text = sentence(&line).to_lowercase();
+        let mut seen: Vec<&str> = Vec::new();
+        for word in words(&text) {
+            if !seen.contains(&word) {
+                seen.push(word);
+                index.entry(word.to_owned()).or_default().push(offset);
+            }
+        }
+        offset += len as u64;
+    }
+    Ok(index)
+}
+
+// The stamp of the corpus on the first line, and then one
+// "WORD\tOFFSET OFFSET..." line per word. None if there is no index yet, or
+// it is of another version of the corpus.
+fn load_index(path: &Path, stamp: &str) -> io::Result<Option<HashMap<String, Vec<u64>>>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    let mut lines = BufReader::new(file).lines();
+    if lines.next().transpose()?.as_deref() != Some(stamp) {
+        return Ok(None);
+    }
+    let mut index = HashMap::new();
+    for line in lines {
+        let line = line?;
+        if let Some((word, offsets)) = line.split_once('\t') {
+            let offsets = offsets
+                .split(' ')
+                .filter_map(|offset| offset.parse().ok())
+                .collect();
+            index.insert(word.to_owned(), offsets);
+        }
+    }
+    Ok(Some(index))
+}
+
+// Written next to the index and moved over it, so that a session reading it
+// meanwhile or a failed write never leaves half an index behind.
+fn save_index(path: &Path, stamp: &str, index: &HashMap<String, Vec<u64>>) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp_path = path.with_extension("index.tmp");
+    let mut writer = BufWriter::new(File::create(&temp_path)?);
+    writeln!(writer, "{}", stamp)?;
+    for (word, offsets) in index {
+        let offsets: Vec<String> = offsets.iter().map(u64::to_string).collect();
+        writeln!(writer, "{}\t{}", word, offsets.join(" "))?;
+    }
+    writer.flush()?;
+    drop(writer);
+    fs::rename(&temp_path, path)
+}
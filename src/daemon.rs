@@ -0,0 +1,430 @@
+// Serves a quiz session over a Unix socket, so that editors can ask questions
+// without starting a process each time.
+//
+// Each request is a JSON object on a single line, answered by a JSON object on
+// a single line. Requests carry a "command" and, for answers, an "answer":
+//
+//   {"command": "next"}
+//   {"index": 0, "phrases": ["to get"], "badge": "new", "hint": "_______"}
+//   {"done": true}                         (no questions left)
+//
+//   {"command": "answer", "answer": "recieve"}
+//   {"correct": false, "mistakes": 1}
+//   {"correct": true, "term": "receive", "mistakes": 1}
+//
+//   {"command": "stats"}
+//   {"answered": 1, "perfect": 0, "remaining": 3}
+//
+//   {"command": "quit"}
+//   {"ok": true}                           (the daemon saves and exits)
+//
+// "next" returns the same question until it is answered correctly. Errors are
+// reported as {"error": "message"}.
+//
+// `vocab-trainer client COMMAND [ANSWER]` sends a single request and prints
+// the response, which is enough for most integrations. From Vim, for example:
+//
+//   let question = json_decode(system('vocab-trainer client next'))
+//   let result = json_decode(system('vocab-trainer client answer '
+//         \ . shellescape(input(join(question.phrases, '/') . '> '))))
+//
+// With `--http ADDRESS`, the daemon also accepts words for the inbox over
+// HTTP, so that a browser extension or a bookmarklet can send them while
+// reading. The body is a JSON object like the requests above, with the token
+// set as `[daemon] token` in config.toml, which keeps other pages open in the
+// browser from filling the inbox:
+//
+//   POST /inbox  {"token": "...", "word": "Fernweh", "context": "Ich habe Fernweh."}
+//   {"added": true}                        (false if it was already there)
+//
+// Pages of other origins can send words but not read the response. A
+// bookmarklet sending the selected word and the paragraph around it:
+//
+//   javascript:fetch('http://127.0.0.1:7879/inbox', {method: 'POST',
+//     mode: 'no-cors', body: JSON.stringify({token: '...',
+//       word: getSelection().toString(),
+//       context: getSelection().anchorNode.textContent})})
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead as _, BufReader, Read as _, Write as _};
+use std::iter::Peekable;
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::str::Chars;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::stats::json_string;
+use crate::{inbox, mask_term, GameState, Question};
+
+// Words and their context sentences are short, so anything longer isn't one.
+const MAX_BODY_BYTES: usize = 16 * 1024;
+
+const MAX_HEADER_BYTES: u64 = 8 * 1024;
+
+// Connections are handled one at a time, so one that is slow to send its
+// request holds up the others for no longer than this.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn serve(state: &mut GameState, socket_path: &Path) -> io::Result<()> {
+    // A socket left behind by a daemon that was killed would fail the bind.
+    if UnixStream::connect(socket_path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("a daemon is already listening on {}", socket_path.display()),
+        ));
+    }
+    if socket_path.exists() {
+        fs::remove_file(socket_path)?;
+    }
+    let listener = UnixListener::bind(socket_path)?;
+    let mut session = Session {
+        state,
+        question: None,
+    };
+    let result = session.accept(&listener);
+    fs::remove_file(socket_path).ok();
+    result
+}
+
+struct Session<'a> {
+    state: &'a mut GameState,
+    question: Option<Question>,
+}
+
+impl Session<'_> {
+    fn accept(&mut self, listener: &UnixListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            // A client going away in the middle of a request, or sending
+            // something that isn't text, is its problem and not the session's.
+            match self.handle(stream?) {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(error) if error.kind() == io::ErrorKind::BrokenPipe => {}
+                Err(error) => eprintln!("daemon: {}", error),
+            }
+        }
+        Ok(())
+    }
+
+    // Returns whether the client asked the daemon to quit.
+    fn handle(&mut self, stream: UnixStream) -> io::Result<bool> {
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (response, quit) = match parse_request(&line) {
+                Ok(request) => self.respond(&request),
+                Err(message) => (error_response(&message), false),
+            };
+            writeln!(writer, "{}", response)?;
+            if quit {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn respond(&mut self, request: &HashMap<String, String>) -> (String, bool) {
+        let command = request.get("command").map(String::as_str);
+        let response = match command {
+            Some("next") => self.next(),
+            Some("answer") => match request.get("answer") {
+                Some(answer) => self.answer(answer),
+                None => error_response("missing answer"),
+            },
+            Some("stats") => format!(
+                "{{\"answered\": {}, \"perfect\": {}, \"remaining\": {}}}",
+                self.state.new_attempts.len(),
+                self.state.perfect_count(),
+                self.state.pending.len() + self.question.is_some() as usize,
+            ),
+            Some("quit") => return ("{\"ok\": true}".to_owned(), true),
+            Some(command) => error_response(&format!("unknown command: {}", command)),
+            None => error_response("missing command"),
+        };
+        (response, false)
+    }
+
+    fn next(&mut self) -> String {
+        if self.question.is_none() {
+            self.question = self.state.next_question();
+        }
+        let question = match &self.question {
+            Some(question) => question,
+            None => return "{\"done\": true}".to_owned(),
+        };
+        let entry = self.state.entry(question);
+        let phrases: Vec<String> = self
+            .state
+            .shown_phrases(question)
+            .map(|phrase| json_string(&phrase.body))
+            .collect();
+        let badge = self.state.difficulty_badge(entry.key());
+        let mut response = format!(
+            "{{\"index\": {}, \"phrases\": [{}], \"badge\": {}",
+            question.index,
+            phrases.join(", "),
+            json_string(badge.label().trim_matches(['[', ']'])),
+        );
+        if self.state.hints_enabled() {
+            let hint = mask_term(&entry.term, self.state.mistakes, self.state.script);
+            response.push_str(&format!(", \"hint\": {}", json_string(&hint)));
+        }
+        response.push('}');
+        response
+    }
+
+    fn answer(&mut self, answer: &str) -> String {
+        let question = match &self.question {
+            Some(question) => question,
+            None => return error_response("no question asked"),
+        };
+        if self.state.answer_question(question, answer) {
+            let response = format!(
+                "{{\"correct\": true, \"term\": {}, \"mistakes\": {}}}",
+                json_string(&self.state.entry(question).term),
+                self.state.mistakes
+            );
+            self.question = None;
+            response
+        } else {
+            format!(
+                "{{\"correct\": false, \"mistakes\": {}}}",
+                self.state.mistakes
+            )
+        }
+    }
+}
+
+// Accepts words for the inbox on the address in the background, for as long
+// as the daemon runs.
+pub fn serve_inbox(address: &str, token: String, inbox_path: PathBuf) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(error) = handle_http(stream, &token, &inbox_path) {
+                eprintln!("inbox: {}", error);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn handle_http(stream: TcpStream, token: &str, inbox_path: &Path) -> io::Result<()> {
+    let deadline = Instant::now() + REQUEST_TIMEOUT;
+    let mut writer = stream.try_clone()?;
+    // The timeout of each read is what is left of the request's, so that a
+    // request sent a byte at a time can't keep the connection open either.
+    let wait = |stream: &TcpStream| {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "request timed out"));
+        }
+        stream.set_read_timeout(Some(left))
+    };
+    writer.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+    let mut head = (&mut reader).take(MAX_HEADER_BYTES);
+    let mut request_line = String::new();
+    wait(&writer)?;
+    head.read_line(&mut request_line)?;
+    let mut content_length = 0;
+    let mut complete = false;
+    loop {
+        let mut header = String::new();
+        wait(&writer)?;
+        if head.read_line(&mut header)? == 0 {
+            break;
+        }
+        if header.trim().is_empty() {
+            complete = true;
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut parts = request_line.split_whitespace();
+    let (status, response) = match (parts.next(), parts.next()) {
+        _ if !complete => (
+            "431 Request Header Fields Too Large",
+            error_response("request headers too large"),
+        ),
+        (Some("POST"), Some("/inbox")) if content_length > MAX_BODY_BYTES => {
+            ("413 Payload Too Large", error_response("request too large"))
+        }
+        (Some("POST"), Some("/inbox")) => {
+            let mut body = vec![0; content_length];
+            wait(&writer)?;
+            reader.read_exact(&mut body)?;
+            let request = parse_request(String::from_utf8_lossy(&body).trim());
+            match request {
+                Ok(request)
+                    if !request
+                        .get("token")
+                        .is_some_and(|given| same_token(given, token)) =>
+                {
+                    ("403 Forbidden", error_response("invalid token"))
+                }
+                Ok(request) => match request.get("word").filter(|word| !word.trim().is_empty()) {
+                    Some(word) => {
+                        let context = request.get("context").map(String::as_str);
+                        let added = inbox::append(inbox_path, word, context)?;
+                        ("200 OK", format!("{{\"added\": {}}}", added))
+                    }
+                    None => ("400 Bad Request", error_response("missing word")),
+                },
+                Err(message) => ("400 Bad Request", error_response(&message)),
+            }
+        }
+        (_, Some("/inbox")) => (
+            "405 Method Not Allowed",
+            error_response("method not allowed"),
+        ),
+        _ => ("404 Not Found", error_response("not found")),
+    };
+    write!(
+        writer,
+        "HTTP/1.1 {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {}",
+        status,
+        response.len(),
+        response
+    )
+}
+
+// Sends one request and returns the response line.
+pub fn send(socket_path: &Path, command: &str, answer: Option<&str>) -> io::Result<String> {
+    let mut stream = UnixStream::connect(socket_path).map_err(|error| {
+        io::Error::new(
+            error.kind(),
+            format!(
+                "no daemon listening on {}: {}",
+                socket_path.display(),
+                error
+            ),
+        )
+    })?;
+    let mut request = format!("{{\"command\": {}", json_string(command));
+    if let Some(answer) = answer {
+        request.push_str(&format!(", \"answer\": {}", json_string(answer)));
+    }
+    request.push('}');
+    writeln!(stream, "{}", request)?;
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    Ok(response.trim_end().to_owned())
+}
+
+// Compares every byte, so that the time taken doesn't tell how much of the
+// token was right.
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+fn error_response(message: &str) -> String {
+    format!("{{\"error\": {}}}", json_string(message))
+}
+
+// Requests are flat objects whose values are strings.
+pub fn parse_request(line: &str) -> Result<HashMap<String, String>, String> {
+    let mut input = line.chars().peekable();
+    let mut request = HashMap::new();
+    expect(&mut input, '{')?;
+    skip_whitespace(&mut input);
+    if input.peek() == Some(&'}') {
+        input.next();
+        return Ok(request);
+    }
+    loop {
+        let key = parse_string(&mut input)?;
+        expect(&mut input, ':')?;
+        let value = parse_string(&mut input)?;
+        request.insert(key, value);
+        skip_whitespace(&mut input);
+        match input.next() {
+            Some(',') => {}
+            Some('}') => return Ok(request),
+            _ => return Err("expected ',' or '}'".to_owned()),
+        }
+    }
+}
+
+pub fn skip_whitespace(input: &mut Peekable<Chars>) {
+    while input.peek().is_some_and(|c| c.is_whitespace()) {
+        input.next();
+    }
+}
+
+fn expect(input: &mut Peekable<Chars>, expected: char) -> Result<(), String> {
+    skip_whitespace(input);
+    match input.next() {
+        Some(c) if c == expected => Ok(()),
+        _ => Err(format!("expected '{}'", expected)),
+    }
+}
+
+// Characters outside the Basic Multilingual Plane are escaped as a pair of
+// UTF-16 surrogates, as Python's json module does for Anki's deck names.
+fn parse_unicode_escape(input: &mut Peekable<Chars>) -> Result<char, String> {
+    let high = parse_code_unit(input)?;
+    let code = if (0xD800..0xDC00).contains(&high) {
+        if input.next() != Some('\\') || input.next() != Some('u') {
+            return Err(format!("unpaired surrogate: \\u{:04x}", high));
+        }
+        let low = parse_code_unit(input)?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(format!("unpaired surrogate: \\u{:04x}", high));
+        }
+        0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+    } else {
+        high
+    };
+    char::from_u32(code).ok_or_else(|| format!("unpaired surrogate: \\u{:04x}", code))
+}
+
+fn parse_code_unit(input: &mut Peekable<Chars>) -> Result<u32, String> {
+    let code: String = input.by_ref().take(4).collect();
+    if code.len() != 4 {
+        return Err(format!("invalid escape: \\u{}", code));
+    }
+    u32::from_str_radix(&code, 16).map_err(|_| format!("invalid escape: \\u{}", code))
+}
+
+pub fn parse_string(input: &mut Peekable<Chars>) -> Result<String, String> {
+    expect(input, '"')?;
+    let mut s = String::new();
+    loop {
+        match input.next() {
+            Some('"') => return Ok(s),
+            Some('\\') => match input.next() {
+                Some('n') => s.push('\n'),
+                Some('t') => s.push('\t'),
+                Some('r') => s.push('\r'),
+                Some('b') => s.push('\u{8}'),
+                Some('f') => s.push('\u{c}'),
+                Some('u') => s.push(parse_unicode_escape(input)?),
+                Some(c) => s.push(c),
+                None => return Err("unterminated string".to_owned()),
+            },
+            Some(c) => s.push(c),
+            None => return Err("unterminated string".to_owned()),
+        }
+    }
+}
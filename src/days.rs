@@ -0,0 +1,166 @@
+// The days reviews are counted in, for streaks, reviews of the day, due
+// counts and burying. They are days of the local time, and start at an hour
+// other than midnight if set, so that reviews done late at night still count
+// towards the day before:
+//
+//   [schedule]
+//   day_starts_at = 4
+//   utc_offset = "+09:00"
+//
+// The offset is by default that of the system's time zone at each time, by
+// its rules, so that days keep their hours across changes of daylight saving
+// time. Only config.toml sets these, as they depend on the player rather than
+// the deck.
+
+use std::ffi::{c_char, c_int, c_long};
+use std::mem::MaybeUninit;
+use std::sync::{Once, OnceLock};
+
+use crate::settings::Settings;
+use crate::stats::days_from_civil;
+use crate::{detect_config_directory, unix_time};
+
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Clock {
+    // None for the offset of the local time zone.
+    utc_offset: Option<i64>,
+    day_start: i64,
+}
+
+impl Clock {
+    fn from_settings(settings: &Settings) -> Result<Self, String> {
+        let utc_offset = match settings.get_str("schedule", "utc_offset") {
+            Some(offset) => Some(
+                parse_offset(offset).ok_or_else(|| format!("invalid utc_offset: {}", offset))?,
+            ),
+            None => None,
+        };
+        let day_start = match settings.get_integer("schedule", "day_starts_at") {
+            Some(hour @ 0..=23) => hour * 60 * 60,
+            Some(hour) => return Err(format!("invalid day_starts_at: {}", hour)),
+            None => 0,
+        };
+        Ok(Self {
+            utc_offset,
+            day_start,
+        })
+    }
+
+    fn offset_at(&self, timestamp: i64) -> i64 {
+        self.utc_offset
+            .unwrap_or_else(|| local_offset(timestamp).unwrap_or(0))
+    }
+}
+
+// Settings that fail to load are reported once, and the days are then those
+// of the local time zone, starting at midnight.
+fn clock() -> Clock {
+    static CLOCK: OnceLock<Clock> = OnceLock::new();
+    *CLOCK.get_or_init(|| {
+        Settings::load(detect_config_directory().join("config.toml"))
+            .map_err(|error| error.to_string())
+            .and_then(|settings| Clock::from_settings(&settings))
+            .unwrap_or_else(|error| {
+                eprintln!("config: {}", error);
+                Clock::default()
+            })
+    })
+}
+
+// "+09:00", "-0530" or "Z".
+fn parse_offset(offset: &str) -> Option<i64> {
+    if offset == "Z" || offset.eq_ignore_ascii_case("utc") {
+        return Some(0);
+    }
+    let (sign, digits) = match offset.split_at_checked(1)? {
+        ("+", digits) => (1, digits),
+        ("-", digits) => (-1, digits),
+        _ => return None,
+    };
+    let digits = digits.replace(':', "");
+    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i64 = digits[..2].parse().ok()?;
+    let minutes: i64 = digits[2..].parse().ok()?;
+    (hours <= 14 && minutes < 60).then_some(sign * (hours * 60 + minutes) * 60)
+}
+
+// The `struct tm` of POSIX, with the fields that BSD and glibc add to it,
+// which are written to whether they are read or not.
+#[repr(C)]
+struct Tm {
+    tm_sec: c_int,
+    tm_min: c_int,
+    tm_hour: c_int,
+    tm_mday: c_int,
+    tm_mon: c_int,
+    tm_year: c_int,
+    tm_wday: c_int,
+    tm_yday: c_int,
+    tm_isdst: c_int,
+    tm_gmtoff: c_long,
+    tm_zone: *const c_char,
+}
+
+extern "C" {
+    fn tzset();
+    fn localtime_r(time: *const i64, result: *mut Tm) -> *mut Tm;
+}
+
+// The offset of the local time zone at the time, as the C library tells it
+// from TZ or /etc/localtime.
+fn local_offset(timestamp: i64) -> Option<i64> {
+    static TZSET: Once = Once::new();
+    // SAFETY: tzset only reads the environment, and localtime_r writes to the
+    // `tm` given to it, unlike localtime.
+    TZSET.call_once(|| unsafe { tzset() });
+    let mut tm = MaybeUninit::<Tm>::zeroed();
+    let result = unsafe { localtime_r(&timestamp, tm.as_mut_ptr()) };
+    if result.is_null() {
+        return None;
+    }
+    let tm = unsafe { tm.assume_init() };
+    let day = days_from_civil(
+        tm.tm_year as i64 + 1900,
+        tm.tm_mon as u32 + 1,
+        tm.tm_mday as u32,
+    );
+    let local = day as i64 * SECS_PER_DAY
+        + tm.tm_hour as i64 * 60 * 60
+        + tm.tm_min as i64 * 60
+        + tm.tm_sec as i64;
+    Some(local - timestamp)
+}
+
+// The day a time falls on, counted from the Unix epoch like dates in UTC are.
+pub fn day(timestamp: u64) -> u64 {
+    let clock = clock();
+    let timestamp = timestamp as i64;
+    (timestamp + clock.offset_at(timestamp) - clock.day_start)
+        .div_euclid(SECS_PER_DAY)
+        .max(0) as u64
+}
+
+pub fn today() -> u64 {
+    day(unix_time())
+}
+
+// The time a day starts at. The offset is that of the start itself, found
+// from the one of the same hour in UTC, which is at most a change of daylight
+// saving time away.
+pub fn start(day: u64) -> u64 {
+    let clock = clock();
+    let local = day as i64 * SECS_PER_DAY + clock.day_start;
+    let guess = local - clock.offset_at(local);
+    (local - clock.offset_at(guess)).max(0) as u64
+}
+
+// The time in the local time zone, as seconds from the local midnight of the
+// epoch, for showing the time of day.
+pub fn local(timestamp: u64) -> u64 {
+    let timestamp = timestamp as i64;
+    (timestamp + clock().offset_at(timestamp)).max(0) as u64
+}
@@ -0,0 +1,59 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Change {
+    Equal,
+    Delete,
+    Insert,
+}
+
+// The table of the LCS grows with the product of the lengths, and answers
+// longer than this are no typos of a term anyway.
+const MAX_LENGTH: usize = 200;
+
+// A character-level diff from `from` to `to`, based on their longest common
+// subsequence. Consecutive characters with the same change are grouped. Texts
+// longer than `MAX_LENGTH` are not compared, and come out as all of `from`
+// deleted and all of `to` inserted.
+pub fn diff(from: &str, to: &str) -> Vec<(Change, String)> {
+    let from: Vec<char> = from.chars().collect();
+    let to: Vec<char> = to.chars().collect();
+    if from.len() > MAX_LENGTH || to.len() > MAX_LENGTH {
+        return [(Change::Delete, from), (Change::Insert, to)]
+            .into_iter()
+            .filter(|(_, text)| !text.is_empty())
+            .map(|(change, text)| (change, text.into_iter().collect()))
+            .collect();
+    }
+
+    // lengths[i][j] is the length of the LCS of from[i..] and to[j..].
+    let mut lengths = vec![vec![0usize; to.len() + 1]; from.len() + 1];
+    for i in (0..from.len()).rev() {
+        for j in (0..to.len()).rev() {
+            lengths[i][j] = if from[i] == to[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes: Vec<(Change, String)> = Vec::new();
+    let mut push = |change: Change, c: char| match changes.last_mut() {
+        Some((last, s)) if *last == change => s.push(c),
+        _ => changes.push((change, c.to_string())),
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < from.len() || j < to.len() {
+        if i < from.len() && j < to.len() && from[i] == to[j] {
+            push(Change::Equal, from[i]);
+            i += 1;
+            j += 1;
+        } else if j == to.len() || (i < from.len() && lengths[i + 1][j] >= lengths[i][j + 1]) {
+            push(Change::Delete, from[i]);
+            i += 1;
+        } else {
+            push(Change::Insert, to[j]);
+            j += 1;
+        }
+    }
+    changes
+}
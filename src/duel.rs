@@ -0,0 +1,396 @@
+// Two players answering the same questions against each other over TCP:
+//
+//   vocab-trainer duel DECK [--listen ADDRESS] [--count N]
+//   vocab-trainer duel --connect HOST:PORT
+//
+// The host picks and shuffles the questions and sends them to the guest up
+// front. Each player then answers at their own pace, and whoever answers a
+// question correctly in less time wins it. Giving up with :suspend loses the
+// question, and :quit ends the duel for both.
+//
+// Messages are JSON objects on single lines, with string values only:
+//
+//   {"type": "deck", "entries": "LINE\tALTERNATIVE...\n..."}   host to guest
+//   {"type": "answer", "index": "0", "correct": "true", "ms": "1520"}
+//                                                   guest to host, per question
+//   {"type": "result", "index": "0", "winner": "host", "host_ms": "1200",
+//    "guest_ms": "1520"}                            host to guest, per question
+//   {"type": "quit"}                                either way
+//
+// Both record the answers in their own scores, as in any other session.
+//
+// The host listens on the loopback address unless given another, such as
+// `--listen 0.0.0.0:7878` to duel over the network, where anyone who can
+// reach the port can join.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read as _, Write as _};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rustyline::error::ReadlineError;
+
+use crate::daemon::parse_request;
+use crate::events::{Event, EventBus};
+use crate::language::Language;
+use crate::notes::Notes;
+use crate::settings::Settings;
+use crate::stats::json_string;
+use crate::{
+    detect_config_directory, load_history, load_scores, load_session_deck, parse_line, Entry,
+    GameState, GameUI, Order, Question, SessionEnd, SoundTheme, UIResponse, EXIT_QUIT,
+};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+
+// The deck is sent in one message, which is the longest by far.
+const MAX_MESSAGE_BYTES: u64 = 4 * 1024 * 1024;
+
+// An opponent who has been silent for this long, even while answering, is
+// taken to have gone.
+const OPPONENT_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+const DEFAULT_COUNT: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Role {
+    Host,
+    Guest,
+}
+
+pub fn run(args: &[String]) -> io::Result<i32> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let mut deck_path = None;
+    let mut listen = DEFAULT_ADDRESS.to_owned();
+    let mut connect = None;
+    let mut count = DEFAULT_COUNT;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--listen" => listen = args.next().cloned().unwrap_or_default(),
+            "--connect" => connect = args.next().cloned(),
+            "--count" => {
+                let value = args.next().map(String::as_str).unwrap_or_default();
+                count = value
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid count: {}", value)))?;
+            }
+            _ if arg.starts_with('-') => return Err(invalid(format!("unknown option: {}", arg))),
+            _ => deck_path = Some(PathBuf::from(arg)),
+        }
+    }
+
+    let config_dir = detect_config_directory();
+    let score_path = config_dir.join("scores.txt");
+    let history_path = config_dir.join("history.txt");
+    let scores = load_scores(&score_path)?;
+    let history = load_history(&history_path)?;
+    let mut settings = Settings::load(config_dir.join("config.toml"))?;
+    let (role, stream, mut state) = match (&deck_path, connect) {
+        (Some(deck_path), None) => {
+            let entries = load_session_deck(deck_path, &mut settings)?;
+            let mut state = GameState::new(entries, scores, history, false);
+            state.arrange(Order::Random, Some(count));
+            let listener = TcpListener::bind(&listen)?;
+            println!("Waiting for an opponent on {}", listener.local_addr()?);
+            let (mut stream, address) = listener.accept()?;
+            stream.set_read_timeout(Some(OPPONENT_TIMEOUT))?;
+            println!("{} joined", address);
+            let entries = state.pending.iter().map(|&i| &state.deck[i]);
+            writeln!(
+                stream,
+                "{{\"type\": \"deck\", \"entries\": {}}}",
+                json_string(&encode_entries(entries))
+            )?;
+            (Role::Host, stream, state)
+        }
+        (None, Some(address)) => {
+            let stream = TcpStream::connect(&address)?;
+            stream.set_read_timeout(Some(OPPONENT_TIMEOUT))?;
+            let mut stream = BufReader::new(stream);
+            let message = receive(&mut stream)?;
+            let lines = message
+                .get("entries")
+                .filter(|_| message.get("type").map(String::as_str) == Some("deck"))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "expected a deck"))?;
+            let mut state = GameState::new(decode_entries(lines)?, scores, history, false);
+            // The guest gets every question the host picked, whatever its own
+            // scores say about them.
+            state.entries = (0..state.deck.len()).collect();
+            state.pending = state.entries.iter().copied().collect();
+            (Role::Guest, stream.into_inner(), state)
+        }
+        _ => {
+            return Err(invalid(
+                "usage: duel DECK [--listen ADDRESS] [--count N] | duel --connect HOST:PORT"
+                    .to_owned(),
+            ))
+        }
+    };
+
+    state.notes = Some(Notes::load(config_dir.join("notes.txt"))?);
+    let prompt = settings.get_str("ui", "prompt").unwrap_or("> ");
+    let mut ui = GameUI::new(
+        SoundTheme::from_settings(&settings),
+        prompt.to_owned(),
+        false,
+        true,
+    );
+    ui.bind_keys(&settings).map_err(invalid)?;
+    let language = Language::from_settings(&settings, &state.deck).map_err(invalid)?;
+    state.set_language(&language, &settings).map_err(invalid)?;
+    ui.rtl_terms = language.rtl;
+    let mut bus = EventBus::for_session(&settings, &config_dir)?;
+    let mut duel = Duel {
+        role,
+        writer: stream.try_clone()?,
+        reader: BufReader::new(stream),
+        wins: 0,
+        losses: 0,
+    };
+    let result = duel.play(&mut ui, &mut state, &mut bus);
+    state.save(&score_path, &history_path)?;
+    let quit = result?;
+    let end = if quit {
+        SessionEnd::Quit
+    } else {
+        SessionEnd::Completed
+    };
+    bus.dispatch(&Event::SessionEnded(end), &state, &mut ui);
+    println!(
+        "Duel: you {} - {} your opponent, {}",
+        duel.wins,
+        duel.losses,
+        match duel.wins.cmp(&duel.losses) {
+            _ if quit => "abandoned",
+            Ordering::Greater => "you won!",
+            Ordering::Less => "you lost",
+            Ordering::Equal => "a draw",
+        }
+    );
+    Ok(if quit { EXIT_QUIT } else { 0 })
+}
+
+struct Duel {
+    role: Role,
+    writer: TcpStream,
+    reader: BufReader<TcpStream>,
+    wins: usize,
+    losses: usize,
+}
+
+impl Duel {
+    // Returns whether either player quit.
+    fn play(
+        &mut self,
+        ui: &mut GameUI,
+        state: &mut GameState,
+        bus: &mut EventBus,
+    ) -> io::Result<bool> {
+        while let Some(question) = state.next_question() {
+            let answer = match ask(ui, state, bus, &question).map_err(io::Error::other)? {
+                Some(answer) => answer,
+                None => {
+                    writeln!(self.writer, "{{\"type\": \"quit\"}}")?;
+                    return Ok(true);
+                }
+            };
+            println!("Waiting for your opponent...");
+            let Some(result) = self.exchange(question.index, answer)? else {
+                println!("Your opponent quit");
+                return Ok(true);
+            };
+            match result {
+                Some(role) if role == self.role => self.wins += 1,
+                Some(_) => self.losses += 1,
+                None => {}
+            }
+            println!(
+                "{} (you {} - {} your opponent)",
+                match result {
+                    Some(role) if role == self.role => "You won this one",
+                    Some(_) => "Your opponent won this one",
+                    None => "Nobody got this one",
+                },
+                self.wins,
+                self.losses
+            );
+        }
+        Ok(false)
+    }
+
+    // Trades this player's answer for the winner of the question, or `None`
+    // if the opponent quit.
+    fn exchange(&mut self, index: usize, answer: Answer) -> io::Result<Option<Option<Role>>> {
+        match self.role {
+            Role::Host => {
+                let message = receive(&mut self.reader)?;
+                if message.get("type").map(String::as_str) != Some("answer") {
+                    return Ok(None);
+                }
+                let guest = Answer {
+                    correct: message.get("correct").map(String::as_str) == Some("true"),
+                    ms: message
+                        .get("ms")
+                        .and_then(|ms| ms.parse().ok())
+                        .unwrap_or(0),
+                };
+                let winner = match (answer.correct, guest.correct) {
+                    (true, true) if guest.ms < answer.ms => Some(Role::Guest),
+                    (true, _) => Some(Role::Host),
+                    (false, true) => Some(Role::Guest),
+                    (false, false) => None,
+                };
+                writeln!(
+                    self.writer,
+                    "{{\"type\": \"result\", \"index\": \"{}\", \"winner\": \"{}\", \
+                     \"host_ms\": \"{}\", \"guest_ms\": \"{}\"}}",
+                    index,
+                    match winner {
+                        Some(Role::Host) => "host",
+                        Some(Role::Guest) => "guest",
+                        None => "none",
+                    },
+                    answer.ms,
+                    guest.ms
+                )?;
+                Ok(Some(winner))
+            }
+            Role::Guest => {
+                writeln!(
+                    self.writer,
+                    "{{\"type\": \"answer\", \"index\": \"{}\", \"correct\": \"{}\", \"ms\": \"{}\"}}",
+                    index, answer.correct, answer.ms
+                )?;
+                let message = receive(&mut self.reader)?;
+                if message.get("type").map(String::as_str) != Some("result") {
+                    return Ok(None);
+                }
+                Ok(Some(match message.get("winner").map(String::as_str) {
+                    Some("host") => Some(Role::Host),
+                    Some("guest") => Some(Role::Guest),
+                    _ => None,
+                }))
+            }
+        }
+    }
+}
+
+// Entries are sent as deck lines followed by their alternative spellings,
+// separated by tabs.
+pub fn encode_entries<'a>(entries: impl Iterator<Item = &'a Entry>) -> String {
+    let lines: Vec<String> = entries
+        .map(|entry| {
+            let mut line = entry.to_string();
+            for alternative in &entry.alternatives {
+                line.push('\t');
+                line.push_str(alternative);
+            }
+            line
+        })
+        .collect();
+    lines.join("\n")
+}
+
+pub fn decode_entries(lines: &str) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for (n, line) in lines.lines().enumerate() {
+        let mut fields = line.split('\t');
+        if let Some(mut entry) = parse_line(fields.next().unwrap_or_default(), n)? {
+            entry.alternatives.extend(fields.map(str::to_owned));
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Answer {
+    pub correct: bool,
+    pub ms: u64,
+}
+
+// Asks the question until it is answered or given up, or returns `None` if the
+// player quit.
+pub fn ask(
+    ui: &mut GameUI,
+    state: &mut GameState,
+    bus: &mut EventBus,
+    question: &Question,
+) -> Result<Option<Answer>, ReadlineError> {
+    bus.dispatch(&Event::QuestionShown(question), state, ui);
+    loop {
+        if ui.is_resized() {
+            ui.redraw(question, state);
+        }
+        match ui.wait_for_input(state.question_hint(question))? {
+            UIResponse::Return(input) => {
+                let ms = state.question_started.elapsed().as_millis() as u64;
+                let correct = state.answer_question(question, &input);
+                let event = Event::AnswerGraded {
+                    question,
+                    answer: &input,
+                    correct,
+                };
+                bus.dispatch(&event, state, ui);
+                if correct {
+                    return Ok(Some(Answer { correct: true, ms }));
+                }
+            }
+            UIResponse::Redraw => ui.redraw(question, state),
+            UIResponse::Note(text) => match state.set_note(question, &text) {
+                Ok(()) => ui.notify_note(question, state),
+                Err(error) => eprintln!("save note: {}", error),
+            },
+            UIResponse::Star => {
+                state.toggle_star(question);
+                ui.notify_starred(question, state);
+            }
+            UIResponse::Stop => ui.stop_audio(),
+            UIResponse::Hint => {
+                let taken = state.take_hint(question);
+                ui.notify_hint(question, state, taken);
+            }
+            // Duels are raced on typed answers, with no `--reveal`.
+            UIResponse::Reveal => {}
+            UIResponse::Suspend => {
+                println!("{}", state.entry(question).term);
+                return Ok(Some(Answer {
+                    correct: false,
+                    ms: state.question_started.elapsed().as_millis() as u64,
+                }));
+            }
+            // Both players are asked the same questions in the same order.
+            UIResponse::Skip | UIResponse::Undo => {}
+            UIResponse::Quit => return Ok(None),
+        }
+    }
+}
+
+pub fn receive<R: BufRead>(reader: &mut R) -> io::Result<HashMap<String, String>> {
+    let mut line = String::new();
+    let read = reader
+        .by_ref()
+        .take(MAX_MESSAGE_BYTES)
+        .read_line(&mut line)
+        .map_err(|error| match error.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+                io::Error::new(io::ErrorKind::TimedOut, "the other end stopped responding")
+            }
+            _ => error,
+        })?;
+    if read == 0 {
+        // The other end hung up, which is as good as quitting.
+        return Ok(HashMap::from([("type".to_owned(), "quit".to_owned())]));
+    }
+    if read as u64 == MAX_MESSAGE_BYTES && !line.ends_with('\n') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message too long",
+        ));
+    }
+    parse_request(line.trim())
+        .map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message))
+}
@@ -0,0 +1,288 @@
+// What happens in a session, told by `run_loop` to the observers registered
+// on the bus, so that the loop itself only asks the questions. The UI is told
+// first, so that whatever the others print comes after it.
+//
+// Hooks are shell commands run on each event, set in:
+//
+//   [hooks]
+//   question_shown = "notify-send vocab-trainer \"$VOCAB_TERM\""
+//   answer_graded = "log-answer --append"
+//   self_graded = "log-answer --append"
+//   session_ended = "sync-scores"
+//
+// They get the event in environment variables: VOCAB_EVENT always,
+// VOCAB_TERM with the questions, VOCAB_ANSWER, VOCAB_CORRECT ("1" or "0")
+// and VOCAB_MISTAKES with graded answers, VOCAB_REMEMBERED with answers
+// graded by the player, and VOCAB_ANSWERED, VOCAB_PERFECT and VOCAB_QUIT at
+// the end. Answers of batch sessions are graded at the end and have no events
+// of their own. Hooks run alongside the session, which doesn't wait for them
+// to finish.
+//
+// Builds with the net feature also post the events to the URLs set in
+// `[webhooks]` the same way, as JSON objects with string values named like
+// the variables:
+//
+//   {"event": "answer_graded", "term": "receive", "answer": "recieve",
+//    "correct": "0", "mistakes": "1"}
+//
+// The terminal belongs to the session, so hooks write their errors, and the
+// failures of hooks and webhooks, to hooks.log in the configuration
+// directory.
+
+use std::fs::OpenOptions;
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::achievements::Unlocker;
+use crate::settings::Settings;
+#[cfg(feature = "net")]
+use crate::stats::json_string;
+use crate::{prune, GameState, GameUI, Question, SessionEnd};
+
+pub enum Event<'a> {
+    QuestionShown(&'a Question),
+    AnswerGraded {
+        question: &'a Question,
+        answer: &'a str,
+        correct: bool,
+    },
+    // The answer was shown with `--reveal`, and the player said whether they
+    // knew it.
+    SelfGraded {
+        question: &'a Question,
+        remembered: bool,
+    },
+    SessionEnded(SessionEnd),
+}
+
+impl Event<'_> {
+    fn name(&self) -> &'static str {
+        match self {
+            Event::QuestionShown(_) => "question_shown",
+            Event::AnswerGraded { .. } => "answer_graded",
+            Event::SelfGraded { .. } => "self_graded",
+            Event::SessionEnded(_) => "session_ended",
+        }
+    }
+}
+
+// Observers may print after the UI, and report their own errors, as a failing
+// one shouldn't end the session.
+pub trait Observer {
+    fn notify(&mut self, event: &Event, state: &GameState, ui: &mut GameUI);
+}
+
+#[derive(Default)]
+pub struct EventBus {
+    observers: Vec<Box<dyn Observer>>,
+}
+
+impl EventBus {
+    // The observers of every kind of session: the hooks, the webhooks and the
+    // achievements.
+    pub fn for_session(settings: &Settings, config_dir: &Path) -> io::Result<Self> {
+        let mut bus = Self::default();
+        let log_path = config_dir.join("hooks.log");
+        if let Some(commands) = PerEvent::from_settings(settings, "hooks") {
+            bus.register(Hooks {
+                commands,
+                log_path: log_path.clone(),
+            });
+        }
+        #[cfg(feature = "net")]
+        if let Some(urls) = PerEvent::from_settings(settings, "webhooks") {
+            bus.register(Webhooks {
+                urls,
+                log_path,
+                sending: Vec::new(),
+            });
+        }
+        #[cfg(not(feature = "net"))]
+        if settings.has_section("webhooks") {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "webhooks require building with the net feature",
+            ));
+        }
+        bus.register(Unlocker::new(
+            config_dir.join("achievements.txt"),
+            prune::load_compacted_days(config_dir)?,
+        ));
+        Ok(bus)
+    }
+
+    pub fn register(&mut self, observer: impl Observer + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
+    pub fn dispatch(&mut self, event: &Event, state: &GameState, ui: &mut GameUI) {
+        ui.observe(event, state);
+        for observer in &mut self.observers {
+            observer.notify(event, state, ui);
+        }
+    }
+}
+
+// What to run or where to post for each kind of event, set in a section.
+struct PerEvent {
+    question_shown: Option<String>,
+    answer_graded: Option<String>,
+    self_graded: Option<String>,
+    session_ended: Option<String>,
+}
+
+impl PerEvent {
+    fn from_settings(settings: &Settings, section: &str) -> Option<Self> {
+        if !settings.has_section(section) {
+            return None;
+        }
+        let value = |key| settings.get_str(section, key).map(str::to_owned);
+        Some(Self {
+            question_shown: value("question_shown"),
+            answer_graded: value("answer_graded"),
+            self_graded: value("self_graded"),
+            session_ended: value("session_ended"),
+        })
+    }
+
+    fn get(&self, event: &Event) -> Option<&str> {
+        match event {
+            Event::QuestionShown(_) => &self.question_shown,
+            Event::AnswerGraded { .. } => &self.answer_graded,
+            Event::SelfGraded { .. } => &self.self_graded,
+            Event::SessionEnded(_) => &self.session_ended,
+        }
+        .as_deref()
+    }
+}
+
+// The details of the event, named as the variables hooks get them in.
+fn fields(event: &Event, state: &GameState) -> Vec<(&'static str, String)> {
+    let flag = |value: bool| if value { "1" } else { "0" }.to_owned();
+    match event {
+        Event::QuestionShown(question) => {
+            vec![("term", state.entry(question).term.to_string())]
+        }
+        Event::AnswerGraded {
+            question,
+            answer,
+            correct,
+        } => vec![
+            ("term", state.entry(question).term.to_string()),
+            ("answer", answer.to_string()),
+            ("correct", flag(*correct)),
+            ("mistakes", state.graded_mistakes().to_string()),
+        ],
+        Event::SelfGraded {
+            question,
+            remembered,
+        } => vec![
+            ("term", state.entry(question).term.to_string()),
+            ("remembered", flag(*remembered)),
+        ],
+        Event::SessionEnded(end) => vec![
+            ("answered", state.new_attempts.len().to_string()),
+            ("perfect", state.perfect_count().to_string()),
+            ("quit", flag(*end == SessionEnd::Quit)),
+        ],
+    }
+}
+
+fn log(path: &Path, message: &str) {
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        writeln!(file, "{}", message).ok();
+    }
+}
+
+struct Hooks {
+    commands: PerEvent,
+    log_path: PathBuf,
+}
+
+impl Observer for Hooks {
+    fn notify(&mut self, event: &Event, state: &GameState, _ui: &mut GameUI) {
+        let Some(hook) = self
+            .commands
+            .get(event)
+            .filter(|hook| !hook.trim().is_empty())
+        else {
+            return;
+        };
+        let stderr = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)
+            .map_or_else(|_| Stdio::null(), Stdio::from);
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(hook)
+            .env("VOCAB_EVENT", event.name())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(stderr);
+        for (name, value) in fields(event, state) {
+            command.env(format!("VOCAB_{}", name.to_uppercase()), value);
+        }
+        let name = event.name();
+        let log_path = self.log_path.clone();
+        match command.spawn() {
+            Ok(mut child) => {
+                thread::spawn(move || match child.wait() {
+                    Ok(status) if !status.success() => {
+                        log(&log_path, &format!("hook {}: exited with {}", name, status))
+                    }
+                    Ok(_) => {}
+                    Err(error) => log(&log_path, &format!("hook {}: {}", name, error)),
+                });
+            }
+            Err(error) => log(&log_path, &format!("hook {}: {}", name, error)),
+        }
+    }
+}
+
+// A webhook that doesn't answer in this long is given up on, which is also as
+// long as the end of a session can wait for the last ones.
+#[cfg(feature = "net")]
+const WEBHOOK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[cfg(feature = "net")]
+struct Webhooks {
+    urls: PerEvent,
+    log_path: PathBuf,
+    sending: Vec<thread::JoinHandle<()>>,
+}
+
+#[cfg(feature = "net")]
+impl Observer for Webhooks {
+    fn notify(&mut self, event: &Event, state: &GameState, _ui: &mut GameUI) {
+        self.sending.retain(|handle| !handle.is_finished());
+        if let Some(url) = self.urls.get(event).filter(|url| !url.trim().is_empty()) {
+            let mut body = format!("{{\"event\": {}", json_string(event.name()));
+            for (name, value) in fields(event, state) {
+                body.push_str(&format!(", {}: {}", json_string(name), json_string(&value)));
+            }
+            body.push('}');
+            let name = event.name();
+            let url = url.to_owned();
+            let log_path = self.log_path.clone();
+            self.sending.push(thread::spawn(move || {
+                let result = ureq::post(&url)
+                    .timeout(WEBHOOK_TIMEOUT)
+                    .set("Content-Type", "application/json")
+                    .send_string(&body);
+                if let Err(error) = result {
+                    log(&log_path, &format!("webhook {}: {}", name, error));
+                }
+            }));
+        }
+        // The threads would be cut short when the program exits.
+        if let Event::SessionEnded(_) = event {
+            for handle in self.sending.drain(..) {
+                handle.join().ok();
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::fmt::Write as _;
+
+use crate::Entry;
+
+const CARD_COLUMNS: usize = 3;
+
+const CARD_ROWS: usize = 4;
+
+// Lays out the entries as a sheet of fronts followed by a sheet of backs.
+// Every row of backs is mirrored so that the sheets line up when printed
+// double-sided and flipped on the long edge.
+pub fn render_cards(entries: &[Entry]) -> String {
+    let mut output = String::from("# Flashcards\n");
+    let pages = entries.chunks(CARD_COLUMNS * CARD_ROWS);
+    let page_count = pages.len();
+    for (i, page) in pages.enumerate() {
+        let rows: Vec<&[Entry]> = page.chunks(CARD_COLUMNS).collect();
+
+        write!(output, "\n## Page {} (front)\n\n", i + 1).unwrap();
+        write_table_header(&mut output);
+        for row in &rows {
+            let cells: Vec<String> = (0..CARD_COLUMNS)
+                .map(|j| row.get(j).map(front).unwrap_or_default())
+                .collect();
+            write_table_row(&mut output, &cells);
+        }
+        write_page_break(&mut output);
+
+        write!(output, "\n## Page {} (back)\n\n", i + 1).unwrap();
+        write_table_header(&mut output);
+        for row in &rows {
+            let cells: Vec<String> = (0..CARD_COLUMNS)
+                .rev()
+                .map(|j| row.get(j).map(back).unwrap_or_default())
+                .collect();
+            write_table_row(&mut output, &cells);
+        }
+        if i + 1 < page_count {
+            write_page_break(&mut output);
+        }
+    }
+    output
+}
+
+fn front(entry: &Entry) -> String {
+    format!("**{}**", escape(&entry.term))
+}
+
+fn back(entry: &Entry) -> String {
+    entry
+        .phrases
+        .iter()
+        .map(|phrase| {
+            if phrase.comment.is_empty() {
+                escape(&phrase.body)
+            } else {
+                format!("{} *({})*", escape(&phrase.body), escape(&phrase.comment))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("<br>")
+}
+
+fn write_table_header(output: &mut String) {
+    output.push('|');
+    for _ in 0..CARD_COLUMNS {
+        output.push_str("   |");
+    }
+    output.push_str("\n|");
+    for _ in 0..CARD_COLUMNS {
+        output.push_str(":---:|");
+    }
+    output.push('\n');
+}
+
+fn write_table_row(output: &mut String, cells: &[String]) {
+    output.push('|');
+    for cell in cells {
+        write!(output, " {} |", cell).unwrap();
+    }
+    output.push('\n');
+}
+
+fn write_page_break(output: &mut String) {
+    output.push_str("\n<div style=\"page-break-after: always\"></div>\n");
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '|' | '*' | '_' | '\\' | '<' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,115 @@
+// Conditions selecting deck entries, as given to `tag --where`:
+//
+//   term~REGEX      the term matches the regular expression
+//   tag=NAME        the entry has the tag (tag!=NAME for the opposite)
+//   FIELD OP VALUE  a score compares to the value, where FIELD is one of
+//                   correct, incorrect, reviews, rate (percent correct),
+//                   lapses, interval (days) or ease, and OP one of <, <=, >,
+//                   >=, = and !=
+//
+// Entries that were never reviewed have zero scores and a rate of 100%.
+
+use regex::Regex;
+
+use crate::{Entry, Score};
+
+#[derive(Debug)]
+pub enum Condition {
+    Term(Regex),
+    Tag(String, bool),
+    Score(Field, Comparison, f64),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Field {
+    Correct,
+    Incorrect,
+    Reviews,
+    Rate,
+    Lapses,
+    Interval,
+    Ease,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Condition {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        if let Some(pattern) = s.strip_prefix("term~") {
+            return Regex::new(pattern)
+                .map(Condition::Term)
+                .map_err(|error| format!("invalid pattern {:?}: {}", pattern, error));
+        }
+        if let Some(tag) = s.strip_prefix("tag!=") {
+            return Ok(Condition::Tag(tag.to_owned(), false));
+        }
+        if let Some(tag) = s.strip_prefix("tag=") {
+            return Ok(Condition::Tag(tag.to_owned(), true));
+        }
+        let at = s
+            .find(['<', '>', '=', '!'])
+            .ok_or_else(|| format!("invalid condition: {}", s))?;
+        let (name, rest) = s.split_at(at);
+        let field = match name.trim() {
+            "correct" => Field::Correct,
+            "incorrect" => Field::Incorrect,
+            "reviews" => Field::Reviews,
+            "rate" => Field::Rate,
+            "lapses" => Field::Lapses,
+            "interval" => Field::Interval,
+            "ease" => Field::Ease,
+            name => return Err(format!("unknown field: {}", name)),
+        };
+        let (comparison, value) = [
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("!=", Comparison::NotEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("=", Comparison::Equal),
+        ]
+        .into_iter()
+        .find_map(|(op, comparison)| rest.strip_prefix(op).map(|value| (comparison, value)))
+        .ok_or_else(|| format!("invalid condition: {}", s))?;
+        let value = value
+            .trim()
+            .trim_end_matches('%')
+            .parse()
+            .map_err(|_| format!("invalid value in condition: {}", s))?;
+        Ok(Condition::Score(field, comparison, value))
+    }
+
+    pub fn matches(&self, entry: &Entry, score: &Score) -> bool {
+        match self {
+            Condition::Term(pattern) => pattern.is_match(&entry.term),
+            Condition::Tag(tag, present) => entry.tags.iter().any(|t| **t == **tag) == *present,
+            Condition::Score(field, comparison, value) => {
+                let actual = match field {
+                    Field::Correct => score.correct as f64,
+                    Field::Incorrect => score.incorrect as f64,
+                    Field::Reviews => score.total_tries() as f64,
+                    Field::Rate => score.correct_rate() as f64 * 100.0,
+                    Field::Lapses => score.schedule.lapses as f64,
+                    Field::Interval => score.schedule.interval,
+                    Field::Ease => score.schedule.ease,
+                };
+                match comparison {
+                    Comparison::Less => actual < *value,
+                    Comparison::LessOrEqual => actual <= *value,
+                    Comparison::Greater => actual > *value,
+                    Comparison::GreaterOrEqual => actual >= *value,
+                    Comparison::Equal => actual == *value,
+                    Comparison::NotEqual => actual != *value,
+                }
+            }
+        }
+    }
+}
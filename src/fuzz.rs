@@ -0,0 +1,182 @@
+// Checks properties of the deck parser against random lines, run with `cargo
+// run --features fuzz -- fuzz [ITERATIONS] [--seed N]`. Lines can also be fed
+// on the standard input with `fuzz -`, for example from an external fuzzer's
+// corpus.
+//
+// The properties are that formatting loses nothing, so that a parsed entry
+// formats to a line parsing back into the same entry, and that it is a fixed
+// point, so that the line formats the same way again. The tests check them
+// with a fixed seed, and on the lines most likely to break them.
+//
+// The crate is a binary alone, with no library an external fuzzer's target
+// could link, which is why the fuzzer is a command of its own.
+
+use std::io::{self, BufRead as _};
+
+use crate::{parse_line, unix_time, Entry, Xorshift};
+
+// Weighted towards the characters the grammar gives a meaning to.
+const ALPHABET: &[char] = &[
+    ' ', ' ', ' ', '/', '/', '/', ';', ';', '#', '@', '~', '=', '\t', 'a', 'b', 'i', 'd', 'é', 'ש',
+    '\u{301}', '*', '(',
+];
+
+const MAX_LENGTH: u64 = 40;
+
+pub fn run(args: &[String]) -> io::Result<()> {
+    let mut iterations = 100_000;
+    let mut seed = None;
+    let mut from_stdin = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-" => from_stdin = true,
+            "--seed" => seed = args.next().and_then(|seed| seed.parse().ok()),
+            _ => {
+                iterations = arg.parse().map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("invalid iterations: {}", arg),
+                    )
+                })?
+            }
+        }
+    }
+
+    if from_stdin {
+        for line in io::stdin().lock().lines() {
+            let line = line?;
+            if let Err(message) = check(&line) {
+                return Err(counterexample(&line, &message));
+            }
+        }
+        return Ok(());
+    }
+
+    let seed = seed.unwrap_or_else(unix_time);
+    println!("seed: {}", seed);
+    let mut random = Xorshift::new(seed);
+    for _ in 0..iterations {
+        let length = random.next() % (MAX_LENGTH + 1);
+        let line: String = (0..length)
+            .map(|_| ALPHABET[(random.next() % ALPHABET.len() as u64) as usize])
+            .collect();
+        if check(&line).is_err() {
+            let line = shrink(line);
+            let message = check(&line).unwrap_err();
+            return Err(counterexample(&line, &message));
+        }
+    }
+    println!("{} lines checked", iterations);
+    Ok(())
+}
+
+fn check(line: &str) -> Result<(), String> {
+    // Lines that fail to parse, such as invalid patterns, are fine.
+    let entry = match parse_line(line, 0) {
+        Ok(Some(entry)) => entry,
+        Ok(None) | Err(_) => return Ok(()),
+    };
+    let first = entry.to_string();
+    let reparsed = match parse_line(&first, 0) {
+        Ok(Some(entry)) => entry,
+        Ok(None) => return Err(format!("{:?} no longer parses", first)),
+        Err(error) => return Err(format!("{:?} no longer parses: {}", first, error)),
+    };
+    if let Some(field) = changed_field(&entry, &reparsed) {
+        return Err(format!("{:?} parses with another {}", first, field));
+    }
+    let second = reparsed.to_string();
+    if first == second {
+        Ok(())
+    } else {
+        Err(format!("{:?} formats as {:?}", first, second))
+    }
+}
+
+fn changed_field(a: &Entry, b: &Entry) -> Option<&'static str> {
+    let phrases = |entry: &Entry| -> Vec<(String, String)> {
+        entry
+            .phrases
+            .iter()
+            .map(|phrase| (phrase.body.clone(), phrase.comment.to_string()))
+            .collect()
+    };
+    if a.term != b.term {
+        Some("term")
+    } else if phrases(a) != phrases(b) {
+        Some("phrases")
+    } else if a.tags != b.tags {
+        Some("tags")
+    } else if a.id != b.id {
+        Some("id")
+    } else if a.alternatives != b.alternatives {
+        Some("alternatives")
+    } else if a.pattern.as_ref().map(|pattern| pattern.as_str())
+        != b.pattern.as_ref().map(|pattern| pattern.as_str())
+    {
+        Some("pattern")
+    } else if a.overrides != b.overrides {
+        Some("overrides")
+    } else {
+        None
+    }
+}
+
+// Removes one character at a time for as long as the line keeps failing.
+fn shrink(mut line: String) -> String {
+    'outer: loop {
+        for (i, c) in line.char_indices() {
+            let mut shorter = line.clone();
+            shorter.replace_range(i..i + c.len_utf8(), "");
+            if check(&shorter).is_err() {
+                line = shorter;
+                continue 'outer;
+            }
+        }
+        return line;
+    }
+}
+
+fn counterexample(line: &str, message: &str) -> io::Error {
+    io::Error::other(format!("counterexample {:?}: {}", line, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_lines_format_to_a_fixed_point() {
+        let args = ["20000", "--seed", "1"].map(String::from);
+        if let Err(error) = run(&args) {
+            panic!("{}", error);
+        }
+    }
+
+    #[test]
+    fn edge_cases_format_to_a_fixed_point() {
+        for line in [
+            "term",
+            "term /",
+            "term //",
+            "term /meaning/",
+            "term /meaning",
+            "term/meaning/",
+            "term /a/b/ #tag @id",
+            "term /a;b/ ease=1.8 interval-cap=30d",
+            "term /a/ ease=",
+            "# a comment",
+            "  # indented /comment/",
+            "term /a # not a tag/",
+            " /meaning/",
+            "term\t/meaning/",
+            "\u{5e9}\u{5dc}\u{5d5}\u{5dd} /peace/",
+            "cafe\u{301} /coffee/",
+        ] {
+            if let Err(message) = check(line) {
+                panic!("{:?}: {}", line, message);
+            }
+        }
+    }
+}
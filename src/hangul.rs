@@ -0,0 +1,198 @@
+// Composition of Hangul jamo into syllable blocks, so that Korean terms can be
+// answered without an IME.
+
+const SYLLABLE_BASE: u32 = 0xAC00;
+
+const SYLLABLE_LAST: u32 = 0xD7A3;
+
+const LEADS: &str = "ㄱㄲㄴㄷㄸㄹㅁㅂㅃㅅㅆㅇㅈㅉㅊㅋㅌㅍㅎ";
+
+const VOWELS: &str = "ㅏㅐㅑㅒㅓㅔㅕㅖㅗㅘㅙㅚㅛㅜㅝㅞㅟㅠㅡㅢㅣ";
+
+const TAILS: &str = "ㄱㄲㄳㄴㄵㄶㄷㄹㄺㄻㄼㄽㄾㄿㅀㅁㅂㅄㅅㅆㅇㅈㅊㅋㅌㅍㅎ";
+
+const LEAD_ROMAN: [&str; 19] = [
+    "g", "kk", "n", "d", "tt", "r", "m", "b", "pp", "s", "ss", "", "j", "jj", "ch", "k", "t", "p",
+    "h",
+];
+
+const VOWEL_ROMAN: [&str; 21] = [
+    "a", "ae", "ya", "yae", "eo", "e", "yeo", "ye", "o", "wa", "wae", "oe", "yo", "u", "wo", "we",
+    "wi", "yu", "eu", "ui", "i",
+];
+
+// Indexed by the tail plus one, zero being a syllable without a tail.
+const TAIL_ROMAN: [&str; 28] = [
+    "", "k", "k", "k", "n", "n", "n", "t", "l", "k", "m", "l", "l", "l", "p", "l", "m", "p", "p",
+    "t", "t", "ng", "t", "t", "k", "t", "p", "t",
+];
+
+fn index_of(table: &str, c: char) -> Option<u32> {
+    table.chars().position(|other| other == c).map(|i| i as u32)
+}
+
+fn nth(table: &str, i: u32) -> Option<char> {
+    table.chars().nth(i as usize)
+}
+
+// Conjoining jamo are mapped to their compatibility forms, which is what
+// keyboards without an IME produce.
+fn to_compatibility(c: char) -> char {
+    let code = c as u32;
+    let mapped = match code {
+        0x1100..=0x1112 => nth(LEADS, code - 0x1100),
+        0x1161..=0x1175 => nth(VOWELS, code - 0x1161),
+        0x11A8..=0x11C2 => nth(TAILS, code - 0x11A8),
+        _ => None,
+    };
+    mapped.unwrap_or(c)
+}
+
+fn is_vowel(c: Option<&char>) -> bool {
+    c.is_some_and(|&c| index_of(VOWELS, c).is_some())
+}
+
+fn combine_vowels(first: char, second: char) -> Option<char> {
+    match (first, second) {
+        ('ㅗ', 'ㅏ') => Some('ㅘ'),
+        ('ㅗ', 'ㅐ') => Some('ㅙ'),
+        ('ㅗ', 'ㅣ') => Some('ㅚ'),
+        ('ㅜ', 'ㅓ') => Some('ㅝ'),
+        ('ㅜ', 'ㅔ') => Some('ㅞ'),
+        ('ㅜ', 'ㅣ') => Some('ㅟ'),
+        ('ㅡ', 'ㅣ') => Some('ㅢ'),
+        _ => None,
+    }
+}
+
+fn combine_tails(first: char, second: char) -> Option<char> {
+    match (first, second) {
+        ('ㄱ', 'ㅅ') => Some('ㄳ'),
+        ('ㄴ', 'ㅈ') => Some('ㄵ'),
+        ('ㄴ', 'ㅎ') => Some('ㄶ'),
+        ('ㄹ', 'ㄱ') => Some('ㄺ'),
+        ('ㄹ', 'ㅁ') => Some('ㄻ'),
+        ('ㄹ', 'ㅂ') => Some('ㄼ'),
+        ('ㄹ', 'ㅅ') => Some('ㄽ'),
+        ('ㄹ', 'ㅌ') => Some('ㄾ'),
+        ('ㄹ', 'ㅍ') => Some('ㄿ'),
+        ('ㄹ', 'ㅎ') => Some('ㅀ'),
+        ('ㅂ', 'ㅅ') => Some('ㅄ'),
+        _ => None,
+    }
+}
+
+// A consonant followed by a vowel starts a new syllable, so it is only taken
+// as the tail of the current one otherwise.
+pub fn compose(input: &str) -> String {
+    let jamo: Vec<char> = input.chars().map(to_compatibility).collect();
+    let mut output = String::with_capacity(input.len());
+    let mut i = 0;
+    while i < jamo.len() {
+        let lead = index_of(LEADS, jamo[i]);
+        let (lead, mut vowel) = match (lead, jamo.get(i + 1)) {
+            (Some(lead), Some(&vowel)) if index_of(VOWELS, vowel).is_some() => (lead, vowel),
+            _ => {
+                output.push(jamo[i]);
+                i += 1;
+                continue;
+            }
+        };
+        let mut j = i + 2;
+        if let Some(combined) = jamo.get(j).and_then(|&c| combine_vowels(vowel, c)) {
+            vowel = combined;
+            j += 1;
+        }
+        let mut tail = None;
+        if let Some(&c) = jamo.get(j) {
+            if index_of(TAILS, c).is_some() && !is_vowel(jamo.get(j + 1)) {
+                tail = Some(c);
+                j += 1;
+                if let Some(combined) = jamo.get(j).and_then(|&next| combine_tails(c, next)) {
+                    if !is_vowel(jamo.get(j + 1)) {
+                        tail = Some(combined);
+                        j += 1;
+                    }
+                }
+            }
+        }
+        let vowel = index_of(VOWELS, vowel).unwrap_or(0);
+        let tail = tail.and_then(|c| index_of(TAILS, c)).map_or(0, |t| t + 1);
+        let code = SYLLABLE_BASE + (lead * 21 + vowel) * 28 + tail;
+        output.extend(char::from_u32(code));
+        i = j;
+    }
+    output
+}
+
+pub fn contains_hangul(text: &str) -> bool {
+    text.chars()
+        .any(|c| (SYLLABLE_BASE..=SYLLABLE_LAST).contains(&(c as u32)))
+}
+
+fn split_tail(tail: char) -> Option<(char, char)> {
+    LEADS
+        .chars()
+        .flat_map(|first| LEADS.chars().map(move |second| (first, second)))
+        .find(|&(first, second)| combine_tails(first, second) == Some(tail))
+}
+
+// Transliterates syllables with the Revised Romanization. Of the sound
+// changes between syllables, only liaison and "ll" are applied: a tail
+// followed by a silent ㅇ is read as the lead of the next syllable, and ㄹ
+// followed by ㄹ or ㄴ is written "ll".
+pub fn romanize(text: &str) -> String {
+    let mut output = String::with_capacity(text.len() * 3);
+    let mut carried_lead = None;
+    let mut previous_tail = None;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let code = c as u32;
+        if !(SYLLABLE_BASE..=SYLLABLE_LAST).contains(&code) {
+            output.push(c);
+            previous_tail = None;
+            continue;
+        }
+        let index = code - SYLLABLE_BASE;
+        let lead = index / (21 * 28);
+        let vowel = index % (21 * 28) / 28;
+        let tail = (index % 28).checked_sub(1).and_then(|t| nth(TAILS, t));
+
+        match carried_lead.take() {
+            // ㄹ turns a following ㄹ or ㄴ into "l".
+            None if (lead == 2 || lead == 5) && previous_tail == Some('ㄹ') => output.push('l'),
+            // ㅎ falls silent before a vowel.
+            Some('ㅎ') => {}
+            Some(carried) => {
+                let carried = index_of(LEADS, carried).unwrap_or(lead);
+                output.push_str(LEAD_ROMAN[carried as usize]);
+            }
+            None => output.push_str(LEAD_ROMAN[lead as usize]),
+        }
+        output.push_str(VOWEL_ROMAN[vowel as usize]);
+
+        let next_is_silent = chars.peek().is_some_and(|&next| {
+            let next = next as u32;
+            (SYLLABLE_BASE..=SYLLABLE_LAST).contains(&next)
+                && (next - SYLLABLE_BASE) / (21 * 28) == 11
+        });
+        previous_tail = tail;
+        let tail = match tail {
+            Some(tail) if next_is_silent && tail != 'ㅇ' => match split_tail(tail) {
+                Some((first, second)) => {
+                    carried_lead = Some(second);
+                    Some(first)
+                }
+                None => {
+                    carried_lead = Some(tail);
+                    None
+                }
+            },
+            tail => tail,
+        };
+        if let Some(t) = tail.and_then(|tail| index_of(TAILS, tail)) {
+            output.push_str(TAIL_ROMAN[t as usize + 1]);
+        }
+    }
+    output
+}
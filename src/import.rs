@@ -0,0 +1,515 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::Path;
+
+use crate::daemon::{parse_string, skip_whitespace};
+use crate::scheduler::{Schedule, INITIAL_EASE, MINIMUM_EASE, SECS_PER_DAY};
+use crate::sqlite::{Database, Row};
+use crate::{intern, Attempt, Entry, Phrase, Score};
+
+// Parses RFC 4180 CSV: fields may be quoted, and quoted fields may contain
+// commas, doubled quotes and line breaks.
+pub fn parse_csv(source: &str) -> io::Result<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+        } else {
+            match c {
+                '"' => in_quotes = true,
+                ',' => row.push(std::mem::take(&mut field)),
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' | '\r' => {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                _ => field.push(c),
+            }
+        }
+    }
+    if in_quotes {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unterminated quoted field",
+        ));
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+// Memrise course exports have the columns word, definition, part of speech
+// and mems. The definition becomes the phrase, and the part of speech and mems
+// go into its comment.
+pub fn import_memrise(source: &str) -> io::Result<Vec<Entry>> {
+    let mut rows = parse_csv(source)?.into_iter().peekable();
+    if rows
+        .peek()
+        .and_then(|row| row.first())
+        .is_some_and(|field| field.trim().eq_ignore_ascii_case("word"))
+    {
+        rows.next();
+    }
+    let mut entries = Vec::new();
+    for row in rows {
+        let mut fields = row.iter().map(|field| sanitize(field));
+        let term = fields.next().unwrap_or_default();
+        let body = fields.next().unwrap_or_default();
+        if term.is_empty() || body.is_empty() {
+            continue;
+        }
+        let comment = fields
+            .filter(|field| !field.is_empty())
+            .collect::<Vec<_>>()
+            .join(" - ");
+        let comment = intern(&comment);
+        entries.push(Entry::new(term, vec![Phrase { body, comment }]));
+    }
+    Ok(entries)
+}
+
+pub struct AnkiNote {
+    pub entry: Entry,
+    // The schedule and the reviews of the note's first card in the deck,
+    // unless it is still new.
+    pub review: Option<(Score, Vec<Attempt>)>,
+}
+
+// Notes of an Anki deck and its subdecks, read from the collection. The first
+// of `fields`, counted from 1, becomes the term, the second the phrase, and
+// the others with text in them go into its comment. Notes whose term was
+// already taken by another are left out.
+pub fn import_anki(path: &Path, deck: &str, fields: (usize, usize)) -> io::Result<Vec<AnkiNote>> {
+    let database = Database::open(path)?;
+    let table = |name: &str| {
+        database.table(name)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not an Anki collection", path.display()),
+            )
+        })
+    };
+    let collection = table("col")?;
+    let Some(collection) = collection.rows().next() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not an Anki collection", path.display()),
+        ));
+    };
+    let created = collection.get("crt").as_i64().unwrap_or_default();
+    // Newer collections have a table of decks, with the parts of their names
+    // separated by \x1f, and older ones a JSON object in the collection.
+    let decks: Vec<(i64, String)> = match database.table("decks")? {
+        Some(decks) => decks
+            .rows()
+            .map(|row| {
+                let name = row
+                    .get("name")
+                    .as_str()
+                    .unwrap_or_default()
+                    .replace('\x1f', "::");
+                (row.get("id").as_i64().unwrap_or_default(), name)
+            })
+            .collect(),
+        None => legacy_decks(collection.get("decks").as_str().unwrap_or_default())
+            .map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message))?,
+    };
+    let deck_ids: HashSet<i64> = decks
+        .iter()
+        .filter(|(_, name)| {
+            name == deck
+                || name
+                    .strip_prefix(deck)
+                    .is_some_and(|rest| rest.starts_with("::"))
+        })
+        .map(|(id, _)| *id)
+        .collect();
+    if deck_ids.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no deck named {} in the collection", deck),
+        ));
+    }
+
+    let cards = table("cards")?;
+    let mut first_cards: HashMap<i64, Row> = HashMap::new();
+    for card in cards.rows() {
+        // Cards moved to a filtered deck remember the deck they came from.
+        let deck_id = match card.get("odid").as_i64() {
+            Some(id) if id != 0 => id,
+            _ => card.get("did").as_i64().unwrap_or_default(),
+        };
+        if !deck_ids.contains(&deck_id) {
+            continue;
+        }
+        let note_id = card.get("nid").as_i64().unwrap_or_default();
+        let ord = card.get("ord").as_i64();
+        if first_cards
+            .get(&note_id)
+            .is_none_or(|first| first.get("ord").as_i64() > ord)
+        {
+            first_cards.insert(note_id, card);
+        }
+    }
+    let mut reviews: HashMap<i64, Vec<(i64, i64, i64)>> = HashMap::new();
+    for review in table("revlog")?.rows() {
+        let value = |column| review.get(column).as_i64().unwrap_or_default();
+        reviews
+            .entry(value("cid"))
+            .or_default()
+            .push((value("id"), value("ease"), value("time")));
+    }
+
+    let mut seen = HashSet::new();
+    let mut notes = Vec::new();
+    for note in table("notes")?.rows() {
+        let Some(card) = first_cards.get(&note.get("id").as_i64().unwrap_or_default()) else {
+            continue;
+        };
+        let values: Vec<String> = note
+            .get("flds")
+            .as_str()
+            .unwrap_or_default()
+            .split('\x1f')
+            .map(|field| sanitize(&strip_html(field)))
+            .collect();
+        let field = |n: usize| values.get(n.wrapping_sub(1)).cloned().unwrap_or_default();
+        let term = field(fields.0);
+        let body = field(fields.1);
+        if term.is_empty() || body.is_empty() || !seen.insert(term.clone()) {
+            continue;
+        }
+        let comment = values
+            .iter()
+            .enumerate()
+            .filter(|&(i, value)| i + 1 != fields.0 && i + 1 != fields.1 && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+            .collect::<Vec<_>>()
+            .join(" - ");
+        let mut entry = Entry::new(
+            term,
+            vec![Phrase {
+                body,
+                comment: intern(&comment),
+            }],
+        );
+        let tags = note.get("tags");
+        entry.tags.extend(
+            tags.as_str()
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(intern),
+        );
+        let card_id = card.get("id").as_i64().unwrap_or_default();
+        let mut review = anki_review(
+            card,
+            reviews.get(&card_id).map_or(&[], Vec::as_slice),
+            created,
+        );
+        if let Some((score, _)) = &mut review {
+            score.id = entry.card_id();
+        }
+        notes.push(AnkiNote { entry, review });
+    }
+    Ok(notes)
+}
+
+// Anki takes Again for a lapse and Hard, Good and Easy for recalls, so only
+// Again counts as a mistake here.
+fn anki_review(
+    card: &Row,
+    reviews: &[(i64, i64, i64)],
+    created: i64,
+) -> Option<(Score, Vec<Attempt>)> {
+    let value = |column| card.get(column).as_i64().unwrap_or_default();
+    let is_new = value("type") == 0;
+    let suspended = value("queue") == -1;
+    // Reviews without an answer are of cards rescheduled by hand.
+    let attempts: Vec<Attempt> = reviews
+        .iter()
+        .filter(|&&(_, ease, _)| ease > 0)
+        .map(|&(id, ease, time)| Attempt {
+            timestamp: (id / 1000).max(0) as u64,
+            mistakes: (ease == 1) as usize,
+            elapsed_ms: (time > 0).then_some(time as u64),
+            answers: Vec::new(),
+            session: None,
+        })
+        .collect();
+    if is_new && attempts.is_empty() && !suspended {
+        return None;
+    }
+    let correct = attempts
+        .iter()
+        .filter(|attempt| attempt.mistakes == 0)
+        .count() as u32;
+    // Negative intervals are of cards being learned, in seconds.
+    let interval = match value("ivl") {
+        ivl if ivl < 0 => -ivl as f64 / SECS_PER_DAY as f64,
+        ivl => ivl as f64,
+    };
+    let ease = match value("factor") {
+        0 => INITIAL_EASE,
+        factor => (factor as f64 / 1000.0).max(MINIMUM_EASE),
+    };
+    let due = match value("odid") {
+        0 => value("due"),
+        _ => value("odue"),
+    };
+    // Review cards are due on a day counted from the creation of the
+    // collection, and cards being learned at a time, in the same column.
+    // Those days are far fewer than the seconds since 1970.
+    let due = if is_new {
+        0
+    } else if due > 1_000_000_000 {
+        due
+    } else {
+        created + due * SECS_PER_DAY as i64
+    };
+    let score = Score {
+        correct,
+        incorrect: attempts.len() as u32 - correct,
+        schedule: Schedule {
+            repetitions: attempts
+                .iter()
+                .rev()
+                .take_while(|attempt| attempt.mistakes == 0)
+                .count() as u32,
+            interval,
+            ease,
+            lapses: value("lapses") as u32,
+            due: due.max(0) as u64,
+            buried_until: 0,
+        },
+        suspended,
+        ..Score::default()
+    };
+    Some((score, attempts))
+}
+
+// The ids and names of the decks in the JSON object older collections keep
+// them in, keyed by id.
+fn legacy_decks(json: &str) -> Result<Vec<(i64, String)>, String> {
+    let mut input = json.chars().peekable();
+    let mut decks = Vec::new();
+    let mut depth = 0;
+    let mut id = 0;
+    let mut key = String::new();
+    while let Some(&c) = input.peek() {
+        if c != '"' {
+            match c {
+                '{' | '[' => depth += 1,
+                '}' | ']' => depth -= 1,
+                _ => {}
+            }
+            input.next();
+            continue;
+        }
+        let s = parse_string(&mut input)?;
+        skip_whitespace(&mut input);
+        if input.peek() == Some(&':') {
+            match depth {
+                1 => id = s.parse().unwrap_or_default(),
+                2 => key = s,
+                _ => {}
+            }
+        } else if depth == 2 && key == "name" {
+            decks.push((id, s));
+        }
+    }
+    Ok(decks)
+}
+
+// Anki keeps fields as HTML, with sounds in [sound:FILE] references.
+fn strip_html(field: &str) -> String {
+    let mut text = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(c) = rest.chars().next() {
+        let skipped = match c {
+            '<' => rest.find('>').map(|end| (end + 1, None)),
+            '[' if rest.starts_with("[sound:") => rest.find(']').map(|end| (end + 1, None)),
+            '&' => rest
+                .char_indices()
+                .take(10)
+                .find(|&(_, c)| c == ';')
+                .and_then(|(end, _)| Some((end + 1, Some(decode_entity(&rest[1..end])?)))),
+            _ => None,
+        };
+        match skipped {
+            Some((length, decoded)) => {
+                // Tags like <br> and <div> break words apart.
+                text.push(decoded.unwrap_or(' '));
+                rest = &rest[length..];
+            }
+            None => {
+                text.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    text
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    match name {
+        "nbsp" => Some(' '),
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => {
+            let code = name.strip_prefix('#')?;
+            let code = match code.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+// The deck grammar has no escapes, so separators inside fields are replaced.
+pub fn sanitize(field: &str) -> String {
+    field
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('/', "|")
+        .replace(';', ",")
+}
+
+// Dictionaries are tab-separated, with a word and its meanings on each line,
+// like the exports of dict.cc and FreeDict. Words may span several lines.
+pub fn parse_dictionary(source: &str) -> HashMap<String, Vec<String>> {
+    let mut dictionary: HashMap<String, Vec<String>> = HashMap::new();
+    for line in source.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split('\t');
+        let word = fields.next().unwrap_or_default().trim();
+        if word.is_empty() {
+            continue;
+        }
+        let meanings = dictionary.entry(word.to_lowercase()).or_default();
+        for meaning in fields.map(sanitize).filter(|meaning| !meaning.is_empty()) {
+            if !meanings.contains(&meaning) {
+                meanings.push(meaning);
+            }
+        }
+    }
+    dictionary
+}
+
+// Skeleton entries for bare words, with their meanings from the dictionary if
+// it has them. The others are left with a "?" phrase and tagged #todo.
+pub fn generate_entries(
+    words: &[String],
+    dictionary: &HashMap<String, Vec<String>>,
+    lang: Option<&str>,
+) -> Vec<Entry> {
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+    for word in words {
+        let term = sanitize(word);
+        if term.is_empty() || !seen.insert(term.clone()) {
+            continue;
+        }
+        let meanings = dictionary
+            .get(&term.to_lowercase())
+            .filter(|meanings| !meanings.is_empty());
+        let phrases = match meanings {
+            Some(meanings) => meanings.clone(),
+            None => vec!["?".to_owned()],
+        };
+        let mut entry = Entry::new(
+            term,
+            phrases
+                .into_iter()
+                .map(|body| Phrase {
+                    body,
+                    comment: intern(""),
+                })
+                .collect(),
+        );
+        entry.tags.extend(lang.map(intern));
+        if meanings.is_none() {
+            entry.tags.push(intern("todo"));
+        }
+        entries.push(entry);
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_notes_of_a_deck_and_its_subdecks() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/collection.anki2");
+        let notes = import_anki(&path, "Spanish", (1, 2)).unwrap();
+        // Every tenth note is in the default deck.
+        assert_eq!(notes.len(), 55);
+        let first = &notes[0];
+        assert_eq!(&*first.entry.term, "perro0");
+        assert_eq!(first.entry.phrases[0].body, "dog");
+        assert_eq!(&*first.entry.phrases[0].comment, "note 0");
+        let verb = notes
+            .iter()
+            .find(|note| &*note.entry.term == "hablar5")
+            .unwrap();
+        assert_eq!(verb.entry.tags, vec![intern("verb")]);
+        assert!(verb.review.is_none());
+
+        let (score, attempts) = first.review.as_ref().unwrap();
+        assert_eq!((score.correct, score.incorrect), (1, 1));
+        assert_eq!(score.schedule.repetitions, 1);
+        assert_eq!(score.schedule.interval, 10.0);
+        assert_eq!(score.schedule.ease, 2.5);
+        assert_eq!(score.schedule.due, 1_700_000_000 + 30 * SECS_PER_DAY);
+        assert_eq!(
+            attempts
+                .iter()
+                .map(|attempt| attempt.mistakes)
+                .collect::<Vec<_>>(),
+            [1, 0]
+        );
+        assert_eq!(attempts[0].elapsed_ms, Some(4000));
+
+        let long = notes.last().unwrap();
+        assert_eq!(&*long.entry.term, "largo");
+        assert_eq!(long.entry.phrases[0].body, "ab".repeat(1500));
+    }
+
+    #[test]
+    fn reads_escaped_deck_names() {
+        // Anki writes the decks of older collections with everything outside
+        // ASCII escaped, and emoji as pairs of surrogates.
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/collection.anki2");
+        let notes = import_anki(&path, "Emoji \u{1F600}", (1, 2)).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(&*notes[0].entry.term, "tener9");
+    }
+
+    #[test]
+    fn rejects_unknown_decks() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/collection.anki2");
+        let error = import_anki(&path, "French", (1, 2)).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+}
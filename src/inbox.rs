@@ -0,0 +1,278 @@
+// Words collected for learning later by `capture` or the daemon's HTTP
+// endpoint, kept in inbox.txt as one "TIMESTAMP\tWORD\tCONTEXT" line each,
+// the oldest first. The context is the sentence the word was found in, if
+// any. `inbox` goes through them
+// one by one and turns each into an entry of a deck, or discards it:
+//
+//   vocab-trainer inbox [--deck DECK] [--dictionary FILE]
+//
+// Meanings are suggested from the dictionary, in the format of `generate`.
+// Words left for later stay in the inbox. Entries are only added to plain
+// text decks, as bundles and compressed decks would be broken by appending to
+// them.
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+
+use crate::archive::is_archive;
+use crate::days;
+use crate::import::{parse_dictionary, sanitize};
+use crate::settings::Settings;
+use crate::stats::format_date;
+use crate::{detect_config_directory, intern, load_recent_decks, unix_time, Entry, Phrase};
+
+#[derive(Clone, Debug)]
+pub struct Item {
+    pub timestamp: u64,
+    pub word: String,
+    pub context: Option<String>,
+}
+
+pub fn load(path: &Path) -> io::Result<Vec<Item>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let items = fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            Some(Item {
+                timestamp: fields.next()?.parse().ok()?,
+                word: fields.next()?.to_owned(),
+                context: fields
+                    .next()
+                    .filter(|context| !context.is_empty())
+                    .map(str::to_owned),
+            })
+        })
+        .collect();
+    Ok(items)
+}
+
+fn save(path: &Path, items: &[Item]) -> io::Result<()> {
+    let mut contents = String::new();
+    for item in items {
+        contents.push_str(&format!(
+            "{}\t{}\t{}\n",
+            item.timestamp,
+            item.word,
+            item.context.as_deref().unwrap_or_default()
+        ));
+    }
+    fs::write(path, contents)
+}
+
+// Adds the word unless it is already waiting in the inbox. Returns whether it
+// was added.
+pub fn append(path: &Path, word: &str, context: Option<&str>) -> io::Result<bool> {
+    let word = collapse_whitespace(word);
+    let context = context.map(collapse_whitespace).unwrap_or_default();
+    if word.is_empty() || load(path)?.iter().any(|item| item.word == word) {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+    writeln!(file, "{}\t{}\t{}", unix_time(), word, context)?;
+    Ok(true)
+}
+
+// Tabs and line breaks would break the format.
+fn collapse_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn run(args: &[String]) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let config_dir = detect_config_directory();
+    let settings = Settings::load(config_dir.join("config.toml"))?;
+    let mut deck = None;
+    let mut dictionary_path = settings.get_str("inbox", "dictionary").map(PathBuf::from);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--deck" => deck = args.next().map(PathBuf::from),
+            "--dictionary" => dictionary_path = args.next().map(PathBuf::from),
+            _ => return Err(invalid(format!("unknown option: {}", arg))),
+        }
+    }
+    if let Some(deck) = deck.as_deref().filter(|deck| is_archive(deck)) {
+        return Err(invalid(not_text_deck(deck)));
+    }
+    let inbox_path = config_dir.join("inbox.txt");
+    let mut items = load(&inbox_path)?;
+    if items.is_empty() {
+        println!("The inbox is empty.");
+        return Ok(());
+    }
+    let dictionary = match dictionary_path {
+        Some(path) => parse_dictionary(&fs::read_to_string(path)?),
+        None => HashMap::new(),
+    };
+    // The text deck practiced last is offered first.
+    if deck.is_none() {
+        deck = load_recent_decks(&config_dir.join("recent.txt"))?
+            .into_iter()
+            .find(|deck| !is_archive(deck));
+    }
+    println!(
+        "{} words in the inbox. Answer :skip to keep a word for later, :discard to drop it \
+         or :quit to stop.",
+        items.len()
+    );
+
+    let mut kept = Vec::new();
+    let mut added = 0;
+    let mut discarded = 0;
+    while !items.is_empty() {
+        let item = items.remove(0);
+        println!();
+        println!(
+            "{}{}{} {}(captured {}, {} more){}",
+            termion::style::Bold,
+            item.word,
+            termion::style::Reset,
+            termion::color::Fg(termion::color::LightBlack),
+            format_date(days::day(item.timestamp)),
+            items.len(),
+            termion::style::Reset,
+        );
+        if let Some(context) = &item.context {
+            println!(
+                "  {}{}{}",
+                termion::style::Faint,
+                context,
+                termion::style::Reset
+            );
+        }
+        let meanings = dictionary.get(&item.word.to_lowercase());
+        match triage(&item, meanings, deck.as_deref())? {
+            Outcome::Added(path) => {
+                added += 1;
+                deck = Some(path);
+            }
+            Outcome::Discarded => discarded += 1,
+            Outcome::Skipped => kept.push(item),
+            Outcome::Quit => {
+                kept.push(item);
+                break;
+            }
+        }
+        // Saving after each word keeps the work done if the terminal goes away.
+        save(&inbox_path, &[kept.as_slice(), items.as_slice()].concat())?;
+    }
+    kept.append(&mut items);
+    save(&inbox_path, &kept)?;
+    println!();
+    println!(
+        "{} added, {} discarded, {} left in the inbox",
+        added,
+        discarded,
+        kept.len()
+    );
+    Ok(())
+}
+
+enum Outcome {
+    Added(PathBuf),
+    Discarded,
+    Skipped,
+    Quit,
+}
+
+fn triage(item: &Item, meanings: Option<&Vec<String>>, deck: Option<&Path>) -> io::Result<Outcome> {
+    let suggestion = meanings
+        .map(|meanings| meanings.join("/"))
+        .unwrap_or_default();
+    let phrases = match prompt("Meanings, separated by /", &suggestion)? {
+        Answer::Text(phrases) if phrases.is_empty() => return Ok(Outcome::Skipped),
+        Answer::Text(phrases) => phrases,
+        Answer::Command(outcome) => return Ok(outcome),
+    };
+    let tags = match prompt("Tags", "")? {
+        Answer::Text(tags) => tags,
+        Answer::Command(outcome) => return Ok(outcome),
+    };
+    let default_deck = deck
+        .map(|deck| deck.display().to_string())
+        .unwrap_or_default();
+    let path = loop {
+        match prompt("Deck", &default_deck)? {
+            Answer::Text(path) if path.is_empty() => println!("No deck given"),
+            Answer::Text(path) if is_archive(Path::new(&path)) => {
+                println!("{}", not_text_deck(Path::new(&path)))
+            }
+            Answer::Text(path) => break PathBuf::from(path),
+            Answer::Command(outcome) => return Ok(outcome),
+        }
+    };
+
+    let mut entry = Entry::new(
+        sanitize(&item.word),
+        phrases
+            .split('/')
+            .map(sanitize)
+            .filter(|body| !body.is_empty())
+            .map(|body| Phrase {
+                body,
+                comment: intern(""),
+            })
+            .collect(),
+    );
+    entry.tags = tags
+        .split_whitespace()
+        .map(|tag| intern(tag.trim_start_matches('#')))
+        .collect();
+    append_entry(&path, &entry)?;
+    println!("Added {} to {}", entry, path.display());
+    Ok(Outcome::Added(path))
+}
+
+enum Answer {
+    Text(String),
+    Command(Outcome),
+}
+
+// An empty answer takes the default shown in brackets, if there is one.
+fn prompt(label: &str, default: &str) -> io::Result<Answer> {
+    if default.is_empty() {
+        print!("{}> ", label);
+    } else {
+        print!("{} [{}]> ", label, default);
+    }
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line)? == 0 {
+        println!();
+        return Ok(Answer::Command(Outcome::Quit));
+    }
+    Ok(match line.trim() {
+        ":skip" => Answer::Command(Outcome::Skipped),
+        ":discard" => Answer::Command(Outcome::Discarded),
+        ":quit" => Answer::Command(Outcome::Quit),
+        "" => Answer::Text(default.to_owned()),
+        answer => Answer::Text(answer.to_owned()),
+    })
+}
+
+fn not_text_deck(path: &Path) -> String {
+    format!(
+        "{} is a bundle or compressed, give a text deck to add to instead",
+        path.display()
+    )
+}
+
+fn append_entry(path: &Path, entry: &Entry) -> io::Result<()> {
+    // A deck without a final newline would have the entry joined to its last
+    // line.
+    let needs_newline =
+        fs::read(path).is_ok_and(|contents| !contents.is_empty() && !contents.ends_with(b"\n"));
+    let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+    if needs_newline {
+        writeln!(file)?;
+    }
+    writeln!(file, "{}", entry)
+}
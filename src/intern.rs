@@ -0,0 +1,17 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, OnceLock};
+
+// Tags and phrase comments repeat across the entries of a deck, so each
+// distinct string is allocated once and shared.
+pub fn intern(s: &str) -> Arc<str> {
+    static STRINGS: OnceLock<Mutex<HashSet<Arc<str>>>> = OnceLock::new();
+    let mut strings = STRINGS.get_or_init(Default::default).lock().unwrap();
+    match strings.get(s) {
+        Some(interned) => interned.clone(),
+        None => {
+            let interned: Arc<str> = Arc::from(s);
+            strings.insert(interned.clone());
+            interned
+        }
+    }
+}
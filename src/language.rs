@@ -0,0 +1,252 @@
+// Guesses the language of a deck from its terms, for the behavior that
+// depends on it when the deck's settings don't say:
+//
+//   [deck]
+//   language = "tr"
+//   direction = "rtl"
+//
+// The script is the one most letters of the terms are written in, and can be
+// set as `script` too. The language comes from the tags when most entries
+// carry the same language code, as generated decks do, or else from the
+// script and the letters only some languages use. A Latin deck without such
+// letters has no language. The language picks the normalizers, the script
+// which marks hints keep with their letters, and terms of right-to-left
+// scripts are laid out so unless `direction` is "ltr" or "auto".
+
+use crate::settings::Settings;
+use crate::Entry;
+
+// Enough terms to tell the script and the distinctive letters of a deck.
+const SAMPLE_ENTRIES: usize = 500;
+
+const LANGUAGE_TAGS: &[&str] = &[
+    "ar", "az", "de", "el", "en", "es", "fa", "fr", "he", "hi", "it", "ja", "ko", "nl", "pl", "pt",
+    "ru", "sv", "th", "tr", "uk", "zh",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Hebrew,
+    Arabic,
+    Devanagari,
+    Thai,
+    Hangul,
+    Kana,
+    Han,
+}
+
+impl Script {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let script = match name {
+            "latin" => Script::Latin,
+            "greek" => Script::Greek,
+            "cyrillic" => Script::Cyrillic,
+            "hebrew" => Script::Hebrew,
+            "arabic" => Script::Arabic,
+            "devanagari" => Script::Devanagari,
+            "thai" => Script::Thai,
+            "hangul" => Script::Hangul,
+            "kana" => Script::Kana,
+            "han" => Script::Han,
+            _ => return None,
+        };
+        Some(script)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Script::Latin => "Latin",
+            Script::Greek => "Greek",
+            Script::Cyrillic => "Cyrillic",
+            Script::Hebrew => "Hebrew",
+            Script::Arabic => "Arabic",
+            Script::Devanagari => "Devanagari",
+            Script::Thai => "Thai",
+            Script::Hangul => "Hangul",
+            Script::Kana => "Kana",
+            Script::Han => "Han",
+        }
+    }
+
+    pub fn of(c: char) -> Option<Self> {
+        let script = match c {
+            'A'..='Z' | 'a'..='z' | '\u{C0}'..='\u{24F}' | '\u{1E00}'..='\u{1EFF}' => Script::Latin,
+            '\u{370}'..='\u{3FF}' | '\u{1F00}'..='\u{1FFF}' => Script::Greek,
+            '\u{400}'..='\u{52F}' => Script::Cyrillic,
+            '\u{590}'..='\u{5FF}' | '\u{FB1D}'..='\u{FB4F}' => Script::Hebrew,
+            '\u{600}'..='\u{6FF}' | '\u{750}'..='\u{77F}' | '\u{FB50}'..='\u{FEFF}' => {
+                Script::Arabic
+            }
+            '\u{900}'..='\u{97F}' => Script::Devanagari,
+            '\u{E00}'..='\u{E7F}' => Script::Thai,
+            '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' | '\u{AC00}'..='\u{D7AF}' => {
+                Script::Hangul
+            }
+            '\u{3040}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' => Script::Kana,
+            '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}' => {
+                Script::Han
+            }
+            _ => return None,
+        };
+        Some(script)
+    }
+
+    // The language a script is all but only used for.
+    fn language(self) -> Option<&'static str> {
+        match self {
+            Script::Greek => Some("el"),
+            Script::Hebrew => Some("he"),
+            Script::Arabic => Some("ar"),
+            Script::Devanagari => Some("hi"),
+            Script::Thai => Some("th"),
+            Script::Hangul => Some("ko"),
+            Script::Kana => Some("ja"),
+            Script::Han => Some("zh"),
+            Script::Latin | Script::Cyrillic => None,
+        }
+    }
+
+    pub fn is_rtl(self) -> bool {
+        matches!(self, Script::Hebrew | Script::Arabic)
+    }
+
+    // Marks that combine with the letter before them, and are masked and
+    // revealed along with it in hints.
+    pub fn is_combining_mark(self, c: char) -> bool {
+        match self {
+            Script::Latin | Script::Greek | Script::Cyrillic => {
+                ('\u{300}'..='\u{36F}').contains(&c)
+            }
+            Script::Hebrew | Script::Arabic => crate::bidi::is_combining_mark(c),
+            // Vowel signs, the virama and the other signs written around a
+            // consonant.
+            Script::Devanagari => matches!(c,
+                '\u{900}'..='\u{903}'
+                | '\u{93A}'..='\u{93C}'
+                | '\u{93E}'..='\u{94F}'
+                | '\u{951}'..='\u{957}'
+                | '\u{962}'..='\u{963}'),
+            Script::Thai => matches!(c, '\u{E31}' | '\u{E34}'..='\u{E3A}' | '\u{E47}'..='\u{E4E}'),
+            // Syllables and characters are whole letters of their own.
+            Script::Hangul | Script::Kana | Script::Han => false,
+        }
+    }
+}
+
+// Characters typed through an input method, which commits a word or more of
+// them at once as it is converted, along with the punctuation and full-width
+// forms it gives.
+pub fn is_input_method_char(c: char) -> bool {
+    matches!(
+        Script::of(c),
+        Some(Script::Hangul | Script::Kana | Script::Han)
+    ) || matches!(c, '\u{3000}'..='\u{303F}' | '\u{FF00}'..='\u{FFEF}')
+}
+
+// Combining marks of any script, for terms whose script isn't known.
+pub fn is_combining_mark(script: Option<Script>, c: char) -> bool {
+    match script {
+        Some(script) => script.is_combining_mark(c),
+        None => [
+            Script::Latin,
+            Script::Hebrew,
+            Script::Devanagari,
+            Script::Thai,
+        ]
+        .iter()
+        .any(|script| script.is_combining_mark(c)),
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Language {
+    pub code: Option<String>,
+    pub script: Option<Script>,
+    // Whether terms are laid out right-to-left, or `None` to follow each one.
+    pub rtl: Option<bool>,
+}
+
+impl Language {
+    // Detected from the terms, for whatever the settings leave out.
+    pub fn from_settings(settings: &Settings, entries: &[Entry]) -> Result<Self, String> {
+        let entries = &entries[..entries.len().min(SAMPLE_ENTRIES)];
+        let script = match settings.get_str("deck", "script") {
+            Some(name) => {
+                Some(Script::from_name(name).ok_or_else(|| format!("unknown script: {}", name))?)
+            }
+            None => dominant_script(entries),
+        };
+        let code = match settings.get_str("deck", "language") {
+            Some(code) => Some(code.to_owned()),
+            None => tagged_language(entries)
+                .or_else(|| script.and_then(Script::language))
+                .or_else(|| match script {
+                    Some(Script::Latin) => latin_language(entries),
+                    _ => None,
+                })
+                .map(str::to_owned),
+        };
+        let rtl = match settings.get_str("deck", "direction") {
+            Some("rtl") => Some(true),
+            Some("ltr") => Some(false),
+            Some("auto") => None,
+            Some(direction) => return Err(format!("unknown direction: {}", direction)),
+            None => script.filter(|script| script.is_rtl()).map(|_| true),
+        };
+        Ok(Self { code, script, rtl })
+    }
+}
+
+fn dominant_script(entries: &[Entry]) -> Option<Script> {
+    let mut counts: Vec<(Script, usize)> = Vec::new();
+    for c in entries.iter().flat_map(|entry| entry.term.chars()) {
+        if let Some(script) = Script::of(c).filter(|_| c.is_alphabetic()) {
+            match counts.iter_mut().find(|(s, _)| *s == script) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((script, 1)),
+            }
+        }
+    }
+    // Japanese is written in kanji as much as in kana, so any kana at all
+    // tells it from Chinese.
+    let has_kana = counts.iter().any(|&(script, _)| script == Script::Kana);
+    counts
+        .iter()
+        .max_by_key(|&&(_, count)| count)
+        .map(|&(script, _)| match script {
+            Script::Han if has_kana => Script::Kana,
+            script => script,
+        })
+}
+
+// A language code most of the entries are tagged with.
+fn tagged_language(entries: &[Entry]) -> Option<&'static str> {
+    LANGUAGE_TAGS.iter().copied().find(|&language| {
+        let tagged = entries
+            .iter()
+            .filter(|entry| entry.tags.iter().any(|tag| &**tag == language))
+            .count();
+        tagged * 2 > entries.len()
+    })
+}
+
+// Letters hardly any other language written in the Latin script uses.
+fn latin_language(entries: &[Entry]) -> Option<&'static str> {
+    let has = |letters: &[char]| entries.iter().any(|entry| entry.term.contains(letters));
+    if has(&['ğ', 'ş', 'ı', 'İ', 'Ğ', 'Ş']) {
+        Some("tr")
+    } else if has(&['ß', 'ẞ']) {
+        Some("de")
+    } else if has(&['ñ', 'Ñ', '¿', '¡']) {
+        Some("es")
+    } else if has(&['œ', 'Œ', 'ê', 'è', 'û', 'ë']) {
+        Some("fr")
+    } else if has(&['ã', 'õ', 'Ã', 'Õ']) {
+        Some("pt")
+    } else {
+        None
+    }
+}
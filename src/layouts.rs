@@ -0,0 +1,71 @@
+// Answers typed with the wrong keyboard layout active, such as "ghbdtn" for
+// "привет" on the keys of the Russian layout, are translated back key by key
+// and taken with a warning if that makes them right. Like the lookalike
+// letters of confusables.rs, this is set with:
+//
+//   [matching]
+//   layouts = "warn"
+//
+// "accept", or "exact" to count them wrong.
+
+struct Layout {
+    name: &'static str,
+    // The characters of the layout on the keys of the US layout's below, in
+    // the same order, unshifted then shifted.
+    keys: &'static str,
+}
+
+const US_KEYS: &str = "`qwertyuiop[]asdfghjkl;'zxcvbnm,./~QWERTYUIOP{}ASDFGHJKL:\"ZXCVBNM<>?";
+
+const LAYOUTS: &[Layout] = &[
+    Layout {
+        name: "Russian",
+        keys: "ёйцукенгшщзхъфывапролджэячсмитьбю.ЁЙЦУКЕНГШЩЗХЪФЫВАПРОЛДЖЭЯЧСМИТЬБЮ,",
+    },
+    Layout {
+        name: "Ukrainian",
+        keys: "ґйцукенгшщзхїфівапролджєячсмитьбю.ҐЙЦУКЕНГШЩЗХЇФІВАПРОЛДЖЄЯЧСМИТЬБЮ,",
+    },
+    Layout {
+        name: "Greek",
+        keys: "`;ςερτυθιοπ[]ασδφγηξκλ΄'ζχψωβνμ,./~:΅ΕΡΤΥΘΙΟΠ{}ΑΣΔΦΓΗΞΚΛ¨\"ΖΧΨΩΒΝΜ<>?",
+    },
+    Layout {
+        name: "Hebrew",
+        keys: ";/'קראטוןםפ][שדגכעיחלךף,זסבהנמצתץ.~QWERTYUIOP}{ASDFGHJKL:\"ZXCVBNM><?",
+    },
+];
+
+impl Layout {
+    // The answer as if typed with this layout instead of the US one, or the
+    // other way round.
+    fn translate(&self, answer: &str, to_layout: bool) -> String {
+        let (from, to) = if to_layout {
+            (US_KEYS, self.keys)
+        } else {
+            (self.keys, US_KEYS)
+        };
+        answer
+            .chars()
+            .map(|c| {
+                from.chars()
+                    .position(|key| key == c)
+                    .and_then(|i| to.chars().nth(i))
+                    .unwrap_or(c)
+            })
+            .collect()
+    }
+}
+
+// The answer translated to or from each layout, with the name of the layout
+// it is taken as typed on.
+pub fn retyped(answer: &str) -> impl Iterator<Item = (&'static str, String)> + '_ {
+    LAYOUTS.iter().flat_map(move |layout| {
+        [
+            (layout.name, layout.translate(answer, true)),
+            ("US", layout.translate(answer, false)),
+        ]
+        .into_iter()
+        .filter(move |(_, translated)| translated != answer)
+    })
+}
@@ -1,35 +1,133 @@
+#[cfg(feature = "bench")]
+extern crate criterion;
+extern crate flate2;
+extern crate regex;
 extern crate rustyline;
 extern crate rustyline_derive;
+extern crate sha2;
+extern crate tar;
 extern crate termion;
+extern crate unicode_width;
+extern crate zip;
+
+mod achievements;
+mod archive;
+mod audio;
+mod autosave;
+#[cfg(feature = "bench")]
+mod bench;
+mod bidi;
+#[cfg(feature = "clipboard")]
+mod capture;
+mod classroom;
+mod confusables;
+mod corpus;
+mod daemon;
+mod days;
+mod diff;
+mod duel;
+mod events;
+mod export;
+mod filter;
+#[cfg(any(test, feature = "fuzz"))]
+mod fuzz;
+mod hangul;
+mod import;
+mod inbox;
+mod intern;
+mod language;
+mod layouts;
+mod menu;
+mod normalize;
+mod notes;
+mod pairs;
+mod player;
+mod prune;
+mod replay;
+mod scheduler;
+mod settings;
+mod sqlite;
+mod stats;
+mod store;
+mod style;
+mod sync;
+mod truefalse;
+mod ulid;
 
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::env;
 use std::fmt;
 use std::fs::{self, File, OpenOptions};
-use std::io::{self, BufRead as _, BufReader, BufWriter, Read, Write};
-use std::iter::Peekable;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::iter::{self, Peekable};
 use std::mem;
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
-use std::str::Chars;
+use std::process;
+use std::str::{Chars, FromStr};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{self, Instant, SystemTime, UNIX_EPOCH};
 
+use regex::Regex;
 use rustyline::error::ReadlineError;
 use rustyline::highlight::Highlighter;
 use rustyline::hint::Hinter;
-use rustyline::{Behavior, Config, Context, Editor};
-use rustyline_derive::{Completer, Helper, Validator};
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{
+    Behavior, Cmd, ColorMode, ConditionalEventHandler, Config, Context, Editor, Event,
+    EventContext, EventHandler, KeyCode, KeyEvent, Modifiers, RepeatCount,
+};
+use rustyline_derive::{Completer, Helper};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use autosave::Autosave;
+use corpus::Corpus;
+use events::EventBus;
+use intern::intern;
+use language::{Language, Script};
+use normalize::Normalizers;
+use notes::Notes;
+use player::Player;
+use scheduler::Schedule;
+use settings::Settings;
+use store::ScoreStore;
+use style::Style;
+#[cfg(feature = "net")]
+use sync::WebDavBackend;
+use sync::{GitBackend, SyncBackend, SyncFiles};
 
 #[derive(Debug)]
 struct Entry {
-    term: String,
+    // Interned, so that a term in several decks, or handed to each of its
+    // questions, is a single allocation.
+    term: Arc<str>,
     phrases: Vec<Phrase>,
+    alternatives: Vec<String>,
+    pattern: Option<Regex>,
+    tags: Vec<Arc<str>>,
+    id: Option<String>,
+    overrides: scheduler::Overrides,
 }
 
 impl Entry {
-    fn parse(mut input: Peekable<Chars>) -> Option<Entry> {
+    fn new(term: String, phrases: Vec<Phrase>) -> Self {
+        Self {
+            term: intern(&term),
+            phrases,
+            alternatives: Vec::new(),
+            pattern: None,
+            tags: Vec::new(),
+            id: None,
+            overrides: scheduler::Overrides::default(),
+        }
+    }
+
+    fn parse(mut input: Peekable<Chars>) -> Result<Option<Entry>, String> {
         match input.peek() {
-            Some(';') | None => None,
+            Some(';') | None => Ok(None),
             Some(_) => {
                 let mut term = String::new();
                 while let Some(c) = input.next() {
@@ -45,12 +143,12 @@ impl Entry {
                 let mut body = String::new();
                 let mut comment = String::new();
                 let mut is_comment = false;
-                while let Some(c) = input.next() {
+                for c in input {
                     match c {
                         '/' => {
                             let phrase = Phrase {
                                 body: mem::take(&mut body),
-                                comment: mem::take(&mut comment),
+                                comment: intern(&mem::take(&mut comment)),
                             };
                             phrases.push(phrase);
                             is_comment = false;
@@ -67,51 +165,455 @@ impl Entry {
                         }
                     }
                 }
-                Some(Entry { term, phrases })
+                let mut entry = Entry::new(term, phrases);
+                // Whatever follows the last '/' holds the attributes.
+                for token in body.split_whitespace() {
+                    if let Some(tag) = token.strip_prefix('#') {
+                        entry.tags.push(intern(tag));
+                    } else if let Some(id) = token
+                        .strip_prefix('@')
+                        .or_else(|| token.strip_prefix("id="))
+                    {
+                        entry.id = Some(id.to_owned());
+                    } else {
+                        entry.overrides.parse_attribute(token)?;
+                    }
+                }
+                Ok(Some(entry))
+            }
+        }
+    }
+
+    // The overrides of the card's tags, set in `[schedule.TAG]` sections,
+    // fill in what the card leaves unset.
+    fn apply_tag_overrides(&mut self, settings: &Settings) -> Result<(), String> {
+        for tag in &self.tags {
+            let overrides = scheduler::Overrides::from_settings(settings, tag)?;
+            self.overrides = self.overrides.or(overrides);
+        }
+        Ok(())
+    }
+
+    // Identifies the card across renames of the term. Without an explicit id,
+    // the term is hashed, so a renamed card can keep its scores by declaring
+    // the hash of its old term as the id.
+    fn card_id(&self) -> String {
+        self.id.clone().unwrap_or_else(|| hash_term(&self.term))
+    }
+
+    // Scores and history are recorded under the explicit id if there is one,
+    // so that terms can be edited and several cards can share a term.
+    fn key(&self) -> &str {
+        self.id.as_deref().unwrap_or(&self.term)
+    }
+
+    // Cards sharing a term, like the meanings of a homonym, would give each
+    // other away when asked back-to-back.
+    fn is_sibling_of(&self, other: &Entry, normalizers: &Normalizers) -> bool {
+        self.key() != other.key()
+            && normalizers.apply(&self.term, false) == normalizers.apply(&other.term, false)
+    }
+
+    fn has_any_tag(&self, tags: &[String]) -> bool {
+        tags.is_empty()
+            || self
+                .tags
+                .iter()
+                .any(|tag| tags.iter().any(|other| **other == **tag))
+    }
+
+    // Which words of an answer are right, for terms of several words like
+    // phrasal verbs, if all but one of them are.
+    fn graded_words(
+        &self,
+        answer: &str,
+        ignore_case: bool,
+        normalizers: &Normalizers,
+    ) -> Option<Vec<bool>> {
+        let term = normalize_whitespace(&self.term);
+        let answer = normalize_whitespace(answer);
+        let term_words: Vec<&str> = term.split(' ').collect();
+        let answer_words: Vec<&str> = answer.split(' ').collect();
+        if term_words.len() < 2 || term_words.len() != answer_words.len() {
+            return None;
+        }
+        let right: Vec<bool> = term_words
+            .iter()
+            .zip(&answer_words)
+            .map(|(term_word, answer_word)| {
+                normalizers.apply(term_word, ignore_case)
+                    == normalizers.apply(answer_word, ignore_case)
+            })
+            .collect();
+        (right.iter().filter(|&&right| !right).count() == 1).then_some(right)
+    }
+
+    // For reverse questions. A phrase listing several meanings, as in "to get,
+    // to receive", is answered by any of them.
+    fn matches_meaning(&self, answer: &str, ignore_case: bool, normalizers: &Normalizers) -> bool {
+        let answer = normalizers.apply(answer, ignore_case);
+        self.phrases.iter().any(|phrase| {
+            iter::once(phrase.body.as_str())
+                .chain(phrase.body.split([',', ';']))
+                .any(|meaning| normalizers.apply(meaning, ignore_case) == answer)
+        })
+    }
+
+    // Which words of an answer are in the order of the term's, as the longest
+    // sequence of them that is.
+    fn words_in_order(
+        &self,
+        answer: &str,
+        ignore_case: bool,
+        normalizers: &Normalizers,
+    ) -> Vec<bool> {
+        let normalize = |word: &str| normalizers.apply(word, ignore_case);
+        let term: Vec<String> = self.term.split_whitespace().map(normalize).collect();
+        let answer: Vec<String> = answer.split_whitespace().map(normalize).collect();
+        let mut lengths = vec![vec![0; term.len() + 1]; answer.len() + 1];
+        for i in (0..answer.len()).rev() {
+            for j in (0..term.len()).rev() {
+                lengths[i][j] = if answer[i] == term[j] {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+        let mut in_order = vec![false; answer.len()];
+        let (mut i, mut j) = (0, 0);
+        while i < answer.len() && j < term.len() {
+            if answer[i] == term[j] {
+                in_order[i] = true;
+                i += 1;
+                j += 1;
+            } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        in_order
+    }
+
+    // The answer with its first letter in the case of the term's, if that is
+    // all they differ in there.
+    fn recapitalized(&self, answer: &str) -> Option<String> {
+        let answer = answer.trim_start();
+        let first = answer.chars().next()?;
+        let term_first = self.term.chars().next()?;
+        (first != term_first && first.to_lowercase().eq(term_first.to_lowercase()))
+            .then(|| format!("{}{}", term_first, &answer[first.len_utf8()..]))
+    }
+
+    fn matches(&self, answer: &str, ignore_case: bool, normalizers: &Normalizers) -> bool {
+        let normalize = |text: &str| normalizers.apply(text, ignore_case);
+        let composed = hangul::compose(&normalize_whitespace(answer));
+        let answer = normalize(&composed);
+        let term = normalize(&self.term);
+        term == answer
+            || (hangul::contains_hangul(&term)
+                && answer.is_ascii()
+                && hangul::romanize(&term) == answer.to_lowercase())
+            || self
+                .pattern
+                .as_ref()
+                .is_some_and(|pattern| pattern.is_match(&composed))
+            || self
+                .alternatives
+                .iter()
+                .any(|alternative| normalize(alternative) == answer)
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.term)?;
+        if let Some(pattern) = &self.pattern {
+            write!(f, " ~{}", pattern.as_str())?;
+        }
+        f.write_str(" /")?;
+        for phrase in &self.phrases {
+            f.write_str(&phrase.body)?;
+            if !phrase.comment.is_empty() {
+                write!(f, ";{}", phrase.comment)?;
             }
+            f.write_str("/")?;
+        }
+        if let Some(id) = &self.id {
+            write!(f, " @{}", id)?;
+        }
+        for tag in &self.tags {
+            write!(f, " #{}", tag)?;
+        }
+        if let Some(ease) = self.overrides.ease {
+            write!(f, " ease={}", ease)?;
         }
+        if let Some(cap) = self.overrides.interval_cap {
+            write!(f, " interval-cap={}d", cap)?;
+        }
+        Ok(())
     }
 }
 
+// 64-bit FNV-1a, which unlike the std hasher is stable across releases.
+fn hash_term(term: &str) -> String {
+    let hash = term.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
 #[derive(Debug)]
 struct Question {
     index: usize,
-    entry: Rc<Entry>,
+    // The index of the entry in the deck.
+    entry: usize,
+    // The indices of the phrases shown, in the order of the deck.
+    phrases: Vec<usize>,
+    kind: QuestionKind,
+    // Whether letters of the term are revealed after mistakes.
+    hints: bool,
+}
+
+#[derive(Debug, Default)]
+enum QuestionKind {
+    // The term is typed from its phrases.
+    #[default]
+    Recall,
+    // The words of the term are shown shuffled, to be typed in order or
+    // picked by their numbers.
+    Arrange(Vec<String>),
+    // The term is picked by its number among others, or typed.
+    Choice(Vec<String>),
+    // The term is shown and one of its phrases typed.
+    Reverse,
+}
+
+// Choice questions show the term with this many others.
+const CHOICE_DISTRACTORS: usize = 3;
+
+// Picking the term out of a few is easier than recalling it, so a right pick
+// is scheduled as a near miss, to come back sooner than a recalled term. It
+// still counts as right, in the points, the statistics and the history.
+const CHOICE_MISTAKES: f64 = 0.5;
+
+// Shorter terms are hardly worth arranging.
+const MIN_ARRANGED_WORDS: usize = 3;
+
+impl Question {
+    // An answer of numbers picks the shown words of an arrange question, and
+    // a number the term of a choice question.
+    fn resolve_answer<'a>(&self, answer: &'a str) -> Cow<'a, str> {
+        let words = match &self.kind {
+            QuestionKind::Arrange(words) => words,
+            QuestionKind::Choice(choices) if answer.split_whitespace().count() == 1 => choices,
+            _ => return Cow::Borrowed(answer),
+        };
+        let picked: Option<Vec<&str>> = answer
+            .split_whitespace()
+            .map(|number| {
+                let i = number.parse::<usize>().ok()?.checked_sub(1)?;
+                words.get(i).map(String::as_str)
+            })
+            .collect();
+        match picked {
+            Some(picked) if !picked.is_empty() => Cow::Owned(picked.join(" ")),
+            _ => Cow::Borrowed(answer),
+        }
+    }
+}
+
+// "1 take, 2 bring" for reading out.
+fn format_numbered(items: &[String]) -> String {
+    let numbered: Vec<String> = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| format!("{} {}", i + 1, item))
+        .collect();
+    numbered.join(", ")
+}
+
+fn shuffle_words(term: &str) -> Option<Vec<String>> {
+    let words: Vec<String> = term.split_whitespace().map(str::to_owned).collect();
+    if words.len() < MIN_ARRANGED_WORDS {
+        return None;
+    }
+    let mut shuffled = words.clone();
+    // Shown in their own order, the words would give the answer away.
+    for _ in 0..10 {
+        shuffle(&mut shuffled);
+        if shuffled != words {
+            break;
+        }
+    }
+    Some(shuffled)
 }
 
-#[derive(Debug, Completer, Helper, Validator)]
+// What the hint of the question being answered is worked out from.
+#[derive(Clone, Debug, Default)]
 struct QuestionHint {
-    entry: Rc<Entry>,
+    term: Arc<str>,
     mistakes: usize,
+    enabled: bool,
+    script: Option<Script>,
+    // Keeps Enter on an empty line from giving an answer.
+    answer_required: bool,
+}
+
+// The editor's helper, set once and kept for the whole session so that what
+// it gathers outlives each question. The question it hints is swapped in
+// with `set_question`.
+#[derive(Debug, Default, Completer, Helper)]
+struct AnswerHelper {
+    question: RefCell<QuestionHint>,
+    // The length of the line and when it was last seen, to tell pasted
+    // answers from typed ones.
+    typing: Cell<Option<(usize, Instant)>>,
+    burst: Cell<usize>,
+    pasted: Cell<bool>,
+}
+
+// Characters arriving faster than anyone types, or several at once as with
+// bracketed paste, make a burst. A burst this long is taken to be pasted.
+const PASTE_BURST_CHARS: usize = 3;
+
+const MIN_KEYSTROKE_INTERVAL: time::Duration = time::Duration::from_millis(10);
+
+impl QuestionHint {
+    fn new(term: Arc<str>, mistakes: usize, enabled: bool, script: Option<Script>) -> Self {
+        Self {
+            term,
+            mistakes,
+            enabled,
+            script,
+            answer_required: false,
+        }
+    }
+}
+
+impl AnswerHelper {
+    // The line is typed afresh, so nothing of the last one counts as pasted.
+    fn set_question(&self, question: QuestionHint) {
+        self.question.replace(question);
+        self.typing.set(None);
+        self.burst.set(0);
+        self.pasted.set(false);
+    }
+
+    // The hint is asked for after every edit of the line, with the cursor
+    // after what was typed. Several characters typed at once with an input
+    // method, as for Chinese, Japanese and Korean, make a single keystroke.
+    fn observe(&self, line: &str, pos: usize) {
+        let len = line.chars().count();
+        let now = Instant::now();
+        let (last_len, is_fast) = match self.typing.get() {
+            Some((last_len, last_time)) => (last_len, now - last_time < MIN_KEYSTROKE_INTERVAL),
+            None => (0, false),
+        };
+        let added = len.saturating_sub(last_len);
+        let committed = || {
+            line.get(..pos).is_some_and(|typed| {
+                typed
+                    .chars()
+                    .rev()
+                    .take(added)
+                    .all(language::is_input_method_char)
+            })
+        };
+        if added == 0 {
+            self.burst.set(0);
+        } else if added > 1 && committed() {
+            self.burst.set(1);
+        } else if added > 1 || is_fast {
+            self.burst.set(self.burst.get() + added);
+        } else {
+            self.burst.set(1);
+        }
+        if self.burst.get() >= PASTE_BURST_CHARS {
+            self.pasted.set(true);
+        }
+        self.typing.set(Some((len, now)));
+    }
 }
 
-impl Hinter for QuestionHint {
+impl Hinter for AnswerHelper {
     type Hint = String;
 
-    fn hint(&self, line: &str, _pos: usize, _ctx: &Context<'_>) -> Option<Self::Hint> {
-        let mut symbols = 0;
-        let hint_string = self
-            .entry
-            .term
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<Self::Hint> {
+        self.observe(line, pos);
+        let question = self.question.borrow();
+        if !question.enabled {
+            return None;
+        }
+        let hint_string = mask_term(&question.term, question.mistakes, question.script)
             .chars()
-            .enumerate()
-            .map(|(i, c)| {
-                if !c.is_ascii_alphabetic() {
-                    symbols += 1;
-                    c
-                } else if i - symbols < self.mistakes {
-                    c
-                } else {
-                    '_'
-                }
-            })
             .skip(line.chars().count())
             .collect();
         Some(hint_string)
     }
 }
 
-impl Highlighter for QuestionHint {
+// Deleted characters are struck out in red and missing ones underlined
+// in green. Without colors they are marked as [-deleted-]{+inserted+}.
+fn format_diff(answer: &str, term: &str, plain: bool) -> String {
+    let mut line = String::new();
+    for (change, text) in diff::diff(answer.trim(), term) {
+        match change {
+            diff::Change::Equal => line.push_str(&text),
+            diff::Change::Delete if plain => line.push_str(&format!("[-{}-]", text)),
+            diff::Change::Insert if plain => line.push_str(&format!("{{+{}+}}", text)),
+            diff::Change::Delete => line.push_str(&format!(
+                "{}{}{}{}",
+                termion::color::Fg(termion::color::LightRed),
+                termion::style::CrossedOut,
+                text,
+                termion::style::Reset,
+            )),
+            diff::Change::Insert => line.push_str(&format!(
+                "{}{}{}{}",
+                termion::color::Fg(termion::color::LightGreen),
+                termion::style::Underline,
+                text,
+                termion::style::Reset,
+            )),
+        }
+    }
+    line
+}
+
+// Masks every letter except the first `revealed` ones in reading order. Spaces,
+// hyphens, apostrophes and other symbols stay visible and don't count as
+// letters.
+fn mask_term(term: &str, revealed: usize, script: Option<Script>) -> String {
+    let mut letters = 0;
+    term.chars()
+        .filter_map(|c| {
+            if language::is_combining_mark(script, c) {
+                // Marks go along with the letter they sit on.
+                (letters <= revealed).then_some(c)
+            } else if !c.is_alphanumeric() {
+                Some(c)
+            } else {
+                letters += 1;
+                if letters <= revealed {
+                    Some(c)
+                } else {
+                    Some('_')
+                }
+            }
+        })
+        .collect()
+}
+
+// The letters `mask_term` counts, which hints reveal one at a time.
+fn count_letters(term: &str, script: Option<Script>) -> usize {
+    term.chars()
+        .filter(|&c| !language::is_combining_mark(script, c) && c.is_alphanumeric())
+        .count()
+}
+
+impl Highlighter for AnswerHelper {
     fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
         Cow::Owned(format!(
             "{}{}{}",
@@ -122,326 +624,5513 @@ impl Highlighter for QuestionHint {
     }
 }
 
+impl Validator for AnswerHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if ctx.input().ends_with('\\') {
+            Ok(ValidationResult::Incomplete)
+        } else if self.question.borrow().answer_required && ctx.input().trim().is_empty() {
+            Ok(ValidationResult::Invalid(Some(
+                "  (type an answer)".to_owned(),
+            )))
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Phrase {
     body: String,
-    comment: String,
+    comment: Arc<str>,
 }
 
-struct GameUI {
-    readline: Editor<QuestionHint>,
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Sound {
+    Silent,
+    Bell(usize),
+    File(PathBuf),
 }
 
-impl GameUI {
-    fn new() -> Self {
-        let config = Config::builder().behavior(Behavior::PreferTerm).build();
-        let readline = Editor::<QuestionHint>::with_config(config);
-        Self { readline }
-    }
-
-    fn notify_question(&mut self, question: &Question, _state: &GameState) {
-        print!(
-            "{}{}Q{}{} ",
-            termion::style::Bold,
-            termion::color::Fg(termion::color::LightYellow),
-            question.index + 1,
-            termion::style::Reset,
-        );
-        for phrase in question.entry.phrases.iter() {
-            if phrase.comment.is_empty() {
-                print!(
-                    "/{}{}{}{}",
-                    termion::style::Bold,
-                    termion::color::Fg(termion::color::LightBlue),
-                    phrase.body,
-                    termion::style::Reset,
-                );
-            } else {
-                print!(
-                    "/{}{}{}{};{}{}",
-                    termion::style::Bold,
-                    termion::color::Fg(termion::color::LightBlue),
-                    phrase.body,
-                    termion::color::Fg(termion::color::LightBlack),
-                    phrase.comment,
-                    termion::style::Reset,
-                );
-            }
+impl Sound {
+    fn parse(s: &str) -> Self {
+        match s {
+            "" | "none" => Sound::Silent,
+            "bell" => Sound::Bell(1),
+            "double-bell" => Sound::Bell(2),
+            "triple-bell" => Sound::Bell(3),
+            path => Sound::File(PathBuf::from(path)),
         }
-        println!("/");
     }
+}
 
-    fn notify_correct(&mut self, question: &Question, state: &GameState) {
-        let score = state.get_score(&question.entry.term).unwrap_or_default();
-        if state.mistakes == 0 {
-            println!(
-                "{}{}> {} {}(perfect, {} try, {:.}% correct){}",
-                termion::cursor::Up(1),
-                termion::clear::CurrentLine,
-                question.entry.term,
-                termion::color::Fg(termion::color::LightGreen),
-                OrdinalNum(score.total_tries()),
-                (score.correct_rate() * 100.0).round(),
-                termion::style::Reset,
-            );
-        } else {
-            println!(
-                "{}{}> {} {}({} mistakes, {} try, {:.}% correct){}",
-                termion::cursor::Up(1),
-                termion::clear::CurrentLine,
-                question.entry.term,
-                termion::color::Fg(termion::color::LightRed),
-                state.mistakes,
-                OrdinalNum(score.total_tries()),
-                (score.correct_rate() * 100.0).round(),
-                termion::style::Reset,
-            );
+#[derive(Debug)]
+struct SoundTheme {
+    correct: Sound,
+    incorrect: Sound,
+    player: String,
+}
+
+impl SoundTheme {
+    fn silent() -> Self {
+        Self {
+            correct: Sound::Silent,
+            incorrect: Sound::Silent,
+            player: String::new(),
         }
     }
 
-    fn notify_incorrect(&mut self, _question: &Question, _state: &GameState) {
-        println!(
-            "{}{}{}",
-            termion::cursor::Up(1),
-            termion::clear::CurrentLine,
-            termion::cursor::Up(1),
-        );
+    fn from_settings(settings: &Settings) -> Self {
+        let sound = |key| Sound::parse(settings.get_str("theme", key).unwrap_or_default());
+        Self {
+            correct: sound("correct_sound"),
+            incorrect: sound("incorrect_sound"),
+            player: settings
+                .get_str("theme", "sound_player")
+                .unwrap_or("aplay -q")
+                .to_owned(),
+        }
     }
 
-    fn wait_for_input(&mut self, hint: QuestionHint) -> Result<UIResponse, ReadlineError> {
-        self.readline.set_helper(Some(hint));
-        match self.readline.readline("> ") {
-            Ok(input) if input.starts_with(":") => {
-                let command = input.get(1..).unwrap_or_default();
-                if "quit".starts_with(command) {
-                    Ok(UIResponse::Quit)
-                } else {
-                    Ok(UIResponse::Return(input))
+    fn play(&self, sound: &Sound, player: &Player) {
+        match sound {
+            Sound::Silent => {}
+            Sound::Bell(count) => {
+                for i in 0..*count {
+                    if i > 0 {
+                        thread::sleep(time::Duration::from_millis(120));
+                    }
+                    print!("\x07");
+                    io::stdout().flush().ok();
                 }
             }
-            Ok(input) => Ok(UIResponse::Return(input)),
-            Err(ReadlineError::Interrupted | ReadlineError::Eof) => Ok(UIResponse::Quit),
-            Err(error) => Err(error),
+            Sound::File(path) => player.play(&self.player, Path::new(path)),
         }
     }
 }
 
-type Scores = HashMap<String, Score>;
+// Commands that can be bound to keys in the `[keys]` section, as in
+// `star = "F2"`. Keys are written F1 to F12, C-x for Ctrl and M-x for Alt.
+const BINDABLE_COMMANDS: &[&str] = &[
+    "quit", "redraw", "suspend", "star", "stop", "hint", "skip", "undo",
+];
 
-#[derive(Clone, Debug, Default)]
-struct Score {
-    correct: u32,
-    incorrect: u32,
-}
+// The keys of the commands which have one unless bound elsewhere.
+const DEFAULT_KEYS: &[(&str, &str)] = &[("hint", "F1"), ("skip", "F5"), ("undo", "C-z")];
 
-impl Score {
-    fn increment_correct(&self) -> Self {
-        Self {
-            correct: self.correct + 1,
-            incorrect: self.incorrect,
+fn parse_key(key: &str) -> Option<KeyEvent> {
+    let char_key = |s: &str| {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c.to_ascii_lowercase()),
+            _ => None,
         }
+    };
+    if let Some(n) = key.strip_prefix('F').and_then(|n| n.parse().ok()) {
+        (1..=12)
+            .contains(&n)
+            .then_some(KeyEvent(KeyCode::F(n), Modifiers::NONE))
+    } else if let Some(c) = key.strip_prefix("C-") {
+        char_key(c).map(KeyEvent::ctrl)
+    } else {
+        key.strip_prefix("M-").and_then(char_key).map(KeyEvent::alt)
     }
+}
 
-    fn increment_incorrect(&self) -> Self {
-        Self {
-            correct: self.correct,
-            incorrect: self.incorrect + 1,
-        }
-    }
+// With `[ui] single_key = true`, these keys run their commands at once when
+// pressed on an empty line, and are typed as usual anywhere else. Answers
+// starting with them then need another key first, such as a space. Suspending
+// a card is left to `:suspend`, as it is too much to undo for a stray key.
+const SINGLE_KEY_COMMANDS: &[(char, &str)] = &[
+    ('q', "quit"),
+    ('r', "redraw"),
+    ('*', "star"),
+    ('h', "hint"),
+    ('s', "skip"),
+    ('u', "undo"),
+];
 
-    fn correct_rate(&self) -> f32 {
-        let tries = self.correct + self.incorrect;
-        if tries == 0 {
-            1.0
-        } else {
-            self.correct as f32 / tries as f32
+// Accepts the line as it is and leaves the command for `wait_for_input`.
+struct CommandKey {
+    command: &'static str,
+    pressed: Arc<Mutex<Option<&'static str>>>,
+    empty_line_only: bool,
+}
+
+impl ConditionalEventHandler for CommandKey {
+    fn handle(
+        &self,
+        _event: &Event,
+        _n: RepeatCount,
+        _positive: bool,
+        ctx: &EventContext,
+    ) -> Option<Cmd> {
+        if self.empty_line_only && !ctx.line().is_empty() {
+            return None;
         }
+        *self.pressed.lock().unwrap() = Some(self.command);
+        Some(Cmd::AcceptLine)
     }
+}
 
-    fn total_tries(&self) -> u32 {
-        self.correct + self.incorrect
-    }
+struct GameUI {
+    readline: Editor<AnswerHelper>,
+    terminal_size: Option<(u16, u16)>,
+    input_lines: usize,
+    attempt_lines: usize,
+    sounds: SoundTheme,
+    prompt: String,
+    plain: bool,
+    accessible: bool,
+    tts_command: Option<String>,
+    // Sound files and speech go through here, not to hold up the input.
+    player: Player,
+    auto_advance: Option<time::Duration>,
+    show_points: bool,
+    // The best score for the deck before this session, if points are shown.
+    high_score: Option<i64>,
+    // Where example sentences come from after correct answers.
+    corpus: Option<Corpus>,
+    paste_policy: PastePolicy,
+    show_queue_counts: bool,
+    // The command of the bound key that ended the last line, if any.
+    pressed_command: Arc<Mutex<Option<&'static str>>>,
+    // Whether the answer being checked was pasted, to be warned about.
+    paste_warning: bool,
+    // The direction of terms for the whole deck, or `None` to follow each.
+    rtl_terms: Option<bool>,
+    style: Style,
+    emoji: bool,
+    // Prints questions in large letters, with the command if any.
+    big: bool,
+    big_command: Option<String>,
+    // The pause after each letter as the term of a right answer is typed out.
+    typewriter: Option<time::Duration>,
+    // Takes empty answers as wrong ones, which reveal another letter.
+    allow_empty: bool,
+    // Shows the answer on an empty one, to be graded by the player.
+    reveal: bool,
+    aliases: Vec<(String, String)>,
+    // The last answer given, which aliases can repeat.
+    last_answer: String,
 }
 
-struct OrdinalNum(u32);
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum PastePolicy {
+    #[default]
+    Allow,
+    Warn,
+    Reject,
+}
 
-impl fmt::Display for OrdinalNum {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)?;
-        match self.0 {
-            1 => f.write_str("st")?,
-            2 => f.write_str("nd")?,
-            3 => f.write_str("rd")?,
-            _ => f.write_str("th")?,
+// How answers whose first letter alone is in the wrong case are graded, for
+// decks of proper nouns typed with the habits of a phone keyboard:
+//
+//   [matching]
+//   capitalization = "auto"
+//
+// "exact" counts them wrong, "auto" right, and "warn" right with half a
+// mistake and a warning.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Capitalization {
+    #[default]
+    Exact,
+    Auto,
+    Warn,
+}
+
+impl Capitalization {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "exact" => Ok(Self::Exact),
+            "auto" => Ok(Self::Auto),
+            "warn" => Ok(Self::Warn),
+            _ => Err(format!("unknown capitalization: {}", s)),
         }
-        Ok(())
     }
 }
 
-struct GameState {
-    entries: Vec<Rc<Entry>>,
-    scores: Scores,
-    progress: usize,
-    mistakes: usize,
+impl PastePolicy {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "allow" => Ok(Self::Allow),
+            "warn" => Ok(Self::Warn),
+            "reject" => Ok(Self::Reject),
+            _ => Err(format!("unknown paste policy: {}", s)),
+        }
+    }
 }
 
-impl GameState {
-    fn new(entries: Vec<Rc<Entry>>, scores: Scores) -> Self {
+impl GameUI {
+    // Without a terminal to draw on, output is plain, so that it can be piped
+    // or logged. Answers are read from the terminal even when the standard
+    // input isn't one, unless they can be read from the standard input, as
+    // when they are scripted.
+    fn new(sounds: SoundTheme, prompt: String, plain: bool, stdin_answers: bool) -> Self {
+        let plain = plain || !termion::is_tty(&io::stdout());
+        let color_mode = if plain {
+            ColorMode::Disabled
+        } else {
+            ColorMode::Enabled
+        };
+        let behavior = if stdin_answers && !termion::is_tty(&io::stdin()) {
+            Behavior::Stdio
+        } else {
+            Behavior::PreferTerm
+        };
+        let config = Config::builder()
+            .behavior(behavior)
+            .color_mode(color_mode)
+            .build();
+        let mut readline = Editor::<AnswerHelper>::with_config(config);
+        readline.set_helper(Some(AnswerHelper::default()));
         Self {
-            entries,
-            scores,
-            progress: 0,
-            mistakes: 0,
+            readline,
+            terminal_size: termion::terminal_size().ok(),
+            input_lines: 1,
+            attempt_lines: 0,
+            sounds,
+            prompt,
+            plain,
+            accessible: false,
+            tts_command: None,
+            player: Player::new(),
+            auto_advance: None,
+            show_points: false,
+            high_score: None,
+            corpus: None,
+            paste_policy: PastePolicy::Allow,
+            show_queue_counts: false,
+            pressed_command: Arc::new(Mutex::new(None)),
+            paste_warning: false,
+            rtl_terms: None,
+            style: Style::default(),
+            emoji: !plain && supports_emoji(),
+            big: false,
+            big_command: None,
+            typewriter: None,
+            allow_empty: false,
+            reveal: false,
+            aliases: Vec::new(),
+            last_answer: String::new(),
         }
     }
 
-    fn next_question(&mut self) -> Option<Question> {
-        if self.progress < self.entries.len() {
-            let i = self.progress;
-            self.progress += 1;
-            self.mistakes = 0;
-            Some(Question {
-                index: i,
-                entry: self.entries[i].clone(),
-            })
+    // Prints a full sentence and reads it out with the TTS command, which
+    // receives the text on its standard input. Sentences are queued behind
+    // the one being read out.
+    fn say(&mut self, text: &str) {
+        println!("{}", text);
+        io::stdout().flush().ok();
+        if let Some(tts_command) = &self.tts_command {
+            self.player.speak(tts_command, text);
+        }
+    }
+
+    fn isolate_term<'t>(&self, term: &'t str) -> Cow<'t, str> {
+        match self.rtl_terms {
+            Some(rtl) => bidi::isolate_as(term, rtl),
+            None => bidi::isolate(term),
+        }
+    }
+
+    fn stop_audio(&mut self) {
+        self.overwrite_command();
+        self.player.stop();
+    }
+
+    // In plain mode every escape sequence is dropped and nothing is
+    // overwritten, so that screen readers can follow the output as it goes.
+    fn ansi<T: fmt::Display>(&self, sequence: T) -> String {
+        if self.plain {
+            String::new()
         } else {
-            None
+            sequence.to_string()
         }
     }
 
-    fn answer_question(&mut self, question: &Question, answer: String) -> bool {
-        use std::collections::hash_map::Entry;
-        let is_correct = question.entry.term == answer;
-        if is_correct {
-            match self.scores.entry(answer) {
-                Entry::Occupied(mut entry) => {
-                    let score = if self.mistakes == 0 {
-                        entry.get().increment_correct()
-                    } else {
-                        entry.get().increment_incorrect()
-                    };
-                    entry.insert(score);
-                }
-                Entry::Vacant(entry) => {
-                    let score = if self.mistakes == 0 {
-                        Score {
-                            correct: 1,
-                            incorrect: 0,
-                        }
-                    } else {
-                        Score {
-                            correct: 0,
-                            incorrect: 1,
-                        }
-                    };
-                    entry.insert(score);
-                }
-            }
+    // Moves back over the input line so that the answer replaces it.
+    fn overwrite_input(&self) -> String {
+        if self.plain {
+            String::new()
         } else {
-            self.mistakes += 1;
+            format!(
+                "{}{}{}",
+                termion::cursor::Up(self.input_lines as u16),
+                termion::clear::AfterCursor,
+                self.prompt
+            )
         }
-        is_correct
     }
 
-    fn get_score(&self, term: &str) -> Option<Score> {
-        self.scores.get(term).cloned()
+    fn terminal_width(&self) -> usize {
+        termion::terminal_size()
+            .map(|(width, _)| width as usize)
+            .unwrap_or(80)
+            .max(1)
     }
-}
 
-enum UIResponse {
-    Return(String),
-    Quit,
-}
+    fn count_lines(&self, text: &str) -> usize {
+        let width = self.terminal_width();
+        text.split('\n')
+            .map(|line| line.width().div_ceil(width).max(1))
+            .sum()
+    }
 
-fn load_entries<R: Read>(handle: R) -> io::Result<Vec<Rc<Entry>>> {
-    let reader = BufReader::new(handle);
-    let mut entries = vec![];
-    for line in reader.lines() {
-        if let Some(entry) = Entry::parse(line?.chars().peekable()) {
-            entries.push(Rc::new(entry))
+    fn redraw(&mut self, question: &Question, state: &GameState) {
+        print!(
+            "{}{}",
+            self.ansi(termion::clear::All),
+            self.ansi(termion::cursor::Goto(1, 1))
+        );
+        self.notify_question(question, state);
+        if !self.accessible && !state.wrong_answers.is_empty() {
+            self.print_wrong_answers(&state.wrong_answers);
         }
     }
-    Ok(entries)
-}
 
-fn load_scores<P: AsRef<Path>>(path: P) -> io::Result<Scores> {
-    let mut scores = HashMap::new();
-    if path.as_ref().exists() {
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
+    // rustyline owns SIGWINCH while a line is being read, so a resize is
+    // detected by comparing the terminal size before each prompt instead.
+    fn is_resized(&mut self) -> bool {
+        let size = termion::terminal_size().ok();
+        size.is_some() && mem::replace(&mut self.terminal_size, size) != size
+    }
+
+    fn notify_question(&mut self, question: &Question, state: &GameState) {
+        let entry = state.entry(question);
+        self.attempt_lines = 0;
+        if self.accessible {
+            let mut described = state.difficulty_badge(entry.key()).describe().to_owned();
+            for badge in state.state_badges(entry.key()) {
+                if badge != StateBadge::New {
+                    described.push_str(", ");
+                    described.push_str(badge.describe());
+                }
+            }
+            let phrases: Vec<String> = state
+                .shown_phrases(question)
+                .map(|phrase| {
+                    if phrase.comment.is_empty() {
+                        phrase.body.clone()
+                    } else {
+                        format!("{} ({})", phrase.body, phrase.comment)
+                    }
+                })
+                .collect();
+            let mut text = if let QuestionKind::Reverse = question.kind {
+                format!(
+                    "Question {}, {}: what does {} mean?",
+                    question.index + 1,
+                    described,
+                    entry.term
+                )
+            } else {
+                format!(
+                    "Question {}, {}: {}.",
+                    question.index + 1,
+                    described,
+                    phrases.join("; ")
+                )
+            };
+            if self.show_queue_counts {
+                let counts = state.queue_counts(question);
+                text.push_str(&format!(
+                    " {} new, {} learning and {} due.",
+                    counts.new, counts.learning, counts.due
+                ));
+            }
+            if let Some(secs) = state.estimated_remaining_secs() {
+                text.push_str(&format!(" About {} left.", HumanDuration(secs)));
+            }
+            if self.show_points {
+                text.push_str(&format!(" {} points.", state.points));
+            }
+            match &question.kind {
+                QuestionKind::Arrange(words) => {
+                    text.push_str(&format!(" Arrange the words: {}.", words.join(", ")))
+                }
+                QuestionKind::Choice(choices) => {
+                    text.push_str(&format!(" Choose: {}.", format_numbered(choices)))
+                }
+                QuestionKind::Recall | QuestionKind::Reverse => {}
+            }
+            if let Some(note) = state.note(question) {
+                text.push_str(&format!(" Your note: {}.", note));
+            }
+            self.say(&text);
+            return;
+        }
+        for _ in 0..self.style.line_spacing {
+            println!();
+        }
+        let width = self.terminal_width();
+        let label = format!("Q{}", question.index + 1);
+        let badge = state.difficulty_badge(entry.key());
+        let state_badges: String = state
+            .state_badges(entry.key())
+            .iter()
+            .map(|badge| {
+                if self.emoji {
+                    badge.emoji()
+                } else {
+                    badge.ascii()
+                }
+            })
+            .collect();
+        let mut status: Vec<String> = Vec::new();
+        status.extend(
+            state
+                .estimated_remaining_secs()
+                .map(|secs| format!("~{} left", HumanDuration(secs))),
+        );
+        status.extend(
+            state
+                .autosave
+                .as_ref()
+                .and_then(Autosave::remaining_answers)
+                .map(|answers| format!("saves in {}", answers)),
+        );
+        if self.show_points {
+            status.push(format!("{} points", state.points));
+        }
+        let eta = status.join(", ");
+        if !state_badges.is_empty() {
+            print!("{} ", state_badges);
+        }
+        print!(
+            "{}{}{}{} {} ",
+            self.ansi(termion::style::Bold),
+            self.ansi(termion::color::Fg(termion::color::LightYellow)),
+            label,
+            self.ansi(termion::style::Reset),
+            if self.plain {
+                badge.label().to_owned()
+            } else {
+                badge.to_string()
+            },
+        );
+        let mut indent = label.width() + 1 + badge.label().width() + 1;
+        if !state_badges.is_empty() {
+            indent += state_badges.width() + 1;
+        }
+        if self.show_queue_counts {
+            let counts = state.queue_counts(question);
+            if self.plain {
+                let text = format!(
+                    "new {}, learning {}, due {}",
+                    counts.new, counts.learning, counts.due
+                );
+                print!("{} ", text);
+                indent += text.width() + 1;
+            } else {
+                // Colored like Anki's counters.
+                print!(
+                    "{}{} {}{} {}{}{} ",
+                    termion::color::Fg(termion::color::LightBlue),
+                    counts.new,
+                    termion::color::Fg(termion::color::LightRed),
+                    counts.learning,
+                    termion::color::Fg(termion::color::LightGreen),
+                    counts.due,
+                    termion::style::Reset,
+                );
+                indent += format!("{} {} {}", counts.new, counts.learning, counts.due).width() + 1;
+            }
+        }
+        if !eta.is_empty() {
+            print!(
+                "{}{}{} ",
+                self.ansi(termion::color::Fg(termion::color::LightBlack)),
+                eta,
+                self.ansi(termion::style::Reset),
+            );
+            indent += eta.width() + 1;
+        }
+        if self.big {
+            let text = if let QuestionKind::Reverse = question.kind {
+                format!("{} = ?", entry.term)
+            } else {
+                let separator = &self.style.separator;
+                let bodies: Vec<&str> = state
+                    .shown_phrases(question)
+                    .map(|phrase| phrase.body.as_str())
+                    .collect();
+                format!("{}{}{}", separator, bodies.join(separator), separator)
+            };
+            println!();
+            self.print_big(&text);
+        } else if let QuestionKind::Reverse = question.kind {
+            println!(
+                "{}{}{} = ?",
+                self.ansi(termion::style::Bold),
+                self.isolate_term(&self.style.spaced(&entry.term)),
+                self.ansi(termion::style::Reset),
+            );
+        } else {
+            let separator = &self.style.separator;
+            let separator_width = separator.width();
+            let mut column = indent;
+            for phrase in state.shown_phrases(question) {
+                let body = self.style.spaced(&phrase.body);
+                let comment = match self.style.comments {
+                    style::Comments::Inline => &*phrase.comment,
+                    style::Comments::Deferred | style::Comments::Hidden => "",
+                };
+                let mut phrase_width = separator_width + body.width();
+                if !comment.is_empty() {
+                    phrase_width += 1 + comment.width();
+                }
+                // Reserve columns for the closing separator.
+                if column > indent && column + phrase_width + separator_width > width {
+                    print!("\n{:1$}", "", indent);
+                    column = indent;
+                }
+                column += phrase_width;
+                if comment.is_empty() {
+                    print!(
+                        "{}{}{}{}{}",
+                        separator,
+                        self.ansi(termion::style::Bold),
+                        self.ansi(termion::color::Fg(termion::color::LightBlue)),
+                        bidi::isolate(&body),
+                        self.ansi(termion::style::Reset),
+                    );
+                } else {
+                    print!(
+                        "{}{}{}{}{};{}{}",
+                        separator,
+                        self.ansi(termion::style::Bold),
+                        self.ansi(termion::color::Fg(termion::color::LightBlue)),
+                        bidi::isolate(&body),
+                        self.ansi(termion::color::Fg(termion::color::LightBlack)),
+                        bidi::isolate(comment),
+                        self.ansi(termion::style::Reset),
+                    );
+                }
+            }
+            println!("{}", separator);
+        }
+        if let QuestionKind::Arrange(words) | QuestionKind::Choice(words) = &question.kind {
+            let numbered: Vec<String> = words
+                .iter()
+                .enumerate()
+                .map(|(i, word)| {
+                    format!(
+                        "{}{}{} {}",
+                        self.ansi(termion::style::Faint),
+                        i + 1,
+                        self.ansi(termion::style::Reset),
+                        self.isolate_term(word),
+                    )
+                })
+                .collect();
+            println!("{}", numbered.join("  "));
+        }
+        if let Some(note) = state.note(question) {
+            self.print_note(note);
+        }
+    }
+
+    // The command, such as figlet, gets the text on its standard input and
+    // prints it. Otherwise each line is printed twice, as the top and bottom
+    // halves of double-height letters, which take two columns each.
+    // A command that fails falls back to the double-height letters.
+    fn print_big(&self, text: &str) {
+        if let Some(output) = self.big_command.as_deref().and_then(|command| {
+            let mut words = command.split_whitespace();
+            let mut child = process::Command::new(words.next()?)
+                .args(words)
+                .stdin(process::Stdio::piped())
+                .stdout(process::Stdio::piped())
+                .stderr(process::Stdio::null())
+                .spawn()
+                .ok()?;
+            child.stdin.take()?.write_all(text.as_bytes()).ok()?;
+            let output = child.wait_with_output().ok()?;
+            if output.status.success() {
+                String::from_utf8(output.stdout).ok()
+            } else {
+                None
+            }
+        }) {
+            println!("{}", output.trim_end_matches('\n'));
+            return;
+        }
+        if self.plain {
+            println!("{}", text);
+            return;
+        }
+        let columns = (self.terminal_width() / 2).max(1);
+        let mut line = String::new();
+        for word in text.split(' ') {
+            if !line.is_empty() && line.width() + 1 + word.width() > columns {
+                println!("\x1b#3{}\n\x1b#4{}", line, line);
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        println!("\x1b#3{}\n\x1b#4{}", line, line);
+    }
+
+    fn print_note(&self, note: &str) {
+        println!(
+            "{}note: {}{}",
+            self.ansi(termion::style::Faint),
+            note,
+            self.ansi(termion::style::Reset),
+        );
+    }
+
+    fn bind_keys(&mut self, settings: &Settings) -> Result<(), String> {
+        for &command in BINDABLE_COMMANDS {
+            let key = settings.get_str("keys", command).or_else(|| {
+                DEFAULT_KEYS
+                    .iter()
+                    .find(|(other, _)| *other == command)
+                    .map(|(_, key)| *key)
+            });
+            let Some(key) = key else {
+                continue;
+            };
+            let key_event = parse_key(key).ok_or_else(|| format!("invalid key: {}", key))?;
+            let handler = CommandKey {
+                command,
+                pressed: Arc::clone(&self.pressed_command),
+                empty_line_only: false,
+            };
+            self.readline
+                .bind_sequence(key_event, EventHandler::Conditional(Box::new(handler)));
+        }
+        if settings.get_bool("ui", "single_key").unwrap_or(false) {
+            for &(key, command) in SINGLE_KEY_COMMANDS {
+                let handler = CommandKey {
+                    command,
+                    pressed: Arc::clone(&self.pressed_command),
+                    empty_line_only: true,
+                };
+                self.readline.bind_sequence(
+                    KeyEvent::new(key, Modifiers::NONE),
+                    EventHandler::Conditional(Box::new(handler)),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    // Shortcuts for answers and commands, set for a deck in its settings as
+    // in:
+    //
+    //   [aliases]
+    //   "=" = "{previous}"
+    //   "?" = ":hint"
+    //   ":n" = ":note"
+    //
+    // A line that is an alias is replaced with what it stands for, where
+    // {previous} is the last answer given. Aliases starting with ":" also take
+    // the rest of a line after a space, so that ":n hard one" adds a note,
+    // while other aliases never eat into an answer that starts like them.
+    fn set_aliases(&mut self, settings: &Settings) {
+        self.aliases = settings
+            .keys("aliases")
+            .filter_map(|alias| {
+                let expansion = settings.get_str("aliases", alias)?;
+                Some((alias.to_owned(), expansion.to_owned()))
+            })
+            .collect();
+        // The longest first, so that none hides a longer one it begins.
+        self.aliases
+            .sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then(a.cmp(b)));
+    }
+
+    fn expand_alias(&self, input: String) -> String {
+        let line = input.trim();
+        for (alias, expansion) in &self.aliases {
+            let rest = if line == alias {
+                ""
+            } else {
+                match line.strip_prefix(alias.as_str()) {
+                    Some(rest) if alias.starts_with(':') && rest.starts_with(' ') => rest,
+                    _ => continue,
+                }
+            };
+            return format!(
+                "{}{}",
+                expansion.replace("{previous}", &self.last_answer),
+                rest
+            );
+        }
+        input
+    }
+
+    fn pasted(&self) -> bool {
+        self.paste_policy != PastePolicy::Allow
+            && self.readline.helper().is_some_and(|hint| hint.pasted.get())
+    }
+
+    // Pasted answers are still checked, with a warning after the result.
+    fn warn_if_pasted(&mut self) -> bool {
+        if !mem::take(&mut self.paste_warning) {
+            return false;
+        }
+        if self.accessible {
+            self.say("That answer looked pasted.");
+        } else {
+            println!(
+                "{}(that answer looked pasted){}",
+                self.ansi(termion::color::Fg(termion::color::LightYellow)),
+                self.ansi(termion::style::Reset),
+            );
+        }
+        true
+    }
+
+    // Confirmations of commands take the place of the command, below the
+    // attempts.
+    fn overwrite_command(&mut self) {
+        if !self.plain {
+            print!(
+                "{}{}",
+                termion::cursor::Up(self.input_lines as u16),
+                termion::clear::AfterCursor
+            );
+        }
+        self.attempt_lines += 1;
+    }
+
+    fn notify_note(&mut self, question: &Question, state: &GameState) {
+        let note = state.note(question);
+        if self.accessible {
+            match note {
+                Some(note) => self.say(&format!("Noted: {}.", note)),
+                None => self.say("Note removed."),
+            }
+            return;
+        }
+        self.overwrite_command();
+        self.print_note(note.unwrap_or("(removed)"));
+    }
+
+    fn notify_hint(&mut self, question: &Question, state: &GameState, taken: bool) {
+        let hint = mask_term(&state.entry(question).term, state.mistakes, state.script);
+        if self.accessible {
+            self.say(if taken {
+                "Another letter is shown."
+            } else {
+                "There are no more hints for this question."
+            });
+            return;
+        }
+        self.overwrite_command();
+        if taken {
+            println!(
+                "{}hint: {}{}",
+                self.ansi(termion::style::Faint),
+                self.isolate_term(&hint),
+                self.ansi(termion::style::Reset),
+            );
+        } else {
+            println!(
+                "{}no more hints for this question{}",
+                self.ansi(termion::style::Faint),
+                self.ansi(termion::style::Reset),
+            );
+        }
+    }
+
+    fn notify_starred(&mut self, question: &Question, state: &GameState) {
+        let starred = state.is_starred(question);
+        if self.accessible {
+            self.say(if starred { "Starred." } else { "Unstarred." });
+            return;
+        }
+        self.overwrite_command();
+        println!(
+            "{}{}{}",
+            self.ansi(termion::style::Faint),
+            if starred {
+                "\u{2605} starred"
+            } else {
+                "\u{2606} unstarred"
+            },
+            self.ansi(termion::style::Reset),
+        );
+    }
+
+    fn notify_skipped(&mut self) {
+        if self.accessible {
+            self.say("Skipped.");
+            return;
+        }
+        self.overwrite_command();
+        println!(
+            "{}skipped{}",
+            self.ansi(termion::style::Faint),
+            self.ansi(termion::style::Reset),
+        );
+    }
+
+    fn notify_undone(&mut self, result: Result<(), &str>) {
+        let message = match result {
+            Ok(()) => "undone, asking the previous question again",
+            Err(message) => message,
+        };
+        if self.accessible {
+            self.say(&format!(
+                "{}{}.",
+                message[..1].to_uppercase(),
+                &message[1..]
+            ));
+            return;
+        }
+        self.overwrite_command();
+        println!(
+            "{}{}{}",
+            self.ansi(termion::style::Faint),
+            message,
+            self.ansi(termion::style::Reset),
+        );
+    }
+
+    fn notify_correct(&mut self, question: &Question, state: &GameState) {
+        let entry = state.entry(question);
+        let score = state.get_score(entry.key()).unwrap_or_default();
+        let correct_rate = state.get_correct_rate(entry.key());
+        if self.accessible {
+            let mistakes = state.graded_mistakes();
+            let result = if mistakes == 0.0 {
+                "perfect".to_owned()
+            } else if mistakes == 1.0 {
+                "after 1 mistake".to_owned()
+            } else {
+                format!("after {} mistakes", mistakes)
+            };
+            self.say(&format!(
+                "Correct, {}: {}. This was your {} try, {} percent correct overall.",
+                result,
+                entry.term,
+                OrdinalNum(score.total_tries()),
+                (correct_rate * 100.0).round()
+            ));
+        } else if state.mistakes == 0 {
+            print!("{}", self.overwrite_input());
+            self.type_out(&self.isolate_term(&entry.term));
+            println!(
+                " {}(perfect, {} try, {:.}% correct){}",
+                self.ansi(termion::color::Fg(termion::color::LightGreen)),
+                OrdinalNum(score.total_tries()),
+                (correct_rate * 100.0).round(),
+                self.ansi(termion::style::Reset),
+            );
+        } else {
+            print!("{}", self.overwrite_input());
+            self.type_out(&self.isolate_term(&entry.term));
+            println!(
+                " {}({} mistakes, {} try, {:.}% correct){}",
+                self.ansi(termion::color::Fg(termion::color::LightRed)),
+                state.graded_mistakes(),
+                OrdinalNum(score.total_tries()),
+                (correct_rate * 100.0).round(),
+                self.ansi(termion::style::Reset),
+            );
+        }
+        let hidden: Vec<&str> = state
+            .hidden_phrases(question)
+            .map(|phrase| phrase.body.as_str())
+            .collect();
+        if !hidden.is_empty() {
+            let reverse = matches!(question.kind, QuestionKind::Reverse);
+            if self.accessible {
+                self.say(&format!(
+                    "It {}means: {}.",
+                    if reverse { "" } else { "also " },
+                    hidden.join("; ")
+                ));
+            } else {
+                let separator = &self.style.separator;
+                println!(
+                    "{}  {} {}{}{}{}",
+                    self.ansi(termion::style::Faint),
+                    if reverse { "means" } else { "also" },
+                    separator,
+                    hidden.join(separator),
+                    separator,
+                    self.ansi(termion::style::Reset),
+                );
+            }
+        }
+        if self.style.comments == style::Comments::Deferred && !self.accessible {
+            let comments: Vec<&str> = state
+                .shown_phrases(question)
+                .map(|phrase| &*phrase.comment)
+                .filter(|comment| !comment.is_empty())
+                .collect();
+            if !comments.is_empty() {
+                println!(
+                    "{}  ({}){}",
+                    self.ansi(termion::style::Faint),
+                    comments.join("; "),
+                    self.ansi(termion::style::Reset),
+                );
+            }
+        }
+        self.warn_if_pasted();
+        if !state.confused.is_empty() {
+            let replaced: Vec<String> = state
+                .confused
+                .iter()
+                .map(|(typed, term)| format!("{} for {}", typed, term))
+                .collect();
+            if self.accessible {
+                self.say(
+                    "Some letters were typed in another script, as with another keyboard layout.",
+                );
+            } else {
+                println!(
+                    "{}(typed with lookalike letters: {}){}",
+                    self.ansi(termion::color::Fg(termion::color::LightYellow)),
+                    replaced.join(", "),
+                    self.ansi(termion::style::Reset),
+                );
+            }
+        }
+        if let Some((layout, answer)) = &state.retyped {
+            if self.accessible {
+                self.say(&format!("Taken as typed on the {} layout.", layout));
+            } else {
+                println!(
+                    "{}({} taken as typed on the {} layout){}",
+                    self.ansi(termion::color::Fg(termion::color::LightYellow)),
+                    answer,
+                    layout,
+                    self.ansi(termion::style::Reset),
+                );
+            }
+        }
+        if state.miscapitalized {
+            if self.accessible {
+                self.say("Mind the capital letter.");
+            } else {
+                println!(
+                    "{}(mind the capital letter){}",
+                    self.ansi(termion::color::Fg(termion::color::LightYellow)),
+                    self.ansi(termion::style::Reset),
+                );
+            }
+        }
+        self.show_example(&entry.term);
+        self.sounds.play(&self.sounds.correct, &self.player);
+    }
+
+    fn show_example(&mut self, term: &str) {
+        let example = match &self.corpus {
+            Some(corpus) => corpus.example(term).unwrap_or_else(|error| {
+                eprintln!("read corpus: {}", error);
+                None
+            }),
+            None => None,
+        };
+        let Some(example) = example else {
+            return;
+        };
+        if self.accessible {
+            self.say(&format!("For example: {}", example));
+        } else {
+            println!(
+                "{}  {}{}",
+                self.ansi(termion::style::Faint),
+                self.isolate_term(&example),
+                self.ansi(termion::style::Reset),
+            );
+        }
+    }
+
+    // Marks and other characters of no width come with the letter before them.
+    fn type_out(&self, text: &str) {
+        let Some(pause) = self.typewriter else {
+            print!("{}", text);
+            return;
+        };
+        let mut stdout = io::stdout();
+        for c in text.chars() {
+            if c.width().unwrap_or(0) > 0 {
+                stdout.flush().ok();
+                thread::sleep(pause);
+            }
+            print!("{}", c);
+        }
+    }
+
+    // Leaves the confirmation on the screen for a moment before the next
+    // question is shown.
+    fn wait_before_next(&self) {
+        if let Some(delay) = self.auto_advance {
+            io::stdout().flush().ok();
+            thread::sleep(delay);
+        }
+    }
+
+    fn notify_incorrect(&mut self, question: &Question, answer: &str, state: &GameState) {
+        let entry = state.entry(question);
+        let answer = &*question.resolve_answer(answer);
+        let earlier = match state.wrong_answers.split_last() {
+            Some((_, earlier)) => earlier,
+            None => &[],
+        };
+        if self.accessible {
+            let mut text = if let (QuestionKind::Arrange(_), Some(in_order)) =
+                (&question.kind, &state.partial_answer)
+            {
+                format!(
+                    "Incorrect, {} of {} words in order, try again.",
+                    in_order.iter().filter(|&&in_order| in_order).count(),
+                    entry.term.split_whitespace().count(),
+                )
+            } else if state.partial_answer.is_some() {
+                "Incorrect, one word is wrong, try again.".to_owned()
+            } else {
+                "Incorrect, try again.".to_owned()
+            };
+            if let Some(count) = state.recurring_typo(entry, answer) {
+                text.push_str(&format!(
+                    " You have written {} {} times.",
+                    answer.trim(),
+                    count
+                ));
+            }
+            if !earlier.is_empty() {
+                text.push_str(&format!(" Earlier attempts: {}.", earlier.join(", ")));
+            }
+            self.say(&text);
+        } else {
+            let expected = state.expected_answer(question);
+            let mut line = match (&question.kind, &state.partial_answer) {
+                (QuestionKind::Arrange(_), Some(in_order)) => {
+                    self.format_arranged(answer, expected, in_order)
+                }
+                (_, Some(right)) => self.format_partial(answer, expected, right),
+                (_, None) => format_diff(answer, expected, self.plain),
+            };
+            if let Some(count) = state.recurring_typo(entry, answer) {
+                line.push_str(&format!(
+                    " {}(you keep writing \"{}\", {} times){}",
+                    self.ansi(termion::color::Fg(termion::color::LightYellow)),
+                    answer.trim(),
+                    count,
+                    self.ansi(termion::style::Reset),
+                ));
+            }
+            if self.plain {
+                println!("incorrect: {}", line);
+            } else {
+                // The diff of the previous attempt is folded into the list
+                // of earlier attempts above it.
+                println!(
+                    "{}{}{}",
+                    termion::cursor::Up((self.input_lines + self.attempt_lines) as u16),
+                    termion::clear::AfterCursor,
+                    line,
+                );
+                self.attempt_lines = 1;
+                if !earlier.is_empty() {
+                    self.print_wrong_answers(earlier);
+                }
+            }
+        }
+        if self.warn_if_pasted() {
+            self.attempt_lines += 1;
+        }
+        self.sounds.play(&self.sounds.incorrect, &self.player);
+    }
+
+    // The right words in green, and the diff of the wrong one.
+    fn format_partial(&self, answer: &str, term: &str, right: &[bool]) -> String {
+        let answer = normalize_whitespace(answer);
+        let term = normalize_whitespace(term);
+        let words: Vec<String> = answer
+            .split(' ')
+            .zip(term.split(' '))
+            .zip(right)
+            .map(|((answer_word, term_word), &right)| {
+                if right {
+                    format!(
+                        "{}{}{}",
+                        self.ansi(termion::color::Fg(termion::color::LightGreen)),
+                        answer_word,
+                        self.ansi(termion::style::Reset),
+                    )
+                } else {
+                    format_diff(answer_word, term_word, self.plain)
+                }
+            })
+            .collect();
+        format!(
+            "{} {}(one word wrong, half a mistake){}",
+            words.join(" "),
+            self.ansi(termion::style::Faint),
+            self.ansi(termion::style::Reset),
+        )
+    }
+
+    // The words in the order of the term's in green, and the others in red.
+    fn format_arranged(&self, answer: &str, term: &str, in_order: &[bool]) -> String {
+        let words: Vec<String> = answer
+            .split_whitespace()
+            .zip(in_order)
+            .map(|(word, &in_order)| {
+                if self.plain && !in_order {
+                    format!("[-{}-]", word)
+                } else {
+                    format!(
+                        "{}{}{}",
+                        if in_order {
+                            self.ansi(termion::color::Fg(termion::color::LightGreen))
+                        } else {
+                            self.ansi(termion::color::Fg(termion::color::LightRed))
+                        },
+                        word,
+                        self.ansi(termion::style::Reset),
+                    )
+                }
+            })
+            .collect();
+        let term_words = term.split_whitespace().count();
+        let count = in_order.iter().filter(|&&in_order| in_order).count();
+        format!(
+            "{} {}({} of {} words in order{}){}",
+            words.join(" "),
+            self.ansi(termion::style::Faint),
+            count,
+            term_words,
+            if count + 1 == term_words && in_order.len() == term_words {
+                ", half a mistake"
+            } else {
+                ""
+            },
+            self.ansi(termion::style::Reset),
+        )
+    }
+
+    fn print_wrong_answers(&mut self, answers: &[String]) {
+        let line = format!("tried: {}", answers.join(", "));
+        self.attempt_lines += self.count_lines(&line);
+        println!(
+            "{}{}{}",
+            self.ansi(termion::style::Faint),
+            line,
+            self.ansi(termion::style::Reset),
+        );
+    }
+
+    fn notify_suspended(&mut self, question: &Question, state: &GameState) {
+        let entry = state.entry(question);
+        if self.accessible {
+            self.say(&format!("Suspended {}.", entry.term));
+            return;
+        }
+        println!(
+            "{}{} {}(suspended){}",
+            self.overwrite_input(),
+            self.isolate_term(&entry.term),
+            self.ansi(termion::style::Faint),
+            self.ansi(termion::style::Reset),
+        );
+    }
+
+    // Shows the answer to a question flipped with Enter, and asks whether it
+    // was known. Returns `None` to quit.
+    fn ask_remembered(
+        &mut self,
+        question: &Question,
+        state: &GameState,
+    ) -> Result<Option<bool>, ReadlineError> {
+        let answer = match question.kind {
+            QuestionKind::Reverse => state
+                .hidden_phrases(question)
+                .map(|phrase| phrase.body.as_str())
+                .collect::<Vec<_>>()
+                .join("; "),
+            _ => state.entry(question).term.to_string(),
+        };
+        if self.accessible {
+            self.say(&format!("The answer is {}. Did you know it?", answer));
+        } else {
+            println!(
+                "{}{}{}{}",
+                self.overwrite_input(),
+                self.ansi(termion::style::Bold),
+                self.isolate_term(&answer),
+                self.ansi(termion::style::Reset),
+            );
+        }
+        self.set_question(QuestionHint::default());
+        loop {
+            match self.readline.readline("did you know it? (y/n) ") {
+                Ok(line) => match line.trim().to_lowercase().as_str() {
+                    "y" | "yes" => return Ok(Some(true)),
+                    "n" | "no" => return Ok(Some(false)),
+                    ":q" | ":quit" => return Ok(None),
+                    _ => {}
+                },
+                Err(ReadlineError::Interrupted | ReadlineError::Eof) => return Ok(None),
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    fn review_mistakes(
+        &mut self,
+        wrong: &[DeferredAnswer],
+        state: &GameState,
+    ) -> Result<(), ReadlineError> {
+        self.set_question(QuestionHint::default());
+        for (i, deferred) in wrong.iter().enumerate() {
+            let entry = &state.deck[deferred.entry];
+            let phrases: Vec<&str> = entry
+                .phrases
+                .iter()
+                .map(|phrase| phrase.body.as_str())
+                .collect();
+            if self.accessible {
+                self.say(&format!(
+                    "Mistake {} of {}: {}. You answered {}, the answer is {}.",
+                    i + 1,
+                    wrong.len(),
+                    phrases.join("; "),
+                    deferred.answer,
+                    entry.term
+                ));
+            } else {
+                println!(
+                    "{}Mistake {}/{}{} /{}/",
+                    self.ansi(termion::style::Bold),
+                    i + 1,
+                    wrong.len(),
+                    self.ansi(termion::style::Reset),
+                    phrases.join("/"),
+                );
+                println!(
+                    "  yours:   {}{}{}",
+                    self.ansi(termion::color::Fg(termion::color::LightRed)),
+                    self.isolate_term(&deferred.answer),
+                    self.ansi(termion::style::Reset),
+                );
+                println!(
+                    "  correct: {}{}{}",
+                    self.ansi(termion::color::Fg(termion::color::LightGreen)),
+                    self.isolate_term(&entry.term),
+                    self.ansi(termion::style::Reset),
+                );
+            }
+            if i + 1 < wrong.len() {
+                match self.readline.readline("(enter for the next mistake) ") {
+                    Ok(_) => {}
+                    Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+                    Err(error) => return Err(error),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // The UI's part in every event, before the other observers'.
+    fn observe(&mut self, event: &events::Event, state: &GameState) {
+        match *event {
+            events::Event::QuestionShown(question) => self.notify_question(question, state),
+            events::Event::AnswerGraded {
+                question,
+                correct: true,
+                ..
+            } => self.notify_correct(question, state),
+            events::Event::AnswerGraded {
+                question,
+                answer,
+                correct: false,
+            } => self.notify_incorrect(question, answer, state),
+            // The answer was shown before it was graded.
+            events::Event::SelfGraded { .. } => {}
+            events::Event::SessionEnded(_) => self.notify_summary(state),
+        }
+    }
+
+    fn notify_summary(&mut self, state: &GameState) {
+        let answered = state.new_attempts.len();
+        if answered == 0 {
+            return;
+        }
+        let perfect = state.perfect_count();
+        let maturity = match state.days_until_deck_mature() {
+            Some(days) if days < 1.0 => "mature".to_owned(),
+            Some(days) => format!("~{} days to go", days.ceil()),
+            None => "not reachable at the current accuracy".to_owned(),
+        };
+        if self.accessible {
+            self.say(&format!(
+                "Session finished: {} questions, {} perfect, {:.0} percent. {} of {} cards are mature, {}.",
+                answered,
+                perfect,
+                stats::percentage(perfect, answered),
+                state.mature_count(),
+                state.entries.len(),
+                maturity.trim_start_matches('~'),
+            ));
+            if self.show_points {
+                let points = state.points;
+                // A deck's first score has nothing to beat, so it isn't
+                // called a high score.
+                match self.high_score {
+                    Some(best) if points > best => self.say(&format!(
+                        "{} points, a new high score! It was {}.",
+                        points, best
+                    )),
+                    Some(best) => self.say(&format!("{} points, high score {}.", points, best)),
+                    None => self.say(&format!("{} points.", points)),
+                }
+            }
+            return;
+        }
+        println!(
+            "{}Session:{} {} questions, {} perfect ({:.0}%) {}{}{}",
+            self.ansi(termion::style::Bold),
+            self.ansi(termion::style::Reset),
+            answered,
+            perfect,
+            stats::percentage(perfect, answered),
+            self.ansi(termion::style::Faint),
+            state.session,
+            self.ansi(termion::style::Reset),
+        );
+        println!(
+            "{}Deck:{} {}/{} cards mature, {}",
+            self.ansi(termion::style::Bold),
+            self.ansi(termion::style::Reset),
+            state.mature_count(),
+            state.entries.len(),
+            maturity,
+        );
+        if self.show_points {
+            let points = state.points;
+            match self.high_score {
+                Some(best) if points > best => {
+                    println!(
+                        "{}Points:{} {} {}{}New high score!{} (was {})",
+                        self.ansi(termion::style::Bold),
+                        self.ansi(termion::style::Reset),
+                        points,
+                        self.ansi(termion::style::Bold),
+                        self.ansi(termion::color::Fg(termion::color::LightYellow)),
+                        self.ansi(termion::style::Reset),
+                        best,
+                    );
+                    self.sounds.play(&self.sounds.correct, &self.player);
+                }
+                Some(best) => println!(
+                    "{}Points:{} {} (high score {})",
+                    self.ansi(termion::style::Bold),
+                    self.ansi(termion::style::Reset),
+                    points,
+                    best
+                ),
+                None => println!(
+                    "{}Points:{} {}",
+                    self.ansi(termion::style::Bold),
+                    self.ansi(termion::style::Reset),
+                    points,
+                ),
+            }
+        }
+    }
+
+    fn notify_achievements(&mut self, achievements: &[&achievements::Achievement]) {
+        for achievement in achievements {
+            if self.accessible {
+                self.say(&format!("Achievement unlocked: {}.", achievement.name));
+            } else {
+                println!(
+                    "{}{}Achievement unlocked:{} {}",
+                    self.ansi(termion::style::Bold),
+                    self.ansi(termion::color::Fg(termion::color::LightYellow)),
+                    self.ansi(termion::style::Reset),
+                    achievement.name,
+                );
+            }
+        }
+    }
+
+    fn set_question(&self, hint: QuestionHint) {
+        if let Some(helper) = self.readline.helper() {
+            helper.set_question(hint);
+        }
+    }
+
+    fn wait_for_input(&mut self, mut hint: QuestionHint) -> Result<UIResponse, ReadlineError> {
+        hint.answer_required = !self.allow_empty && !self.reveal;
+        self.paste_warning = false;
+        self.set_question(hint.clone());
+        let mut result = self.readline.readline(&self.prompt);
+        if let Ok(input) = &result {
+            self.input_lines = self.count_lines(&format!("{}{}", self.prompt, input));
+        }
+        // Whatever was typed before the key is dropped.
+        if let Some(command) = self.pressed_command.lock().unwrap().take() {
+            if let Ok(input) = &mut result {
+                *input = format!(":{}", command);
+            }
+        }
+        while self.pasted() && result.as_ref().is_ok_and(|input| !input.starts_with(':')) {
+            if self.paste_policy == PastePolicy::Warn {
+                self.paste_warning = true;
+                break;
+            }
+            if self.accessible {
+                self.say("Pasted answers are not accepted, type it instead.");
+            } else {
+                self.overwrite_command();
+                println!(
+                    "{}pasted answers are not accepted, type it instead{}",
+                    self.ansi(termion::color::Fg(termion::color::LightYellow)),
+                    self.ansi(termion::style::Reset),
+                );
+            }
+            self.set_question(hint.clone());
+            result = self.readline.readline(&self.prompt);
+            if let Ok(input) = &result {
+                self.input_lines = self.count_lines(&format!("{}{}", self.prompt, input));
+            }
+        }
+        // Lines read without the editor, as from a script, aren't validated,
+        // nor are aliases, which may stand for nothing, such as {previous}
+        // before any answer.
+        let mut result = result.map(|input| self.expand_alias(input));
+        while hint.answer_required && result.as_ref().is_ok_and(|input| input.trim().is_empty()) {
+            result = self
+                .readline
+                .readline(&self.prompt)
+                .map(|input| self.expand_alias(input));
+        }
+        match result.map(|input| input.replace("\\\n", "\n")) {
+            Ok(input) if self.reveal && input.trim().is_empty() => Ok(UIResponse::Reveal),
+            Ok(input) if input.starts_with(':') => {
+                let command = input.get(1..).unwrap_or_default();
+                if "quit".starts_with(command) {
+                    Ok(UIResponse::Quit)
+                } else if "redraw".starts_with(command) {
+                    Ok(UIResponse::Redraw)
+                } else if command.len() >= 2 && "suspend".starts_with(command) {
+                    Ok(UIResponse::Suspend)
+                } else if command.len() >= 2 && "star".starts_with(command) {
+                    Ok(UIResponse::Star)
+                } else if command.len() >= 3 && "stop".starts_with(command) {
+                    Ok(UIResponse::Stop)
+                } else if "hint".starts_with(command) {
+                    Ok(UIResponse::Hint)
+                } else if command.len() >= 2 && "skip".starts_with(command) {
+                    Ok(UIResponse::Skip)
+                } else if "undo".starts_with(command) {
+                    Ok(UIResponse::Undo)
+                } else if let Some(text) = command
+                    .strip_prefix("note")
+                    .filter(|text| text.is_empty() || text.starts_with(char::is_whitespace))
+                {
+                    Ok(UIResponse::Note(text.trim().to_owned()))
+                } else {
+                    Ok(UIResponse::Return(input))
+                }
+            }
+            Ok(input) => {
+                self.last_answer = input.trim().to_owned();
+                Ok(UIResponse::Return(input))
+            }
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => Ok(UIResponse::Quit),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+type Scores = HashMap<String, Score>;
+
+#[derive(Clone, Debug, Default)]
+struct Score {
+    correct: u32,
+    incorrect: u32,
+    schedule: Schedule,
+    id: String,
+    suspended: bool,
+    starred: bool,
+    // Wrong answers given to the term and how many times each was given,
+    // the most frequent first.
+    typos: Vec<(String, u32)>,
+}
+
+impl Score {
+    fn increment_correct(&self) -> Self {
+        Self {
+            correct: self.correct + 1,
+            ..self.clone()
+        }
+    }
+
+    fn increment_incorrect(&self) -> Self {
+        Self {
+            incorrect: self.incorrect + 1,
+            ..self.clone()
+        }
+    }
+
+    fn typo_count(&self, answer: &str) -> u32 {
+        self.typos
+            .iter()
+            .find(|(typo, _)| typo == answer)
+            .map_or(0, |&(_, count)| count)
+    }
+
+    // Once the dictionary is full, a new typo takes the place of the oldest
+    // of the least frequent ones, which come last in the order they were
+    // added, so that one given again has the time to count more. Answers
+    // that cannot be stored in a field are skipped.
+    fn record_typo(&mut self, answer: &str) {
+        if answer.is_empty() || answer.contains(['\t', '\n', '|']) {
+            return;
+        }
+        match self.typos.iter().position(|(typo, _)| typo == answer) {
+            Some(i) => self.typos[i].1 += 1,
+            None => {
+                while self.typos.len() >= TYPO_LIMIT {
+                    let lowest = self.typos[self.typos.len() - 1].1;
+                    let oldest = self
+                        .typos
+                        .iter()
+                        .position(|&(_, count)| count == lowest)
+                        .unwrap_or_default();
+                    self.typos.remove(oldest);
+                }
+                self.typos.push((answer.to_owned(), 1));
+            }
+        }
+        self.typos.sort_by_key(|&(_, count)| Reverse(count));
+    }
+
+    fn review(self, mistakes: f64, now: u64, overrides: scheduler::Overrides) -> Self {
+        Self {
+            schedule: self.schedule.review(mistakes, now, overrides),
+            ..self
+        }
+    }
+
+    fn correct_rate(&self) -> f32 {
+        let tries = self.correct + self.incorrect;
+        if tries == 0 {
+            1.0
+        } else {
+            self.correct as f32 / tries as f32
+        }
+    }
+
+    fn total_tries(&self) -> u32 {
+        self.correct + self.incorrect
+    }
+
+    // Old attempts fade out, pulling the rate back towards the prior.
+    fn decayed_correct_rate(&self, attempts: &[Attempt], now: u64) -> f32 {
+        if attempts.is_empty() {
+            return self.correct_rate();
+        }
+        let mut weighted_correct = DECAY_PRIOR_RATE * DECAY_PRIOR_WEIGHT;
+        let mut total_weight = DECAY_PRIOR_WEIGHT;
+        for attempt in attempts {
+            let age = now.saturating_sub(attempt.timestamp) as f64;
+            let weight = 0.5f64.powf(age / DECAY_HALF_LIFE_SECS);
+            if attempt.mistakes == 0 {
+                weighted_correct += weight;
+            }
+            total_weight += weight;
+        }
+        (weighted_correct / total_weight) as f32
+    }
+}
+
+const TYPO_LIMIT: usize = 5;
+
+const DECAY_HALF_LIFE_SECS: f64 = 30.0 * 24.0 * 60.0 * 60.0;
+
+const DECAY_PRIOR_RATE: f64 = 0.5;
+
+const DECAY_PRIOR_WEIGHT: f64 = 1.0;
+
+type History = HashMap<String, Vec<Attempt>>;
+
+#[derive(Clone, Debug)]
+struct Attempt {
+    timestamp: u64,
+    mistakes: usize,
+    elapsed_ms: Option<u64>,
+    // The distinct wrong answers given before the right one, the latest last.
+    answers: Vec<String>,
+    // The ULID of the session, which older records don't have.
+    session: Option<Arc<str>>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct QueueCounts {
+    new: usize,
+    learning: usize,
+    due: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DifficultyBadge {
+    New,
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl DifficultyBadge {
+    fn label(&self) -> &'static str {
+        match self {
+            DifficultyBadge::New => "[new]",
+            DifficultyBadge::Easy => "[easy]",
+            DifficultyBadge::Normal => "[normal]",
+            DifficultyBadge::Hard => "[hard]",
+        }
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            DifficultyBadge::New => "new card",
+            DifficultyBadge::Easy => "easy",
+            DifficultyBadge::Normal => "normal",
+            DifficultyBadge::Hard => "hard",
+        }
+    }
+
+    fn color(&self) -> &'static dyn termion::color::Color {
+        match self {
+            DifficultyBadge::New => &termion::color::LightCyan,
+            DifficultyBadge::Easy => &termion::color::LightGreen,
+            DifficultyBadge::Normal => &termion::color::LightYellow,
+            DifficultyBadge::Hard => &termion::color::LightRed,
+        }
+    }
+}
+
+impl fmt::Display for DifficultyBadge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}",
+            termion::color::Fg(self.color()),
+            self.label(),
+            termion::style::Reset
+        )
+    }
+}
+
+// Why a card is being asked, shown before each question. Emoji need a
+// terminal and font that have them, so plain output and locales other than
+// UTF-8 fall back to ASCII, which `[ui] emoji` overrides.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum StateBadge {
+    New,
+    // Forgotten and being learned again.
+    Relearning,
+    Starred,
+    // Forgotten so often that it wastes reviews, and is better rewritten.
+    Leech,
+}
+
+// As in Anki.
+const LEECH_LAPSES: u32 = 8;
+
+impl StateBadge {
+    fn emoji(self) -> &'static str {
+        match self {
+            StateBadge::New => "\u{1f195}",
+            StateBadge::Relearning => "\u{1f501}",
+            StateBadge::Starred => "\u{2b50}",
+            StateBadge::Leech => "\u{1f41b}",
+        }
+    }
+
+    fn ascii(self) -> &'static str {
+        match self {
+            StateBadge::New => "+",
+            StateBadge::Relearning => "~",
+            StateBadge::Starred => "*",
+            StateBadge::Leech => "!",
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            StateBadge::New => "new",
+            StateBadge::Relearning => "relearning",
+            StateBadge::Starred => "starred",
+            StateBadge::Leech => "a leech",
+        }
+    }
+}
+
+fn supports_emoji() -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+        .is_some_and(|locale| {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
+
+struct HumanDuration(u64);
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0;
+        if secs < 60 {
+            write!(f, "{}s", secs)
+        } else if secs < 60 * 60 {
+            write!(f, "{}m", (secs + 30) / 60)
+        } else {
+            write!(f, "{}h{:02}m", secs / 3600, secs % 3600 / 60)
+        }
+    }
+}
+
+struct OrdinalNum(u32);
+
+impl fmt::Display for OrdinalNum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)?;
+        // 11th to 13th, 111th to 113th and so on, but 21st and 101st.
+        let suffix = match (self.0 % 100, self.0 % 10) {
+            (11..=13, _) => "th",
+            (_, 1) => "st",
+            (_, 2) => "nd",
+            (_, 3) => "rd",
+            _ => "th",
+        };
+        f.write_str(suffix)
+    }
+}
+
+const ADAPTIVE_WINDOW: usize = 5;
+
+const ADAPTIVE_HARD_THRESHOLD: f32 = 0.8;
+
+const ADAPTIVE_EASY_THRESHOLD: f32 = 0.4;
+
+// Entries are referred to by their index in the deck rather than shared, which
+// keeps the state `Send`.
+struct GameState {
+    deck: Vec<Entry>,
+    // The ULID every attempt of this session is recorded with.
+    session: Arc<str>,
+    entries: Vec<usize>,
+    pending: VecDeque<usize>,
+    asked: Vec<usize>,
+    scores: ScoreStore,
+    history: History,
+    new_attempts: Vec<(String, Attempt)>,
+    progress: usize,
+    mistakes: usize,
+    // How many of the mistakes had only one wrong word, which count as half.
+    partial_mistakes: usize,
+    // Which words of the last answer were right, if it was graded by words:
+    // when only one was wrong, or in arrange questions.
+    partial_answer: Option<Vec<bool>>,
+    // Asks terms of several words as arrange questions.
+    arrange_words: bool,
+    question_types: Option<QuestionTypes>,
+    adaptive: bool,
+    recent_results: VecDeque<bool>,
+    is_review: bool,
+    question_started: Instant,
+    hints: bool,
+    ignore_case: bool,
+    capitalization: Capitalization,
+    // Whether the last answer was taken with its first letter recapitalized,
+    // to be warned about.
+    miscapitalized: bool,
+    confusables: confusables::Policy,
+    // The letters of the last answer taken for lookalikes in the term, as the
+    // typed letter and the term's, to be warned about.
+    confused: Vec<(char, char)>,
+    layouts: confusables::Policy,
+    // The last answer and the layout it was taken as typed on, to be warned
+    // about.
+    retyped: Option<(&'static str, String)>,
+    // Set with `set_normalizers`, which keeps `by_term` in step.
+    normalizers: Normalizers,
+    // The script of the terms, which hints mask by.
+    script: Option<Script>,
+    batch: bool,
+    deferred: Vec<DeferredAnswer>,
+    // Entries by their normalized term, to find siblings.
+    by_term: HashMap<String, Vec<usize>>,
+    // Distinct wrong answers to the current question, the latest last.
+    wrong_answers: Vec<String>,
+    autosave: Option<Autosave>,
+    saved_attempts: usize,
+    history_writer: HistoryWriter,
+    // In streaming mode, entries are taken from here once the pending ones
+    // run out. Only entries with one of `stream_tags` are kept, up to
+    // `stream_limit` of them, and only starred ones with `stream_starred`.
+    stream: Option<EntryStream>,
+    stream_tags: Vec<String>,
+    stream_starred: bool,
+    // Entries with more phrases show this many of them, picked anew each time.
+    max_phrases: Option<usize>,
+    stream_limit: Option<usize>,
+    stream_error: Option<io::Error>,
+    // The index of the first entry of each deck whose questions alternate.
+    interleaved_decks: Vec<usize>,
+    points: i64,
+    // The points before the current question, and what the last answer
+    // changed, for `undo`.
+    question_points: i64,
+    undo: Option<Undo>,
+    // An entry to ask before any other, as one whose answer was taken back.
+    retry: Option<usize>,
+    notes: Option<Notes>,
+}
+
+const _: () = {
+    fn assert_send<T: Send>() {}
+    let _ = assert_send::<GameState>;
+};
+
+// The state before an answer was recorded: the scores of the term and of the
+// siblings it buries, the questions still to ask, and the points.
+struct Undo {
+    entry: usize,
+    scores: Vec<(String, Option<Score>)>,
+    pending: VecDeque<usize>,
+    recent_results: VecDeque<bool>,
+    points: i64,
+}
+
+struct DeferredAnswer {
+    entry: usize,
+    answer: String,
+    elapsed_ms: u64,
+}
+
+const BATCH_LAPSE_MISTAKES: f64 = 2.0;
+
+// A correct answer after mistakes was helped by the letters each mistake
+// reveals.
+const POINTS_PERFECT: i64 = 10;
+
+const POINTS_WITH_HINTS: i64 = 5;
+
+const POINTS_PER_MISTAKE: i64 = 5;
+
+impl GameState {
+    fn new(deck: Vec<Entry>, scores: Scores, history: History, adaptive: bool) -> Self {
+        let now = unix_time();
+        let entries: Vec<usize> = (0..deck.len())
+            .filter(|&i| {
+                !scores
+                    .get(deck[i].key())
+                    .is_some_and(|score| score.suspended || score.schedule.is_buried(now))
+            })
+            .collect();
+        let normalizers = Normalizers::default();
+        let mut by_term: HashMap<String, Vec<usize>> = HashMap::new();
+        for &i in &entries {
+            by_term
+                .entry(normalizers.apply(&deck[i].term, false))
+                .or_default()
+                .push(i);
+        }
+        Self {
+            deck,
+            session: Arc::from(ulid::generate()),
+            by_term,
+            entries: entries.clone(),
+            pending: entries.into(),
+            asked: Vec::new(),
+            scores: ScoreStore::new(scores),
+            history,
+            new_attempts: Vec::new(),
+            progress: 0,
+            mistakes: 0,
+            partial_mistakes: 0,
+            partial_answer: None,
+            arrange_words: false,
+            question_types: None,
+            adaptive,
+            recent_results: VecDeque::with_capacity(ADAPTIVE_WINDOW),
+            is_review: false,
+            question_started: Instant::now(),
+            hints: true,
+            ignore_case: false,
+            capitalization: Capitalization::Exact,
+            miscapitalized: false,
+            confusables: confusables::Policy::default(),
+            confused: Vec::new(),
+            layouts: confusables::Policy::default(),
+            retyped: None,
+            normalizers,
+            script: None,
+            batch: false,
+            deferred: Vec::new(),
+            wrong_answers: Vec::new(),
+            autosave: None,
+            saved_attempts: 0,
+            history_writer: HistoryWriter::default(),
+            stream: None,
+            stream_tags: Vec::new(),
+            stream_starred: false,
+            max_phrases: None,
+            stream_limit: None,
+            interleaved_decks: Vec::new(),
+            points: 0,
+            question_points: 0,
+            undo: None,
+            retry: None,
+            notes: None,
+            stream_error: None,
+        }
+    }
+
+    fn arrange(&mut self, order: Order, count: Option<usize>) {
+        let pending = self.pending.make_contiguous();
+        match order {
+            Order::Deck => {}
+            Order::Due => self.scores.read(|scores| {
+                pending.sort_by_key(|&i| {
+                    scores
+                        .get(self.deck[i].key())
+                        .map_or(0, |score| score.schedule.due)
+                })
+            }),
+            Order::Random => shuffle(pending),
+        }
+        if self.interleaved_decks.len() > 1 {
+            let decks = &self.interleaved_decks;
+            let mut queues: Vec<VecDeque<usize>> = vec![VecDeque::new(); decks.len()];
+            for &i in pending.iter() {
+                queues[decks.partition_point(|&start| start <= i) - 1].push_back(i);
+            }
+            self.pending.clear();
+            while queues.iter().any(|queue| !queue.is_empty()) {
+                self.pending
+                    .extend(queues.iter_mut().filter_map(VecDeque::pop_front));
+            }
+        }
+        if let Some(count) = count {
+            self.pending.truncate(count);
+        }
+    }
+
+    fn next_question(&mut self) -> Option<Question> {
+        let entry = if let Some(entry) = self.retry.take() {
+            entry
+        } else if self.adaptive {
+            self.pick_adaptive()?
+        } else {
+            match self.pending.pop_front() {
+                Some(entry) => entry,
+                None => self.pull_stream()?,
+            }
+        };
+        let i = self.progress;
+        self.progress += 1;
+        self.mistakes = 0;
+        self.partial_mistakes = 0;
+        self.partial_answer = None;
+        self.miscapitalized = false;
+        self.confused.clear();
+        self.retyped = None;
+        self.wrong_answers.clear();
+        self.question_started = Instant::now();
+        self.question_points = self.points;
+        self.asked.push(entry);
+        let mut phrases: Vec<usize> = (0..self.deck[entry].phrases.len()).collect();
+        if let Some(max_phrases) = self.max_phrases.filter(|&max| max < phrases.len()) {
+            shuffle(&mut phrases);
+            phrases.truncate(max_phrases);
+            phrases.sort_unstable();
+        }
+        let question_type = match &self.question_types {
+            Some(types) => types.for_score(self.get_score(self.deck[entry].key()).as_ref()),
+            None if self.arrange_words => QuestionType::Arrange,
+            None => QuestionType::Typed,
+        };
+        let kind = match question_type {
+            QuestionType::Choice => self
+                .choices(entry)
+                .map_or(QuestionKind::Recall, QuestionKind::Choice),
+            // Batch mode grades the term after the session, so nothing else
+            // can be typed.
+            QuestionType::Reverse if !self.batch && !self.deck[entry].phrases.is_empty() => {
+                QuestionKind::Reverse
+            }
+            QuestionType::Arrange => shuffle_words(&self.deck[entry].term)
+                .map_or(QuestionKind::Recall, QuestionKind::Arrange),
+            _ if self.arrange_words => shuffle_words(&self.deck[entry].term)
+                .map_or(QuestionKind::Recall, QuestionKind::Arrange),
+            _ => QuestionKind::Recall,
+        };
+        let hints = question_type != QuestionType::NoHints
+            && matches!(kind, QuestionKind::Recall | QuestionKind::Arrange(_));
+        Some(Question {
+            index: i,
+            entry,
+            phrases,
+            kind,
+            hints,
+        })
+    }
+
+    // The term and others of the deck in random order, or `None` if there are
+    // no others.
+    fn choices(&self, entry: usize) -> Option<Vec<String>> {
+        let term = &self.deck[entry].term;
+        let normalize = |term: &str| self.normalizers.apply(term, self.ignore_case);
+        // Suspended cards are out of the deck, and terms that read the same as
+        // another once normalized would make two right choices.
+        let mut seen = HashSet::from([normalize(term)]);
+        // The deck is walked from a random card with a random step prime to
+        // its size, which comes to every card once in a shuffled order, and
+        // only as far as it takes to find enough others, however large the
+        // deck is.
+        let len = self.deck.len();
+        let mut random = Xorshift::from_clock();
+        let start = random.next() as usize % len;
+        let mut step = random.next() as usize % len + 1;
+        while gcd(step, len) != 1 {
+            step += 1;
+        }
+        let others: Vec<&str> = self.scores.read(|scores| {
+            (0..len)
+                .map(|k| &self.deck[(start + k * step) % len])
+                .filter(|other| !scores.get(other.key()).is_some_and(|score| score.suspended))
+                .map(|other| &*other.term)
+                .filter(|other| seen.insert(normalize(other)))
+                .take(CHOICE_DISTRACTORS)
+                .collect()
+        });
+        if others.is_empty() {
+            return None;
+        }
+        let mut choices: Vec<String> = others.into_iter().map(str::to_owned).collect();
+        choices.push(term.to_string());
+        shuffle(&mut choices);
+        Some(choices)
+    }
+
+    fn pull_stream(&mut self) -> Option<usize> {
+        if self.stream_limit == Some(0) {
+            return None;
+        }
+        let now = unix_time();
+        loop {
+            let entry = match self.stream.as_mut()?.next()? {
+                Ok(entry) => entry,
+                Err(error) => {
+                    self.stream = None;
+                    self.stream_error = Some(error);
+                    return None;
+                }
+            };
+            let score = self.scores.get(entry.key());
+            let is_skipped = !entry.has_any_tag(&self.stream_tags)
+                || score
+                    .as_ref()
+                    .is_some_and(|score| score.suspended || score.schedule.is_buried(now))
+                || self.stream_starred && !score.is_some_and(|score| score.starred);
+            if is_skipped {
+                continue;
+            }
+            let i = self.deck.len();
+            self.by_term
+                .entry(self.normalizers.apply(&entry.term, false))
+                .or_default()
+                .push(i);
+            self.deck.push(entry);
+            self.entries.push(i);
+            if let Some(limit) = &mut self.stream_limit {
+                *limit -= 1;
+            }
+            return Some(i);
+        }
+    }
+
+    fn entry(&self, question: &Question) -> &Entry {
+        &self.deck[question.entry]
+    }
+
+    fn shown_phrases<'a>(&'a self, question: &'a Question) -> impl Iterator<Item = &'a Phrase> {
+        let entry = self.entry(question);
+        question.phrases.iter().map(|&i| &entry.phrases[i])
+    }
+
+    // The phrases left out by `max_phrases`, revealed once answered. Reverse
+    // questions show none.
+    fn hidden_phrases<'a>(&'a self, question: &'a Question) -> impl Iterator<Item = &'a Phrase> {
+        let reverse = matches!(question.kind, QuestionKind::Reverse);
+        self.entry(question)
+            .phrases
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| reverse || !question.phrases.contains(i))
+            .map(|(_, phrase)| phrase)
+    }
+
+    fn pick_adaptive(&mut self) -> Option<usize> {
+        let performance = self.recent_performance();
+        let was_review = mem::take(&mut self.is_review);
+        match performance {
+            Some(rate) if rate >= ADAPTIVE_HARD_THRESHOLD => {
+                let (i, _) = self.pending.iter().enumerate().max_by(|(_, &a), (_, &b)| {
+                    self.difficulty(self.deck[a].key())
+                        .total_cmp(&self.difficulty(self.deck[b].key()))
+                })?;
+                self.pending.remove(i)
+            }
+            Some(rate) if rate <= ADAPTIVE_EASY_THRESHOLD && !was_review => {
+                // Skip the entry just asked so that the review is not a repeat.
+                let candidates = &self.asked[..self.asked.len().saturating_sub(1)];
+                let review = candidates
+                    .iter()
+                    .min_by(|&&a, &&b| {
+                        self.difficulty(self.deck[a].key())
+                            .total_cmp(&self.difficulty(self.deck[b].key()))
+                    })
+                    .copied();
+                if review.is_some() {
+                    self.is_review = true;
+                    review
+                } else {
+                    self.pending.pop_front()
+                }
+            }
+            _ => self.pending.pop_front(),
+        }
+    }
+
+    fn recent_performance(&self) -> Option<f32> {
+        if self.recent_results.len() < ADAPTIVE_WINDOW {
+            return None;
+        }
+        let perfect = self.recent_results.iter().filter(|&&result| result).count();
+        Some(perfect as f32 / self.recent_results.len() as f32)
+    }
+
+    fn difficulty(&self, term: &str) -> f32 {
+        if self.scores.contains(term) {
+            1.0 - self.get_correct_rate(term)
+        } else {
+            1.0
+        }
+    }
+
+    fn state_badges(&self, term: &str) -> Vec<StateBadge> {
+        let Some(score) = self.get_score(term) else {
+            return vec![StateBadge::New];
+        };
+        let mut badges = Vec::new();
+        if score.schedule.lapses > 0 && score.schedule.is_learning() {
+            badges.push(StateBadge::Relearning);
+        }
+        if score.starred {
+            badges.push(StateBadge::Starred);
+        }
+        if score.schedule.lapses >= LEECH_LAPSES {
+            badges.push(StateBadge::Leech);
+        }
+        badges
+    }
+
+    fn difficulty_badge(&self, term: &str) -> DifficultyBadge {
+        if !self.scores.contains(term) {
+            DifficultyBadge::New
+        } else {
+            match self.difficulty(term) {
+                d if d < 0.2 => DifficultyBadge::Easy,
+                d if d < 0.5 => DifficultyBadge::Normal,
+                _ => DifficultyBadge::Hard,
+            }
+        }
+    }
+
+    // Prefers this session's pace and falls back to the recorded history.
+    fn average_response_ms(&self) -> Option<u64> {
+        let session: Vec<u64> = self
+            .new_attempts
+            .iter()
+            .filter_map(|(_, attempt)| attempt.elapsed_ms)
+            .collect();
+        let samples = if session.is_empty() {
+            self.history
+                .values()
+                .flatten()
+                .filter_map(|attempt| attempt.elapsed_ms)
+                .collect()
+        } else {
+            session
+        };
+        if samples.is_empty() {
+            None
+        } else {
+            Some(samples.iter().sum::<u64>() / samples.len() as u64)
+        }
+    }
+
+    // The questions left in the session, the current one included, by whether
+    // their cards are new, being learned or due for review.
+    fn queue_counts(&self, question: &Question) -> QueueCounts {
+        let mut counts = QueueCounts::default();
+        for &entry in iter::once(&question.entry).chain(&self.pending) {
+            match self.get_score(self.deck[entry].key()) {
+                None => counts.new += 1,
+                Some(score) if score.schedule.is_learning() => counts.learning += 1,
+                Some(_) => counts.due += 1,
+            }
+        }
+        counts
+    }
+
+    fn estimated_remaining_secs(&self) -> Option<u64> {
+        let average = self.average_response_ms()?;
+        Some(average * (self.pending.len() as u64 + 1) / 1000)
+    }
+
+    fn overall_correct_rate(&self) -> Option<f64> {
+        let attempts: Vec<&Attempt> = self.history.values().flatten().collect();
+        if attempts.is_empty() {
+            None
+        } else {
+            let perfect = attempts
+                .iter()
+                .filter(|attempt| attempt.mistakes == 0)
+                .count();
+            Some(perfect as f64 / attempts.len() as f64)
+        }
+    }
+
+    // The deck is mature once its slowest card is, assuming daily practice.
+    fn days_until_deck_mature(&self) -> Option<f64> {
+        let now = unix_time();
+        let fallback_rate = self.overall_correct_rate().unwrap_or(DECAY_PRIOR_RATE);
+        let mut days: f64 = 0.0;
+        for entry in self.entries.iter().map(|&i| &self.deck[i]) {
+            let schedule = self
+                .get_score(entry.key())
+                .map(|score| score.schedule)
+                .unwrap_or_default();
+            let success_rate = if self.history.contains_key(entry.key()) {
+                self.get_correct_rate(entry.key()) as f64
+            } else {
+                fallback_rate
+            };
+            days = days.max(schedule.days_until_mature(success_rate, now)?);
+        }
+        Some(days)
+    }
+
+    fn mature_count(&self) -> usize {
+        self.scores.read(|scores| {
+            self.entries
+                .iter()
+                .filter(|&&i| {
+                    scores
+                        .get(self.deck[i].key())
+                        .is_some_and(|score| score.schedule.is_mature())
+                })
+                .count()
+        })
+    }
+
+    // Returns whether the hint could be taken, which counts as half a
+    // mistake.
+    // Nothing is charged once every letter is shown.
+    fn take_hint(&mut self, question: &Question) -> bool {
+        if !self.hints_enabled()
+            || !question.hints
+            || self.mistakes >= count_letters(&self.entry(question).term, self.script)
+        {
+            return false;
+        }
+        self.mistakes += 1;
+        self.partial_mistakes += 1;
+        self.points -= POINTS_PER_MISTAKE / 2;
+        true
+    }
+
+    fn hints_enabled(&self) -> bool {
+        self.hints
+            && (!self.adaptive
+                || self
+                    .recent_performance()
+                    .is_none_or(|rate| rate < ADAPTIVE_HARD_THRESHOLD))
+    }
+
+    fn note(&self, question: &Question) -> Option<&str> {
+        self.notes.as_ref()?.get(&self.entry(question).card_id())
+    }
+
+    fn set_note(&mut self, question: &Question, text: &str) -> io::Result<()> {
+        let card_id = self.entry(question).card_id();
+        match &mut self.notes {
+            Some(notes) => notes.set(&card_id, text),
+            None => Err(io::Error::other("notes are not available in this session")),
+        }
+    }
+
+    // What a wrong answer is compared with.
+    fn expected_answer<'a>(&'a self, question: &'a Question) -> &'a str {
+        match question.kind {
+            QuestionKind::Reverse => self
+                .shown_phrases(question)
+                .next()
+                .map_or("", |phrase| phrase.body.as_str()),
+            _ => &self.entry(question).term,
+        }
+    }
+
+    fn question_hint(&self, question: &Question) -> QuestionHint {
+        QuestionHint::new(
+            self.entry(question).term.clone(),
+            self.mistakes,
+            self.hints_enabled() && question.hints,
+            self.script,
+        )
+    }
+
+    fn graded_mistakes(&self) -> f64 {
+        self.mistakes as f64 - self.partial_mistakes as f64 / 2.0
+    }
+
+    // Whether the answer is right but for the case of its first letter, if
+    // that is forgiven.
+    fn matches_recapitalized(&self, entry: &Entry, answer: &str) -> bool {
+        self.capitalization != Capitalization::Exact
+            && !self.ignore_case
+            && entry
+                .recapitalized(answer)
+                .is_some_and(|answer| entry.matches(&answer, false, &self.normalizers))
+    }
+
+    // The letters replaced if the answer is right in the term's lookalikes.
+    fn matches_deconfused(&self, entry: &Entry, answer: &str) -> Option<Vec<(char, char)>> {
+        if self.confusables == confusables::Policy::Exact {
+            return None;
+        }
+        let (answer, replaced) = confusables::in_letters_of(answer, &entry.term)?;
+        entry
+            .matches(&answer, self.ignore_case, &self.normalizers)
+            .then_some(replaced)
+    }
+
+    // The layout the answer is right on if it was typed on the wrong one.
+    fn matches_retyped(&self, entry: &Entry, answer: &str) -> Option<&'static str> {
+        if self.layouts == confusables::Policy::Exact {
+            return None;
+        }
+        layouts::retyped(answer)
+            .find(|(_, retyped)| entry.matches(retyped, self.ignore_case, &self.normalizers))
+            .map(|(layout, _)| layout)
+    }
+
+    fn answer_question(&mut self, question: &Question, answer: &str) -> bool {
+        let answer = question.resolve_answer(answer);
+        let entry = self.entry(question);
+        let is_correct = match question.kind {
+            QuestionKind::Reverse => {
+                entry.matches_meaning(&answer, self.ignore_case, &self.normalizers)
+            }
+            _ => entry.matches(&answer, self.ignore_case, &self.normalizers),
+        };
+        let is_recall = !matches!(question.kind, QuestionKind::Reverse);
+        let miscapitalized = !is_correct && is_recall && self.matches_recapitalized(entry, &answer);
+        let confused = if !is_correct && !miscapitalized && is_recall {
+            self.matches_deconfused(entry, &answer)
+        } else {
+            None
+        };
+        let retyped = if !is_correct && !miscapitalized && confused.is_none() && is_recall {
+            self.matches_retyped(entry, &answer)
+        } else {
+            None
+        };
+        if miscapitalized && self.capitalization == Capitalization::Warn {
+            self.mistakes += 1;
+            self.partial_mistakes += 1;
+            self.miscapitalized = true;
+        }
+        let is_correct = is_correct || miscapitalized || confused.is_some() || retyped.is_some();
+        if self.confusables == confusables::Policy::Warn {
+            self.confused = confused.unwrap_or_default();
+        }
+        if self.layouts == confusables::Policy::Warn {
+            self.retyped = retyped.map(|layout| (layout, answer.trim().to_owned()));
+        }
+        if is_correct {
+            let elapsed_ms = self.question_started.elapsed().as_millis() as u64;
+            let wrong_answers = mem::take(&mut self.wrong_answers);
+            let mistakes = self.graded_mistakes();
+            let review_mistakes = match question.kind {
+                QuestionKind::Choice(_) => mistakes.max(CHOICE_MISTAKES),
+                _ => mistakes,
+            };
+            self.record_attempt(
+                question.entry,
+                mistakes,
+                review_mistakes,
+                Some(elapsed_ms),
+                &wrong_answers,
+            );
+            self.points += if self.mistakes == 0 {
+                POINTS_PERFECT
+            } else {
+                POINTS_WITH_HINTS
+            };
+        } else {
+            self.mistakes += 1;
+            let entry = self.entry(question);
+            let term_words = entry.term.split_whitespace().count();
+            self.partial_answer = match question.kind {
+                QuestionKind::Recall => {
+                    entry.graded_words(&answer, self.ignore_case, &self.normalizers)
+                }
+                QuestionKind::Arrange(_) => {
+                    Some(entry.words_in_order(&answer, self.ignore_case, &self.normalizers))
+                }
+                QuestionKind::Choice(_) | QuestionKind::Reverse => None,
+            };
+            let is_near_miss = self.partial_answer.as_ref().is_some_and(|right| {
+                right.len() == term_words && right.iter().filter(|&&right| !right).count() == 1
+            });
+            if is_near_miss {
+                self.partial_mistakes += 1;
+                self.points -= POINTS_PER_MISTAKE / 2;
+            } else {
+                self.points -= POINTS_PER_MISTAKE;
+            }
+            let answer = answer.trim();
+            self.wrong_answers
+                .retain(|wrong_answer| wrong_answer != answer);
+            self.wrong_answers.push(answer.to_owned());
+        }
+        is_correct
+    }
+
+    // In batch mode answers are only graded once every question was asked.
+    fn defer_answer(&mut self, question: &Question, answer: String) {
+        self.deferred.push(DeferredAnswer {
+            entry: question.entry,
+            answer: question.resolve_answer(&answer).into_owned(),
+            elapsed_ms: self.question_started.elapsed().as_millis() as u64,
+        });
+    }
+
+    // Returns the answers that were wrong. Since they were never corrected,
+    // they count as lapses.
+    fn grade_deferred(&mut self) -> Vec<DeferredAnswer> {
+        let mut wrong = Vec::new();
+        for deferred in mem::take(&mut self.deferred) {
+            let entry = &self.deck[deferred.entry];
+            if entry.matches(&deferred.answer, self.ignore_case, &self.normalizers)
+                || self.matches_deconfused(entry, &deferred.answer).is_some()
+                || self.matches_retyped(entry, &deferred.answer).is_some()
+            {
+                self.record_attempt(deferred.entry, 0.0, 0.0, Some(deferred.elapsed_ms), &[]);
+                self.points += POINTS_PERFECT;
+            } else if self.matches_recapitalized(entry, &deferred.answer) {
+                let mistakes = match self.capitalization {
+                    Capitalization::Warn => 0.5,
+                    _ => 0.0,
+                };
+                self.record_attempt(
+                    deferred.entry,
+                    mistakes,
+                    mistakes,
+                    Some(deferred.elapsed_ms),
+                    &[],
+                );
+                self.points += POINTS_PERFECT;
+            } else {
+                self.points -= POINTS_PER_MISTAKE;
+                let wrong_answers = [deferred.answer.trim().to_owned()];
+                self.record_attempt(
+                    deferred.entry,
+                    BATCH_LAPSE_MISTAKES,
+                    BATCH_LAPSE_MISTAKES,
+                    Some(deferred.elapsed_ms),
+                    &wrong_answers,
+                );
+                wrong.push(deferred);
+            }
+        }
+        wrong
+    }
+
+    // Answers graded by the player count as lapses when forgotten, as in
+    // batch mode, and keep the mistakes typed before the answer was revealed
+    // when remembered. The time is left out since it is mostly spent
+    // listening.
+    fn grade_self(&mut self, question: &Question, remembered: bool) {
+        let wrong_answers = mem::take(&mut self.wrong_answers);
+        let mistakes = if remembered {
+            self.graded_mistakes()
+        } else {
+            BATCH_LAPSE_MISTAKES.max(self.graded_mistakes())
+        };
+        self.record_attempt(question.entry, mistakes, mistakes, None, &wrong_answers);
+    }
+
+    // The history keeps whole mistakes, where half a one counts as one.
+    // `review_mistakes` are those the card is scheduled by, which may be more
+    // than were made.
+    fn record_attempt(
+        &mut self,
+        entry: usize,
+        mistakes: f64,
+        review_mistakes: f64,
+        elapsed_ms: Option<u64>,
+        wrong_answers: &[String],
+    ) {
+        use std::collections::hash_map::Entry;
+        let term = self.deck[entry].key().to_owned();
+        let group = self
+            .by_term
+            .get(&self.normalizers.apply(&self.deck[entry].term, false));
+        let keys: HashSet<&str> = group
+            .into_iter()
+            .flatten()
+            .map(|&i| self.deck[i].key())
+            .chain([term.as_str()])
+            .collect();
+        let scores = self.scores.read(|scores| {
+            keys.into_iter()
+                .map(|key| (key.to_owned(), scores.get(key).cloned()))
+                .collect()
+        });
+        self.undo = Some(Undo {
+            entry,
+            scores,
+            pending: self.pending.clone(),
+            recent_results: self.recent_results.clone(),
+            points: self.question_points,
+        });
+        let card_id = self.deck[entry].card_id();
+        let overrides = self.deck[entry].overrides;
+        let is_perfect = mistakes == 0.0;
+        let attempt = Attempt {
+            timestamp: unix_time(),
+            mistakes: mistakes.ceil() as usize,
+            elapsed_ms,
+            answers: wrong_answers
+                .iter()
+                .filter(|answer| !answer.contains(['\t', '\n', '|']))
+                .cloned()
+                .collect(),
+            session: Some(self.session.clone()),
+        };
+        self.history
+            .entry(term.clone())
+            .or_default()
+            .push(attempt.clone());
+        self.new_attempts.push((term.clone(), attempt.clone()));
+        if self.recent_results.len() == ADAPTIVE_WINDOW {
+            self.recent_results.pop_front();
+        }
+        self.recent_results.push_back(is_perfect);
+        self.scores.update(|scores| match scores.entry(term) {
+            Entry::Occupied(mut score_entry) => {
+                let mut score = if is_perfect {
+                    score_entry.get().increment_correct()
+                } else {
+                    score_entry.get().increment_incorrect()
+                };
+                if score.id.is_empty() {
+                    score.id = card_id;
+                }
+                for answer in wrong_answers {
+                    score.record_typo(answer);
+                }
+                score_entry.insert(score.review(review_mistakes, attempt.timestamp, overrides));
+            }
+            Entry::Vacant(score_entry) => {
+                let mut score = Score {
+                    id: card_id,
+                    ..Score::default()
+                };
+                if is_perfect {
+                    score.correct = 1;
+                } else {
+                    score.incorrect = 1;
+                }
+                for answer in wrong_answers {
+                    score.record_typo(answer);
+                }
+                score_entry.insert(score.review(review_mistakes, attempt.timestamp, overrides));
+            }
+        });
+        self.bury_siblings(entry, attempt.timestamp);
+        self.queue_autosave();
+    }
+
+    fn queue_autosave(&mut self) {
+        if let Some(autosave) = &self.autosave {
+            autosave.update(&self.new_attempts[self.saved_attempts..]);
+            self.saved_attempts = self.new_attempts.len();
+        }
+    }
+
+    // Hands the remaining changes to the autosave thread, or saves them here
+    // if there is none.
+    fn save(&mut self, score_path: &Path, history_path: &Path) -> io::Result<()> {
+        match self.autosave.take() {
+            Some(autosave) => {
+                autosave.update(&self.new_attempts[self.saved_attempts..]);
+                autosave.finish()
+            }
+            None => {
+                save_scores(score_path, self.scores.snapshot().0)?;
+                self.history_writer
+                    .append(history_path, &self.new_attempts[self.saved_attempts..])
+            }
+        }?;
+        self.saved_attempts = self.new_attempts.len();
+        Ok(())
+    }
+
+    // Returns how many times the answer was given, including this time, if it
+    // was given to an earlier question too.
+    fn recurring_typo(&self, entry: &Entry, answer: &str) -> Option<u32> {
+        let count = self.scores.get(entry.key())?.typo_count(answer.trim());
+        (count > 0).then_some(count + 1)
+    }
+
+    // The language detected from the deck, or given in the settings.
+    fn set_language(&mut self, language: &Language, settings: &Settings) -> Result<(), String> {
+        self.set_normalizers(Normalizers::from_settings(
+            settings,
+            language.code.as_deref(),
+        )?);
+        self.script = language.script;
+        Ok(())
+    }
+
+    fn set_normalizers(&mut self, normalizers: Normalizers) {
+        self.by_term.clear();
+        for &i in &self.entries {
+            self.by_term
+                .entry(normalizers.apply(&self.deck[i].term, false))
+                .or_default()
+                .push(i);
+        }
+        self.normalizers = normalizers;
+    }
+
+    fn bury_siblings(&mut self, entry: usize, now: u64) {
+        let entry = &self.deck[entry];
+        let group = match self
+            .by_term
+            .get(&self.normalizers.apply(&entry.term, false))
+        {
+            Some(group) if group.len() > 1 => group,
+            _ => return,
+        };
+        let siblings = group
+            .iter()
+            .map(|&i| &self.deck[i])
+            .filter(|other| other.is_sibling_of(entry, &self.normalizers));
+        self.scores.update(|scores| {
+            for sibling in siblings {
+                let score = scores.entry(sibling.key().to_owned()).or_default();
+                if score.id.is_empty() {
+                    score.id = sibling.card_id();
+                }
+                score.schedule.bury(now);
+            }
+        });
+        let (deck, normalizers) = (&self.deck, &self.normalizers);
+        self.pending
+            .retain(|&other| !deck[other].is_sibling_of(entry, normalizers));
+    }
+
+    fn is_starred(&self, question: &Question) -> bool {
+        self.scores
+            .get(self.entry(question).key())
+            .is_some_and(|score| score.starred)
+    }
+
+    fn toggle_star(&mut self, question: &Question) {
+        let entry = &self.deck[question.entry];
+        self.scores.update(|scores| {
+            let score = scores.entry(entry.key().to_owned()).or_default();
+            if score.id.is_empty() {
+                score.id = entry.card_id();
+            }
+            score.starred = !score.starred;
+        });
+        self.queue_autosave();
+    }
+
+    // Puts the question after the others, as if it hadn't been asked yet.
+    fn skip(&mut self, question: &Question) {
+        self.asked.pop();
+        self.progress -= 1;
+        self.pending.push_back(question.entry);
+    }
+
+    // Takes back the answer to the question before this one, as long as it
+    // wasn't saved yet, and asks it again followed by this one. In batch mode
+    // the answer was only deferred.
+    fn undo(&mut self, question: &Question) -> Result<(), &'static str> {
+        let entry = if self.batch {
+            self.deferred.pop().ok_or("nothing to undo")?.entry
+        } else {
+            let undo = self.undo.take().ok_or("nothing to undo")?;
+            let term = self.deck[undo.entry].key();
+            let is_unsaved = match &self.autosave {
+                Some(autosave) => autosave.retract(term),
+                None => self.saved_attempts < self.new_attempts.len(),
+            };
+            if !is_unsaved {
+                return Err("the answer was already saved");
+            }
+            self.new_attempts.pop();
+            self.saved_attempts = self.saved_attempts.min(self.new_attempts.len());
+            if let Some(attempts) = self.history.get_mut(term) {
+                attempts.pop();
+            }
+            self.scores.update(|scores| {
+                for (key, score) in undo.scores {
+                    match score {
+                        Some(score) => scores.insert(key, score),
+                        None => scores.remove(&key),
+                    };
+                }
+            });
+            self.pending = undo.pending;
+            self.recent_results = undo.recent_results;
+            self.points = undo.points;
+            undo.entry
+        };
+        if !self.pending.contains(&question.entry) {
+            self.pending.push_front(question.entry);
+        }
+        self.asked.truncate(self.asked.len().saturating_sub(2));
+        self.progress = self.progress.saturating_sub(2);
+        self.retry = Some(entry);
+        Ok(())
+    }
+
+    fn suspend(&mut self, question: &Question) {
+        let entry = &self.deck[question.entry];
+        self.scores.update(|scores| {
+            let score = scores.entry(entry.key().to_owned()).or_default();
+            if score.id.is_empty() {
+                score.id = entry.card_id();
+            }
+            score.suspended = true;
+        });
+        self.entries.retain(|&other| other != question.entry);
+        self.asked.retain(|&other| other != question.entry);
+        // The questions before it are no longer where undoing expects them.
+        self.undo = None;
+        self.queue_autosave();
+    }
+
+    // The number of questions answered without mistakes in this session.
+    fn perfect_count(&self) -> usize {
+        self.new_attempts
+            .iter()
+            .filter(|(_, attempt)| attempt.mistakes == 0)
+            .count()
+    }
+
+    fn get_score(&self, term: &str) -> Option<Score> {
+        self.scores.get(term)
+    }
+
+    fn get_correct_rate(&self, term: &str) -> f32 {
+        let score = self.get_score(term).unwrap_or_default();
+        let attempts = self.history.get(term).map(Vec::as_slice).unwrap_or(&[]);
+        score.decayed_correct_rate(attempts, unix_time())
+    }
+}
+
+enum UIResponse {
+    Return(String),
+    Redraw,
+    Suspend,
+    Star,
+    // Cancels the sounds and speech still playing.
+    Stop,
+    // An empty note removes the one on the card.
+    Note(String),
+    // Enter on an empty line, to be shown the answer, see `--reveal`.
+    Reveal,
+    // Another letter of the hint, for half a mistake.
+    Hint,
+    // Leaves the question for the end, with nothing recorded.
+    Skip,
+    // Takes back the answer to the previous question and asks it again.
+    Undo,
+    Quit,
+}
+
+fn normalize_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Decks with at least this many lines are parsed on several threads.
+const PARALLEL_LOAD_LINES: usize = 10_000;
+
+fn load_entries<R: Read>(mut handle: R) -> io::Result<Vec<Entry>> {
+    let mut source = String::new();
+    handle.read_to_string(&mut source)?;
+    let lines: Vec<&str> = source.lines().collect();
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    if lines.len() < PARALLEL_LOAD_LINES || threads == 1 {
+        return parse_entries(&lines, 0);
+    }
+    let chunk_size = lines.len().div_ceil(threads);
+    thread::scope(|scope| {
+        let handles: Vec<_> = lines
+            .chunks(chunk_size)
+            .enumerate()
+            .map(|(i, chunk)| scope.spawn(move || parse_entries(chunk, i * chunk_size)))
+            .collect();
+        let mut entries = Vec::with_capacity(lines.len());
+        for handle in handles {
+            let chunk = handle
+                .join()
+                .unwrap_or_else(|payload| std::panic::resume_unwind(payload))?;
+            entries.extend(chunk);
+        }
+        Ok(entries)
+    })
+}
+
+// `offset` is the number of the first line, counted from zero.
+fn parse_entries(lines: &[&str], offset: usize) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::with_capacity(lines.len());
+    for (n, line) in lines.iter().enumerate() {
+        entries.extend(parse_line(line, offset + n)?);
+    }
+    Ok(entries)
+}
+
+fn parse_line(line: &str, n: usize) -> io::Result<Option<Entry>> {
+    let invalid = |error: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("line {}: {}", n + 1, error),
+        )
+    };
+    let mut entry = match Entry::parse(line.chars().peekable()).map_err(invalid)? {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+    // "display ~pattern" accepts answers matching the pattern.
+    if let Some((display, pattern)) = entry.term.split_once(" ~") {
+        let pattern = Regex::new(pattern).map_err(|error| invalid(error.to_string()))?;
+        entry.term = intern(display);
+        entry.pattern = Some(pattern);
+    }
+    Ok(Some(entry))
+}
+
+// Parses the entries of a deck one at a time as they are asked, so that a
+// session over a huge or endless deck starts right away.
+struct EntryStream {
+    lines: io::Lines<Box<dyn BufRead + Send>>,
+    line_number: usize,
+    alternatives: HashMap<String, Vec<String>>,
+}
+
+impl EntryStream {
+    fn open(path: Option<&Path>) -> io::Result<Self> {
+        let (reader, alternatives): (Box<dyn BufRead + Send>, _) = match path {
+            Some(path) => {
+                let path = archive::resolve_deck(path, &detect_cache_directory())?;
+                (
+                    Box::new(BufReader::new(File::open(&path)?)),
+                    load_alternatives(path.with_extension("alt"))?,
+                )
+            }
+            None => (Box::new(BufReader::new(io::stdin())), HashMap::new()),
+        };
+        Ok(Self {
+            lines: reader.lines(),
+            line_number: 0,
+            alternatives,
+        })
+    }
+}
+
+impl Iterator for EntryStream {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(error) => return Some(Err(error)),
+            };
+            let n = self.line_number;
+            self.line_number += 1;
+            match parse_line(&line, n) {
+                Ok(Some(mut entry)) => {
+                    if let Some(spellings) = self.alternatives.get(&*entry.term) {
+                        entry.alternatives.extend(spellings.iter().cloned());
+                    }
+                    return Some(Ok(entry));
+                }
+                Ok(None) => {}
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+fn load_deck(path: &Path) -> io::Result<Vec<Entry>> {
+    let path = &archive::resolve_deck(path, &detect_cache_directory())?;
+    let mut entries = load_entries(File::open(path)?)?;
+    let alternatives = load_alternatives(path.with_extension("alt"))?;
+    for entry in entries.iter_mut() {
+        if let Some(spellings) = alternatives.get(&*entry.term) {
+            entry.alternatives.extend(spellings.iter().cloned());
+        }
+    }
+    Ok(entries)
+}
+
+// The sections a deck may set. Decks are shared, so the others, some of
+// which run commands or open listeners, are only taken from config.toml.
+const DECK_SECTIONS: &[&str] = &["aliases", "deck", "hints", "matching", "quiz", "style"];
+
+const DECK_SECTION_PREFIXES: &[&str] = &["schedule.", "scheduler."];
+
+// Per-deck defaults come from DECK.toml, or from a deck.toml next to the
+// deck as shipped in bundles. They override the ones in config.toml.
+fn load_deck_settings(path: &Path) -> io::Result<Settings> {
+    let path = archive::resolve_deck(path, &detect_cache_directory())?;
+    let sidecar = path.with_extension("toml");
+    let sidecar = if sidecar.exists() {
+        sidecar
+    } else {
+        path.with_file_name("deck.toml")
+    };
+    let mut settings = Settings::load(&sidecar)?;
+    let ignored = settings.retain_sections(|name| {
+        DECK_SECTIONS.contains(&name)
+            || DECK_SECTION_PREFIXES
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+    });
+    for name in ignored {
+        eprintln!(
+            "{}: ignoring [{}], which only config.toml can set",
+            sidecar.display(),
+            name
+        );
+    }
+    Ok(settings)
+}
+
+// A deck file to practice, with its settings merged into `settings` and the
+// overrides of their tags given to its cards.
+fn load_session_deck(path: &Path, settings: &mut Settings) -> io::Result<Vec<Entry>> {
+    settings.merge(load_deck_settings(path)?);
+    let mut entries = load_deck(path)?;
+    for entry in &mut entries {
+        entry
+            .apply_tag_overrides(settings)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    }
+    Ok(entries)
+}
+
+// The alternatives file uses the deck grammar: each phrase is an accepted
+// spelling of the term.
+fn load_alternatives<P: AsRef<Path>>(path: P) -> io::Result<HashMap<String, Vec<String>>> {
+    let mut alternatives = HashMap::new();
+    if path.as_ref().exists() {
+        for entry in load_entries(File::open(path)?)? {
+            let spellings = entry.phrases.into_iter().map(|phrase| phrase.body);
+            alternatives
+                .entry(entry.term.to_string())
+                .or_insert_with(Vec::new)
+                .extend(spellings);
+        }
+    }
+    Ok(alternatives)
+}
+
+// Interleaved decks keep their scores apart even where they share terms,
+// under keys prefixed with the deck's canonical path, which tells apart decks
+// of the same name in different directories.
+fn interleaved_key(deck: &Path, key: &str) -> String {
+    let deck = fs::canonicalize(deck).unwrap_or_else(|_| deck.to_owned());
+    format!("{}:{}", deck.display(), key)
+}
+
+fn load_scores<P: AsRef<Path>>(path: P) -> io::Result<Scores> {
+    if path.as_ref().exists() {
+        let file = File::open(path)?;
+        read_scores(BufReader::new(file))
+    } else {
+        Ok(HashMap::new())
+    }
+}
+
+fn read_scores<R: BufRead>(reader: R) -> io::Result<Scores> {
+    let mut scores = HashMap::new();
+    for line in reader.lines() {
+        let line = line?;
+        let mut parts = line.split('\t');
+        if let Some(term) = parts.next() {
+            let correct = parse_field(parts.next()).unwrap_or(0);
+            let incorrect = parse_field(parts.next()).unwrap_or(0);
+            let default = Schedule::default();
+            let schedule = Schedule {
+                repetitions: parse_field(parts.next()).unwrap_or(default.repetitions),
+                interval: parse_field(parts.next()).unwrap_or(default.interval),
+                ease: parse_field(parts.next()).unwrap_or(default.ease),
+                lapses: parse_field(parts.next()).unwrap_or(default.lapses),
+                due: parse_field(parts.next()).unwrap_or(default.due),
+                buried_until: default.buried_until,
+            };
+            let mut score = Score {
+                correct,
+                incorrect,
+                schedule,
+                id: parts.next().unwrap_or_default().to_owned(),
+                suspended: parts.next() == Some("1"),
+                starred: false,
+                typos: Vec::new(),
+            };
+            if let Some(buried_until) = parse_field(parts.next()) {
+                score.schedule.buried_until = buried_until;
+            }
+            if let Some(typos) = parts.next() {
+                score.typos = typos
+                    .split('|')
+                    .filter_map(|typo| {
+                        let (count, typo) = typo.split_once(':')?;
+                        Some((typo.to_owned(), count.parse().ok()?))
+                    })
+                    .collect();
+            }
+            score.starred = parts.next() == Some("1");
+            scores.insert(term.to_owned(), score);
+        }
+    }
+    Ok(scores)
+}
+
+fn parse_field<T: FromStr>(part: Option<&str>) -> Option<T> {
+    part.and_then(|part| part.parse().ok())
+}
+
+// The scores are written to a temporary file first and renamed over the old
+// ones, so that they are never left half written.
+fn save_scores<P: AsRef<Path>>(path: P, scores: Scores) -> io::Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temp_name = path.file_name().unwrap_or_default().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&temp_path)?;
+    let mut writer = BufWriter::new(file);
+    write_scores(&mut writer, &scores)?;
+    writer
+        .into_inner()
+        .map_err(|error| error.into_error())?
+        .sync_all()?;
+    fs::rename(&temp_path, path)
+}
+
+fn write_scores<W: Write>(mut writer: W, scores: &Scores) -> io::Result<()> {
+    for (term, score) in scores {
+        let schedule = &score.schedule;
+        let typos: Vec<String> = score
+            .typos
+            .iter()
+            .map(|(typo, count)| format!("{}:{}", count, typo))
+            .collect();
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            term,
+            score.correct,
+            score.incorrect,
+            schedule.repetitions,
+            schedule.interval,
+            schedule.ease,
+            schedule.lapses,
+            schedule.due,
+            score.id,
+            score.suspended as u8,
+            schedule.buried_until,
+            typos.join("|"),
+            score.starred as u8
+        )?;
+    }
+    Ok(())
+}
+
+fn load_history<P: AsRef<Path>>(path: P) -> io::Result<History> {
+    let mut history: History = HashMap::new();
+    if path.as_ref().exists() {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
         for line in reader.lines() {
             let line = line?;
             let mut parts = line.split('\t');
-            if let Some(term) = parts.next() {
-                let score = Score {
-                    correct: parts
-                        .next()
-                        .and_then(|part| str::parse(part).ok())
-                        .unwrap_or(0),
-                    incorrect: parts
-                        .next()
-                        .and_then(|part| str::parse(part).ok())
-                        .unwrap_or(0),
-                };
-                scores.insert(term.to_owned(), score);
+            if let (Some(timestamp), Some(term), Some(mistakes)) =
+                (parts.next(), parts.next(), parts.next())
+            {
+                if let (Ok(timestamp), Ok(mistakes)) = (timestamp.parse(), mistakes.parse()) {
+                    history.entry(term.to_owned()).or_default().push(Attempt {
+                        timestamp,
+                        mistakes,
+                        elapsed_ms: parts.next().and_then(|part| part.parse().ok()),
+                        answers: parts
+                            .next()
+                            .filter(|part| !part.is_empty())
+                            .map(|part| part.split('|').map(str::to_owned).collect())
+                            .unwrap_or_default(),
+                        session: parts.next().filter(|part| ulid::is_valid(part)).map(intern),
+                    });
+                }
+            }
+        }
+    }
+    Ok(history)
+}
+
+// The best points per deck, one "POINTS\tDECK" line each.
+fn load_high_scores(path: &Path) -> io::Result<HashMap<String, i64>> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let high_scores = fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| {
+            let (points, deck) = line.split_once('\t')?;
+            Some((deck.to_owned(), points.parse().ok()?))
+        })
+        .collect();
+    Ok(high_scores)
+}
+
+fn save_high_scores(path: &Path, high_scores: &HashMap<String, i64>) -> io::Result<()> {
+    let mut decks: Vec<(&String, &i64)> = high_scores.iter().collect();
+    decks.sort();
+    let mut contents = String::new();
+    for (deck, points) in decks {
+        contents.push_str(&format!("{}\t{}\n", points, deck));
+    }
+    fs::write(path, contents)
+}
+
+const RECENT_DECKS: usize = 10;
+
+// The decks practiced lately, the latest first, one path per line.
+fn load_recent_decks(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let decks = fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect();
+    Ok(decks)
+}
+
+fn record_recent_deck(path: &Path, deck: &Path) -> io::Result<()> {
+    let deck = fs::canonicalize(deck)?;
+    let mut decks = load_recent_decks(path)?;
+    decks.retain(|other| *other != deck);
+    decks.insert(0, deck);
+    decks.truncate(RECENT_DECKS);
+    let mut contents = String::new();
+    for deck in decks {
+        contents.push_str(&format!("{}\n", deck.display()));
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)
+}
+
+// Appends attempts to the history, remembering the records it wrote, so that
+// attempts handed to it again after a failed write aren't written twice.
+// Records are told apart by their session, time and term, so any without a
+// session are always written. A record cut short by a failed write is ended
+// before the next one, which leaves it unreadable rather than joined to it.
+#[derive(Debug, Default)]
+struct HistoryWriter {
+    written: HashSet<(Arc<str>, u64, String)>,
+}
+
+impl HistoryWriter {
+    fn append<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        attempts: &[(String, Attempt)],
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        if file.metadata()?.len() > 0 {
+            let mut last = [0];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+            if last != *b"\n" {
+                file.write_all(b"\n")?;
+            }
+        }
+        for (term, attempt) in attempts {
+            let key = attempt
+                .session
+                .as_ref()
+                .map(|session| (session.clone(), attempt.timestamp, term.clone()));
+            if key.as_ref().is_some_and(|key| self.written.contains(key)) {
+                continue;
+            }
+            let mut line = format!("{}\t{}\t{}", attempt.timestamp, term, attempt.mistakes);
+            if attempt.elapsed_ms.is_some()
+                || !attempt.answers.is_empty()
+                || attempt.session.is_some()
+            {
+                line.push('\t');
+                if let Some(elapsed_ms) = attempt.elapsed_ms {
+                    line.push_str(&elapsed_ms.to_string());
+                }
+            }
+            if !attempt.answers.is_empty() || attempt.session.is_some() {
+                line.push('\t');
+                line.push_str(&attempt.answers.join("|"));
+            }
+            if let Some(session) = &attempt.session {
+                line.push('\t');
+                line.push_str(session);
+            }
+            line.push('\n');
+            // One write per record, so that those before a failed one count
+            // as written.
+            file.write_all(line.as_bytes())?;
+            if let Some(key) = key {
+                self.written.insert(key);
+            }
+        }
+        Ok(())
+    }
+}
+
+// An xorshift generator, plenty for putting questions in a different order
+// every session and for making up lines to check the parser with.
+struct Xorshift(u64);
+
+impl Xorshift {
+    // A state of zero would stay zero.
+    fn new(seed: u64) -> Self {
+        Self(seed | 1)
+    }
+
+    fn from_clock() -> Self {
+        Self::new(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_nanos() as u64)
+                .unwrap_or(0),
+        )
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// Fisher-Yates, seeded by the clock.
+fn shuffle<T>(items: &mut [T]) {
+    let mut random = Xorshift::from_clock();
+    for i in (1..items.len()).rev() {
+        items.swap(i, (random.next() % (i as u64 + 1)) as usize);
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+fn detect_config_directory() -> PathBuf {
+    env::var("XDG_CONFIG_HOME")
+        .map(|config_home| Path::new(&config_home).to_path_buf())
+        .or_else(|_| env::var("HOME").map(|home_dir| Path::new(&home_dir).join(".config")))
+        .unwrap_or_else(|_| env::temp_dir())
+        .join("vocab-trainer")
+}
+
+fn detect_cache_directory() -> PathBuf {
+    env::var("XDG_CACHE_HOME")
+        .map(|cache_home| Path::new(&cache_home).to_path_buf())
+        .or_else(|_| env::var("HOME").map(|home_dir| Path::new(&home_dir).join(".cache")))
+        .unwrap_or_else(|_| env::temp_dir())
+        .join("vocab-trainer")
+}
+
+// The runtime directory is private to the user, the configuration directory
+// is the fallback where there is none.
+fn detect_socket_path() -> PathBuf {
+    match env::var("XDG_RUNTIME_DIR") {
+        Ok(runtime_dir) => Path::new(&runtime_dir).join("vocab-trainer.sock"),
+        Err(_) => detect_config_directory().join("daemon.sock"),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SessionEnd {
+    Completed,
+    Quit,
+}
+
+fn run_loop(
+    ui: &mut GameUI,
+    state: &mut GameState,
+    bus: &mut EventBus,
+) -> Result<SessionEnd, ReadlineError> {
+    let mut end = SessionEnd::Completed;
+    'outer: while let Some(question) = state.next_question() {
+        bus.dispatch(&events::Event::QuestionShown(&question), state, ui);
+
+        loop {
+            if ui.is_resized() {
+                ui.redraw(&question, state);
+            }
+            match ui.wait_for_input(state.question_hint(&question))? {
+                UIResponse::Return(input) if state.batch => {
+                    state.defer_answer(&question, input);
+                    break;
+                }
+                UIResponse::Return(input) => {
+                    let correct = state.answer_question(&question, &input);
+                    let event = events::Event::AnswerGraded {
+                        question: &question,
+                        answer: &input,
+                        correct,
+                    };
+                    bus.dispatch(&event, state, ui);
+                    if correct {
+                        ui.wait_before_next();
+                        break;
+                    }
+                }
+                UIResponse::Reveal if state.batch => {
+                    state.defer_answer(&question, String::new());
+                    break;
+                }
+                UIResponse::Reveal => {
+                    let Some(remembered) = ui.ask_remembered(&question, state)? else {
+                        end = SessionEnd::Quit;
+                        break 'outer;
+                    };
+                    state.grade_self(&question, remembered);
+                    let event = events::Event::SelfGraded {
+                        question: &question,
+                        remembered,
+                    };
+                    bus.dispatch(&event, state, ui);
+                    ui.wait_before_next();
+                    break;
+                }
+                UIResponse::Redraw => ui.redraw(&question, state),
+                UIResponse::Note(text) => match state.set_note(&question, &text) {
+                    Ok(()) => ui.notify_note(&question, state),
+                    Err(error) => eprintln!("save note: {}", error),
+                },
+                UIResponse::Star => {
+                    state.toggle_star(&question);
+                    ui.notify_starred(&question, state);
+                }
+                UIResponse::Stop => ui.stop_audio(),
+                UIResponse::Hint => {
+                    let taken = state.take_hint(&question);
+                    ui.notify_hint(&question, state, taken);
+                }
+                UIResponse::Suspend => {
+                    state.suspend(&question);
+                    ui.notify_suspended(&question, state);
+                    break;
+                }
+                UIResponse::Skip => {
+                    state.skip(&question);
+                    ui.notify_skipped();
+                    break;
+                }
+                UIResponse::Undo => {
+                    let result = state.undo(&question);
+                    let is_undone = result.is_ok();
+                    ui.notify_undone(result);
+                    if is_undone {
+                        break;
+                    }
+                }
+                UIResponse::Quit => {
+                    end = SessionEnd::Quit;
+                    break 'outer;
+                }
+            }
+        }
+    }
+    if state.batch {
+        let wrong = state.grade_deferred();
+        ui.review_mistakes(&wrong, state)?;
+    }
+    bus.dispatch(&events::Event::SessionEnded(end), state, ui);
+    Ok(end)
+}
+
+const SAMPLE_DECKS: &[(&str, &str)] = &[
+    ("example", include_str!("../decks/example.txt")),
+    ("de", include_str!("../decks/de.txt")),
+    ("en", include_str!("../decks/en.txt")),
+    ("es", include_str!("../decks/es.txt")),
+    ("fr", include_str!("../decks/fr.txt")),
+];
+
+fn init_deck(lang: &str) -> io::Result<PathBuf> {
+    let contents = SAMPLE_DECKS
+        .iter()
+        .find(|(name, _)| *name == lang)
+        .map(|(_, contents)| *contents)
+        .ok_or_else(|| {
+            let names: Vec<_> = SAMPLE_DECKS.iter().map(|(name, _)| *name).collect();
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "no sample deck for '{}' (available: {})",
+                    lang,
+                    names.join(", ")
+                ),
+            )
+        })?;
+    let path = PathBuf::from(format!("{}.txt", lang));
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|error| match error.kind() {
+            io::ErrorKind::AlreadyExists => {
+                io::Error::new(error.kind(), format!("{} already exists", path.display()))
+            }
+            _ => error,
+        })?;
+    file.write_all(contents.as_bytes())?;
+    Ok(path)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Order {
+    #[default]
+    Deck,
+    Due,
+    Random,
+}
+
+impl Order {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "deck" => Ok(Order::Deck),
+            "due" => Ok(Order::Due),
+            "random" => Ok(Order::Random),
+            _ => Err(format!("unknown order: {}", s)),
+        }
+    }
+}
+
+// The kind of question asked for a card at each stage of learning it, set in
+// `[scheduler.question_types]`, so that new cards start out easy:
+//
+//   [scheduler.question_types]
+//   new = "choice"
+//   learning = "typed"
+//   young = "typed"
+//   mature = "no_hints"
+//
+// Learning cards have been answered without mistakes fewer than three times in
+// a row, and mature ones have an interval of three weeks or more. The other
+// types are "reverse", where the term is shown and a meaning typed, and
+// "arrange", see `--arrange-words`. Without the section every card is typed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum QuestionType {
+    Choice,
+    Typed,
+    NoHints,
+    Reverse,
+    Arrange,
+}
+
+impl QuestionType {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "choice" => Ok(QuestionType::Choice),
+            "typed" => Ok(QuestionType::Typed),
+            "no_hints" => Ok(QuestionType::NoHints),
+            "reverse" => Ok(QuestionType::Reverse),
+            "arrange" => Ok(QuestionType::Arrange),
+            _ => Err(format!("unknown question type: {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct QuestionTypes {
+    new: QuestionType,
+    learning: QuestionType,
+    young: QuestionType,
+    mature: QuestionType,
+}
+
+impl QuestionTypes {
+    fn from_settings(settings: &Settings) -> Result<Option<Self>, String> {
+        const SECTION: &str = "scheduler.question_types";
+        if !settings.has_section(SECTION) {
+            return Ok(None);
+        }
+        let get = |key, default| {
+            settings
+                .get_str(SECTION, key)
+                .map_or(Ok(default), QuestionType::parse)
+        };
+        Ok(Some(Self {
+            new: get("new", QuestionType::Choice)?,
+            learning: get("learning", QuestionType::Typed)?,
+            young: get("young", QuestionType::Typed)?,
+            mature: get("mature", QuestionType::NoHints)?,
+        }))
+    }
+
+    fn for_score(&self, score: Option<&Score>) -> QuestionType {
+        match score.map(|score| &score.schedule) {
+            None => self.new,
+            Some(schedule) if schedule.is_learning() => self.learning,
+            Some(schedule) if schedule.is_mature() => self.mature,
+            Some(_) => self.young,
+        }
+    }
+}
+
+// A round before the typed questions, over the cards they are about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum WarmUp {
+    // See pairs.rs.
+    Pairs,
+    // See truefalse.rs.
+    TrueFalse,
+}
+
+impl WarmUp {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "pairs" => Ok(WarmUp::Pairs),
+            "true-false" => Ok(WarmUp::TrueFalse),
+            _ => Err(format!("unknown warm-up: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct QuizOptions {
+    deck: Option<PathBuf>,
+    // Decks after the first, whose entries follow the first deck's unless
+    // `interleave` is set.
+    more_decks: Vec<PathBuf>,
+    interleave: bool,
+    points: bool,
+    starred: bool,
+    tags: Vec<String>,
+    adaptive: bool,
+    silent: bool,
+    plain: bool,
+    accessible: bool,
+    batch: bool,
+    count: Option<usize>,
+    max_phrases: Option<usize>,
+    paste: Option<PastePolicy>,
+    // Opens the deck practiced last.
+    recent: bool,
+    order: Option<Order>,
+    auto_advance: Option<u64>,
+    fail_under: Option<f32>,
+    // Reads the questions out instead, see audio.rs.
+    audio: bool,
+    self_grade: bool,
+    pause: Option<u64>,
+    daemon: bool,
+    socket: Option<PathBuf>,
+    // Where the daemon accepts words for the inbox over HTTP.
+    http: Option<String>,
+    stream: bool,
+    preset: Option<String>,
+    arrange_words: bool,
+    warm_up: Option<WarmUp>,
+    // Questions in large letters, for a projector or low vision.
+    big: bool,
+    // Enter on an empty line shows the answer, which is then graded by the
+    // player as with flashcards. Typed answers are graded as ever.
+    reveal: bool,
+}
+
+impl QuizOptions {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--tag" => options.tags.extend(args.next().cloned()),
+                "--adaptive" => options.adaptive = true,
+                "--silent" => options.silent = true,
+                "--plain" => options.plain = true,
+                "--accessible" => options.accessible = true,
+                "--batch" => options.batch = true,
+                "--daemon" => options.daemon = true,
+                "--audio" => options.audio = true,
+                "--self-grade" => options.self_grade = true,
+                "--stream" => options.stream = true,
+                "--arrange-words" => options.arrange_words = true,
+                "--big" => options.big = true,
+                "--reveal" => options.reveal = true,
+                "--interleave" => options.interleave = true,
+                "--points" => options.points = true,
+                "--starred" => options.starred = true,
+                "--recent" => options.recent = true,
+                "--socket" => options.socket = args.next().map(PathBuf::from),
+                "--http" => options.http = args.next().cloned(),
+                "--count" => {
+                    let count = args.next().map(String::as_str).unwrap_or_default();
+                    let count = count
+                        .parse()
+                        .map_err(|_| format!("invalid count: {}", count))?;
+                    options.count = Some(count);
+                }
+                "--max-phrases" => {
+                    let max_phrases = args.next().map(String::as_str).unwrap_or_default();
+                    let max_phrases = max_phrases
+                        .parse()
+                        .ok()
+                        .filter(|&max_phrases| max_phrases > 0)
+                        .ok_or_else(|| format!("invalid number of phrases: {}", max_phrases))?;
+                    options.max_phrases = Some(max_phrases);
+                }
+                "--paste" => {
+                    let policy = args.next().map(String::as_str).unwrap_or_default();
+                    options.paste = Some(PastePolicy::parse(policy)?);
+                }
+                "--order" => {
+                    let order = args.next().map(String::as_str).unwrap_or_default();
+                    options.order = Some(Order::parse(order)?);
+                }
+                "--warm-up" => {
+                    let warm_up = args.next().map(String::as_str).unwrap_or_default();
+                    options.warm_up = Some(WarmUp::parse(warm_up)?);
+                }
+                "--pause" => {
+                    let pause = args.next().map(String::as_str).unwrap_or_default();
+                    let pause = pause
+                        .parse()
+                        .map_err(|_| format!("invalid pause: {}", pause))?;
+                    options.pause = Some(pause);
+                }
+                "--auto-advance" => {
+                    let delay = args.next().map(String::as_str).unwrap_or_default();
+                    let delay = delay
+                        .parse()
+                        .map_err(|_| format!("invalid delay: {}", delay))?;
+                    options.auto_advance = Some(delay);
+                }
+                "--fail-under" => {
+                    let percentage = args.next().map(String::as_str).unwrap_or_default();
+                    let percentage = percentage
+                        .parse()
+                        .map_err(|_| format!("invalid percentage: {}", percentage))?;
+                    options.fail_under = Some(percentage);
+                }
+                "--preset" => options.preset = args.next().cloned(),
+                "-" => options.deck = None,
+                _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+                _ if options.deck.is_some() => options.more_decks.push(PathBuf::from(arg)),
+                _ => options.deck = Some(PathBuf::from(arg)),
+            }
+        }
+        Ok(options)
+    }
+
+    // A preset is a `[preset.NAME]` section holding the same keys as the
+    // command line options. Options given on the command line take precedence.
+    fn with_preset(mut self, settings: &Settings) -> Result<Self, String> {
+        let name = match &self.preset {
+            Some(name) => name,
+            None => return Ok(self),
+        };
+        let section = format!("preset.{}", name);
+        if !settings.has_section(&section) {
+            return Err(format!("unknown preset: {}", name));
+        }
+        let flag = |key| settings.get_bool(&section, key).unwrap_or(false);
+        self.adaptive |= flag("adaptive");
+        self.silent |= flag("silent");
+        self.plain |= flag("plain");
+        self.accessible |= flag("accessible");
+        self.batch |= flag("batch");
+        self.interleave |= flag("interleave");
+        self.points |= flag("points");
+        self.starred |= flag("starred");
+        self.audio |= flag("audio");
+        self.self_grade |= flag("self_grade");
+        self.arrange_words |= flag("arrange_words");
+        self.big |= flag("big");
+        self.reveal |= flag("reveal");
+        if self.deck.is_none() {
+            self.deck = settings.get_str(&section, "deck").map(PathBuf::from);
+        }
+        if self.tags.is_empty() {
+            self.tags = settings.get_str_array(&section, "tags").unwrap_or_default();
+        }
+        if self.count.is_none() {
+            self.count = settings
+                .get_integer(&section, "count")
+                .map(|count| count.max(0) as usize);
+        }
+        if self.max_phrases.is_none() {
+            self.max_phrases = settings
+                .get_integer(&section, "max_phrases")
+                .map(|max_phrases| max_phrases.max(1) as usize);
+        }
+        if self.pause.is_none() {
+            self.pause = settings
+                .get_integer(&section, "pause")
+                .map(|pause| pause.max(0) as u64);
+        }
+        if self.auto_advance.is_none() {
+            self.auto_advance = settings
+                .get_integer(&section, "auto_advance")
+                .map(|delay| delay.max(0) as u64);
+        }
+        if self.fail_under.is_none() {
+            self.fail_under = settings
+                .get_integer(&section, "fail_under")
+                .map(|percentage| percentage as f32);
+        }
+        if self.order.is_none() {
+            self.order = settings
+                .get_str(&section, "order")
+                .map(Order::parse)
+                .transpose()?;
+        }
+        if self.paste.is_none() {
+            self.paste = settings
+                .get_str(&section, "paste")
+                .map(PastePolicy::parse)
+                .transpose()?;
+        }
+        if self.warm_up.is_none() {
+            self.warm_up = settings
+                .get_str(&section, "warm_up")
+                .map(WarmUp::parse)
+                .transpose()?;
+        }
+        Ok(self)
+    }
+
+    // Flags given on the command line can't be turned off by the settings.
+    fn with_defaults(mut self, settings: &Settings) -> Result<Self, String> {
+        let flag = |section, key| settings.get_bool(section, key).unwrap_or(false);
+        self.adaptive |= flag("scheduler", "adaptive");
+        self.silent |= flag("quiz", "silent");
+        self.plain |= flag("quiz", "plain");
+        self.accessible |= flag("quiz", "accessible");
+        self.batch |= flag("quiz", "batch");
+        self.points |= flag("quiz", "points");
+        self.arrange_words |= flag("quiz", "arrange_words");
+        self.big |= flag("quiz", "big");
+        self.reveal |= flag("quiz", "reveal");
+        if self.tags.is_empty() {
+            self.tags = settings.get_str_array("quiz", "tags").unwrap_or_default();
+        }
+        if self.auto_advance.is_none() {
+            self.auto_advance = settings
+                .get_integer("quiz", "auto_advance")
+                .map(|delay| delay.max(0) as u64);
+        }
+        if self.paste.is_none() {
+            self.paste = settings
+                .get_str("quiz", "paste")
+                .map(PastePolicy::parse)
+                .transpose()?;
+        }
+        if self.warm_up.is_none() {
+            self.warm_up = settings
+                .get_str("quiz", "warm_up")
+                .map(WarmUp::parse)
+                .transpose()?;
+        }
+        Ok(self)
+    }
+}
+
+// Exit codes of a quiz session, so that scripts can tell how it went. Other
+// failures, such as invalid options, exit with 1.
+const EXIT_QUIT: i32 = 2;
+
+const EXIT_DECK_ERROR: i32 = 3;
+
+const EXIT_IO_ERROR: i32 = 4;
+
+const EXIT_BELOW_THRESHOLD: i32 = 5;
+
+// Where the answers typed outside the line editor come from, as in warm-ups:
+// the terminal when the deck takes up the standard input, or else the
+// standard input, which may be scripted.
+enum AnswerInput {
+    Stdin,
+    Terminal(BufReader<File>),
+}
+
+impl AnswerInput {
+    fn open(deck_on_stdin: bool) -> io::Result<Self> {
+        if !deck_on_stdin {
+            return Ok(Self::Stdin);
+        }
+        // The line editor opens the terminal on its own, so it is only
+        // checked for here.
+        match termion::get_tty() {
+            Ok(tty) => Ok(Self::Terminal(BufReader::new(tty))),
+            Err(error) => Err(io::Error::new(
+                error.kind(),
+                format!(
+                    "the deck is read from the standard input, so the answers \
+                     need a terminal, which can't be opened ({}); give the deck \
+                     as a file instead",
+                    error
+                ),
+            )),
+        }
+    }
+
+    fn read_line(&mut self, line: &mut String) -> io::Result<usize> {
+        match self {
+            Self::Stdin => io::stdin().read_line(line),
+            Self::Terminal(tty) => tty.read_line(line),
+        }
+    }
+
+    fn keys(&mut self) -> Box<dyn Iterator<Item = io::Result<termion::event::Key>> + '_> {
+        use termion::input::TermRead;
+        match self {
+            Self::Stdin => Box::new(TermRead::keys(io::stdin())),
+            Self::Terminal(tty) => Box::new(TermRead::keys(tty)),
+        }
+    }
+}
+
+fn exit_on_error<T, E: fmt::Display>(result: Result<T, E>, context: &str, code: i32) -> T {
+    result.unwrap_or_else(|error| {
+        eprintln!("{}: {}", context, error);
+        process::exit(code);
+    })
+}
+
+// Malformed decks and archives that fail to verify are reported as deck
+// errors, anything else while loading them as I/O errors.
+fn deck_error_code(error: &io::Error) -> i32 {
+    if error.kind() == io::ErrorKind::InvalidData {
+        EXIT_DECK_ERROR
+    } else {
+        EXIT_IO_ERROR
+    }
+}
+
+fn run_quiz(mut options: QuizOptions) {
+    options.plain |= !termion::is_tty(&io::stdout());
+    let config_dir = detect_config_directory();
+    let score_path = config_dir.join("scores.txt");
+    let history_path = config_dir.join("history.txt");
+    let recent_path = config_dir.join("recent.txt");
+    if options.recent {
+        if options.deck.is_some() {
+            eprintln!("--recent opens the last deck and takes no deck");
+            process::exit(1);
+        }
+        let decks = exit_on_error(load_recent_decks(&recent_path), "load recent decks", 1);
+        match decks.into_iter().next() {
+            Some(deck) => options.deck = Some(deck),
+            None => {
+                eprintln!("no deck has been practiced yet");
+                process::exit(1);
+            }
+        }
+    }
+    let mut settings = exit_on_error(
+        Settings::load(config_dir.join("config.toml")),
+        "load config",
+        EXIT_IO_ERROR,
+    );
+    let options = exit_on_error(options.with_preset(&settings), "preset", 1);
+    if let Some(path) = &options.deck {
+        match load_deck_settings(path) {
+            Ok(deck_settings) => settings.merge(deck_settings),
+            Err(error) => {
+                eprintln!("load deck settings: {}", error);
+                process::exit(deck_error_code(&error));
+            }
+        }
+    }
+    let options = exit_on_error(options.with_defaults(&settings), "config", 1);
+    // Entries can only be streamed when they are asked in the deck order.
+    if options.stream
+        && (options.adaptive || options.order.is_some_and(|order| order != Order::Deck))
+    {
+        eprintln!("--stream can't be combined with --adaptive or --order");
+        process::exit(1);
+    }
+    if options.stream && !options.more_decks.is_empty() {
+        eprintln!("--stream takes a single deck");
+        process::exit(1);
+    }
+    if options.interleave && (options.adaptive || options.deck.is_none()) {
+        eprintln!("--interleave needs deck files and can't be combined with --adaptive");
+        process::exit(1);
+    }
+    let deck_on_stdin = options.deck.is_none();
+    let entries = if options.stream {
+        Ok(Vec::new())
+    } else {
+        match &options.deck {
+            Some(path) => load_deck(path),
+            None => load_entries(io::stdin()),
+        }
+    };
+    let entries = entries.unwrap_or_else(|error| {
+        eprintln!("load deck: {}", error);
+        process::exit(deck_error_code(&error));
+    });
+    let mut decks = vec![entries];
+    for path in &options.more_decks {
+        decks.push(load_deck(path).unwrap_or_else(|error| {
+            eprintln!("load deck {}: {}", path.display(), error);
+            process::exit(deck_error_code(&error));
+        }));
+    }
+    if let Some(path) = &options.deck {
+        if let Err(error) = record_recent_deck(&recent_path, path) {
+            eprintln!("record recent deck: {}", error);
+        }
+    }
+    let mut entries = Vec::new();
+    let mut deck_starts = Vec::new();
+    let mut scores = exit_on_error(load_scores(&score_path), "load scores", EXIT_IO_ERROR);
+    let mut history = exit_on_error(load_history(&history_path), "load history", EXIT_IO_ERROR);
+    let paths: Vec<&PathBuf> = options.deck.iter().chain(&options.more_decks).collect();
+    for (i, deck) in decks.into_iter().enumerate() {
+        deck_starts.push(entries.len());
+        let path = paths.get(i).filter(|_| options.interleave);
+        let stem = path.map(|path| path.file_stem().unwrap_or_default().to_string_lossy());
+        for mut entry in deck {
+            if !entry.has_any_tag(&options.tags) {
+                continue;
+            }
+            if let (Some(path), Some(stem)) = (path, &stem) {
+                let key = interleaved_key(path, entry.key());
+                // Scores from before the deck was interleaved, or from when
+                // keys were prefixed with the file name, are carried over.
+                if !scores.contains_key(&key) {
+                    let old_keys = [format!("{}:{}", stem, entry.key()), entry.key().to_owned()];
+                    if let Some(old_key) = old_keys.iter().find(|old| scores.contains_key(*old)) {
+                        let score = scores[old_key].clone();
+                        scores.insert(key.clone(), score);
+                        if let Some(attempts) = history.get(old_key).cloned() {
+                            history.insert(key.clone(), attempts);
+                        }
+                    }
+                }
+                entry.id = Some(key);
+            }
+            exit_on_error(entry.apply_tag_overrides(&settings), "config", 1);
+            entries.push(entry);
+        }
+    }
+    if options.starred {
+        entries.retain(|entry| scores.get(entry.key()).is_some_and(|score| score.starred));
+    }
+    let mut state = GameState::new(entries, scores, history, options.adaptive);
+    if options.interleave {
+        state.interleaved_decks = deck_starts;
+    }
+    state.arrange(options.order.unwrap_or_default(), options.count);
+    if options.stream {
+        match EntryStream::open(options.deck.as_deref()) {
+            Ok(stream) => state.stream = Some(stream),
+            Err(error) => {
+                eprintln!("load deck: {}", error);
+                process::exit(deck_error_code(&error));
+            }
+        }
+        state.stream_tags = options.tags.clone();
+        state.stream_starred = options.starred;
+        state.stream_limit = options.count;
+    }
+    state.batch = options.batch;
+    state.arrange_words = options.arrange_words;
+    state.question_types = exit_on_error(QuestionTypes::from_settings(&settings), "config", 1);
+    state.max_phrases = options.max_phrases;
+    state.notes = Some(exit_on_error(
+        Notes::load(config_dir.join("notes.txt")),
+        "load notes",
+        EXIT_IO_ERROR,
+    ));
+    let autosave_answers = settings.get_integer("autosave", "answers").unwrap_or(5);
+    let autosave_secs = settings.get_integer("autosave", "seconds").unwrap_or(30);
+    state.autosave = Some(Autosave::start(
+        state.scores.clone(),
+        score_path.clone(),
+        history_path.clone(),
+        autosave_answers.max(0) as usize,
+        time::Duration::from_secs(autosave_secs.max(0) as u64),
+    ));
+    state.hints = settings.get_bool("hints", "enabled").unwrap_or(true);
+    let language = exit_on_error(Language::from_settings(&settings, &state.deck), "config", 1);
+    exit_on_error(state.set_language(&language, &settings), "config", 1);
+    state.ignore_case = settings
+        .get_bool("matching", "ignore_case")
+        .unwrap_or(false);
+    if let Some(policy) = settings.get_str("matching", "confusables") {
+        state.confusables = exit_on_error(confusables::Policy::parse(policy), "config", 1);
+    }
+    if let Some(policy) = settings.get_str("matching", "layouts") {
+        state.layouts = exit_on_error(confusables::Policy::parse(policy), "config", 1);
+    }
+    if let Some(capitalization) = settings.get_str("matching", "capitalization") {
+        state.capitalization = exit_on_error(Capitalization::parse(capitalization), "config", 1);
+    }
+    if options.daemon {
+        let socket_path = options.socket.clone().unwrap_or_else(detect_socket_path);
+        let http = options
+            .http
+            .as_deref()
+            .or_else(|| settings.get_str("daemon", "http"));
+        if let Some(address) = http {
+            let Some(token) = settings
+                .get_str("daemon", "token")
+                .filter(|token| !token.is_empty())
+            else {
+                eprintln!("config: [daemon] token must be set to accept words over HTTP");
+                process::exit(1);
+            };
+            exit_on_error(
+                daemon::serve_inbox(address, token.to_owned(), config_dir.join("inbox.txt")),
+                "inbox",
+                EXIT_IO_ERROR,
+            );
+        }
+        let result = daemon::serve(&mut state, &socket_path);
+        exit_on_error(
+            state.save(&score_path, &history_path),
+            "save scores",
+            EXIT_IO_ERROR,
+        );
+        exit_on_error(result, "daemon", EXIT_IO_ERROR);
+        return;
+    }
+    // Opened only here, as the daemon answers over its socket and has no
+    // terminal to need.
+    let mut input = exit_on_error(AnswerInput::open(deck_on_stdin), "open terminal", 1);
+    if options.audio {
+        let audio_options = audio::Options {
+            pause: options.pause,
+            self_grade: options.self_grade,
+        };
+        let result = audio::run(&mut state, &settings, &audio_options, &mut input);
+        exit_on_error(
+            state.save(&score_path, &history_path),
+            "save scores",
+            EXIT_IO_ERROR,
+        );
+        if exit_on_error(result, "audio", EXIT_IO_ERROR) {
+            process::exit(EXIT_QUIT);
+        }
+        return;
+    }
+    let quit = match options.warm_up {
+        Some(WarmUp::Pairs) => pairs::run(&state, &settings, options.plain, &mut input),
+        Some(WarmUp::TrueFalse) => truefalse::run(&mut state, &settings, options.plain, &mut input),
+        None => Ok(false),
+    };
+    if exit_on_error(quit, "warm-up", EXIT_IO_ERROR) {
+        process::exit(EXIT_QUIT);
+    }
+    let sounds = if options.silent {
+        SoundTheme::silent()
+    } else {
+        SoundTheme::from_settings(&settings)
+    };
+    let prompt = settings.get_str("ui", "prompt").unwrap_or("> ");
+    let mut ui = GameUI::new(
+        sounds,
+        prompt.to_owned(),
+        options.plain || options.accessible,
+        !deck_on_stdin,
+    );
+    if options.accessible {
+        ui.accessible = true;
+        ui.tts_command = settings
+            .get_str("accessibility", "tts_command")
+            .map(str::to_owned);
+    }
+    ui.auto_advance = options.auto_advance.map(time::Duration::from_millis);
+    ui.paste_policy = options.paste.unwrap_or_default();
+    ui.show_queue_counts = settings.get_bool("ui", "queue_counts").unwrap_or(false);
+    ui.rtl_terms = language.rtl;
+    ui.style = exit_on_error(Style::from_settings(&settings), "config", 1);
+    ui.typewriter = settings
+        .get_integer("ui", "typewriter")
+        .filter(|&pause| pause > 0)
+        .map(|pause| time::Duration::from_millis(pause as u64));
+    ui.allow_empty = settings
+        .get_bool("matching", "allow_empty")
+        .unwrap_or(false);
+    ui.reveal = options.reveal;
+    ui.big = options.big;
+    ui.big_command = settings.get_str("big", "command").map(str::to_owned);
+    if let Some(emoji) = settings.get_bool("ui", "emoji") {
+        ui.emoji = emoji && !ui.plain;
+    }
+    exit_on_error(ui.bind_keys(&settings), "config", 1);
+    ui.set_aliases(&settings);
+    if let Some(path) = settings.get_str("corpus", "path") {
+        match Corpus::open(Path::new(path), &detect_cache_directory()) {
+            Ok(corpus) => ui.corpus = Some(corpus),
+            Err(error) => eprintln!("load corpus: {}", error),
+        }
+    }
+    let high_score_path = config_dir.join("highscores.txt");
+    let mut high_scores = HashMap::new();
+    // Decks read from the standard input have nothing to be remembered by.
+    let high_score_key = options.deck.as_ref().filter(|_| options.points).map(|_| {
+        let paths: Vec<String> = options
+            .deck
+            .iter()
+            .chain(&options.more_decks)
+            .map(|path| {
+                let path = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+                path.display().to_string()
+            })
+            .collect();
+        paths.join(" + ")
+    });
+    if let Some(key) = &high_score_key {
+        high_scores = exit_on_error(
+            load_high_scores(&high_score_path),
+            "load high scores",
+            EXIT_IO_ERROR,
+        );
+        ui.high_score = high_scores.get(key).copied();
+    }
+    ui.show_points = options.points;
+    let mut bus = exit_on_error(
+        EventBus::for_session(&settings, &config_dir),
+        "load history",
+        EXIT_IO_ERROR,
+    );
+    let end = run_loop(&mut ui, &mut state, &mut bus);
+    exit_on_error(
+        state.save(&score_path, &history_path),
+        "save scores",
+        EXIT_IO_ERROR,
+    );
+    if let Some(key) = high_score_key {
+        let answered = !state.new_attempts.is_empty();
+        if answered && ui.high_score.is_none_or(|best| state.points > best) {
+            high_scores.insert(key, state.points);
+            exit_on_error(
+                save_high_scores(&high_score_path, &high_scores),
+                "save high scores",
+                EXIT_IO_ERROR,
+            );
+        }
+    }
+    // Lets the last sentences be read out, which exiting would cut off.
+    drop(ui);
+    if let Some(error) = state.stream_error.take() {
+        eprintln!("load deck: {}", error);
+        process::exit(deck_error_code(&error));
+    }
+    if exit_on_error(end, "read input", EXIT_IO_ERROR) == SessionEnd::Quit {
+        process::exit(EXIT_QUIT);
+    }
+    let answered = state.new_attempts.len();
+    if let Some(threshold) = options.fail_under {
+        if answered > 0 && stats::percentage(state.perfect_count(), answered) < threshold {
+            process::exit(EXIT_BELOW_THRESHOLD);
+        }
+    }
+}
+
+fn run_stats(args: &[String]) -> io::Result<()> {
+    let mut html_path = None;
+    let mut deck_path = None;
+    let mut frequency_path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--html" => html_path = args.next().map(PathBuf::from),
+            "--deck" => deck_path = args.next().map(PathBuf::from),
+            "--frequency" => frequency_path = args.next().map(PathBuf::from),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown option: {}", arg),
+                ))
+            }
+        }
+    }
+    if let Some(deck_path) = deck_path {
+        let entries = load_deck(&deck_path)?;
+        let frequency_list = frequency_path.map(load_frequency_list).transpose()?;
+        stats::print_deck_summary(&entries, frequency_list.as_deref());
+        return Ok(());
+    }
+    if frequency_path.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--frequency requires --deck",
+        ));
+    }
+    let config_dir = detect_config_directory();
+    let scores = load_scores(config_dir.join("scores.txt"))?;
+    let history = load_history(config_dir.join("history.txt"))?;
+    let compacted = prune::load_compacted_days(&config_dir)?;
+    match html_path {
+        Some(path) => {
+            fs::write(&path, stats::render_html(&scores, &history, &compacted))?;
+            println!("Wrote {}", path.display());
+        }
+        None => stats::print_summary(&scores, &history, &compacted),
+    }
+    Ok(())
+}
+
+// Words from the most frequent, one per line. A count after the word, as in
+// most published lists, is ignored.
+fn load_frequency_list(path: PathBuf) -> io::Result<Vec<String>> {
+    let words = fs::read_to_string(path)?
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            match line.rsplit_once(char::is_whitespace) {
+                Some((word, count)) if count.chars().all(|c| c.is_ascii_digit()) => word.trim_end(),
+                _ => line,
+            }
+        })
+        .filter(|word| !word.is_empty() && !word.starts_with('#'))
+        .map(str::to_owned)
+        .collect();
+    Ok(words)
+}
+
+fn run_achievements(args: &[String]) -> io::Result<()> {
+    if let Some(arg) = args.first() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unknown option: {}", arg),
+        ));
+    }
+    let config_dir = detect_config_directory();
+    let scores = load_scores(config_dir.join("scores.txt"))?;
+    let history = load_history(config_dir.join("history.txt"))?;
+    let achievements_path = config_dir.join("achievements.txt");
+    let mut unlocked = achievements::load(&achievements_path)?;
+    let compacted = prune::load_compacted_days(&config_dir)?;
+    let progress = achievements::Progress::new(&scores, &history, &compacted, 0);
+    // Such as milestones reached before achievements were tracked.
+    if !achievements::unlock(&mut unlocked, &progress).is_empty() {
+        achievements::save(&achievements_path, &unlocked)?;
+    }
+    achievements::print(&unlocked, &progress);
+    Ok(())
+}
+
+fn run_status(args: &[String]) -> io::Result<()> {
+    let mut format = stats::StatusFormat::Plain;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                let name = args.next().map(String::as_str).unwrap_or_default();
+                format = stats::StatusFormat::parse(name).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("unknown format: {}", name),
+                    )
+                })?;
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown option: {}", arg),
+                ))
             }
         }
     }
-    Ok(scores)
+    let config_dir = detect_config_directory();
+    let scores = load_scores(config_dir.join("scores.txt"))?;
+    let history = load_history(config_dir.join("history.txt"))?;
+    let compacted = prune::load_compacted_days(&config_dir)?;
+    println!(
+        "{}",
+        stats::status_line(&scores, &history, &compacted, format)
+    );
+    Ok(())
 }
 
-fn save_scores<P: AsRef<Path>>(path: P, scores: Scores) -> io::Result<()> {
-    if let Some(parent) = path.as_ref().parent() {
-        fs::create_dir_all(parent)?;
+// With --deck, the card is looked up in the deck for the overrides of its
+// scheduling.
+fn run_explain(args: &[String]) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let mut term = None;
+    let mut deck_path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--deck" => deck_path = args.next().map(PathBuf::from),
+            _ if arg.starts_with('-') => return Err(invalid(format!("unknown option: {}", arg))),
+            _ if term.is_none() => term = Some(arg),
+            _ => return Err(invalid("usage: explain TERM [--deck DECK]".to_owned())),
+        }
     }
-    let file = OpenOptions::new().write(true).create(true).open(path)?;
-    let mut writer = BufWriter::new(file);
-    for (term, score) in scores {
-        writeln!(writer, "{}\t{}\t{}", term, score.correct, score.incorrect)?;
+    let term = term.ok_or_else(|| invalid("usage: explain TERM [--deck DECK]".to_owned()))?;
+    let config_dir = detect_config_directory();
+    let scores = load_scores(config_dir.join("scores.txt"))?;
+    let (key, score) = scores
+        .get_key_value(term)
+        .or_else(|| scores.iter().find(|(_, score)| score.id == *term))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} has not been reviewed yet", term),
+            )
+        })?;
+    let overrides = match &deck_path {
+        Some(deck_path) => {
+            let mut settings = Settings::load(config_dir.join("config.toml"))?;
+            load_session_deck(deck_path, &mut settings)?
+                .iter()
+                .find(|entry| entry.key() == key || entry.card_id() == score.id)
+                .map(|entry| entry.overrides)
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("{} is not in {}", key, deck_path.display()),
+                    )
+                })?
+        }
+        None => scheduler::Overrides::default(),
+    };
+    let schedule = &score.schedule;
+    let now = unix_time();
+    let today = days::today();
+    let due_day = days::day(schedule.due);
+
+    println!("{}", key);
+    if !score.id.is_empty() {
+        println!("  id:          {}", score.id);
+    }
+    println!(
+        "  answers:     {}/{} correct",
+        score.correct,
+        score.total_tries()
+    );
+    println!("  repetitions: {}", schedule.repetitions);
+    println!("  interval:    {:.1} days", schedule.interval);
+    println!("  ease:        {:.2}", schedule.ease);
+    if let Some(ease) = overrides.ease {
+        println!("  deck ease:   {:.2}", ease);
+    }
+    if let Some(cap) = overrides.interval_cap {
+        println!("  cap:         {:.0} days", cap);
+    }
+    println!("  lapses:      {}", schedule.lapses);
+    println!("  due:         {}", stats::format_date(due_day));
+    println!();
+
+    let history = match schedule.repetitions {
+        0 if schedule.lapses > 0 => format!(
+            "It was forgotten at the last review, its {} lapse, so the interval \
+             went back to a day.",
+            OrdinalNum(schedule.lapses)
+        ),
+        0 => "It has not been answered without mistakes yet.".to_owned(),
+        1 => "It was answered correctly once, which schedules it a day later.".to_owned(),
+        2 => "It was answered correctly twice in a row, which schedules it six days later."
+            .to_owned(),
+        repetitions => format!(
+            "It was answered correctly {} times in a row, so the interval grew from 6 \
+             days by its ease of {:.2} at each review after the second.",
+            repetitions, schedule.ease
+        ),
+    };
+    println!("{}", history);
+    if schedule.ease < scheduler::INITIAL_EASE {
+        println!(
+            "Its ease is below the initial {:.2} because of answers with mistakes{}.",
+            scheduler::INITIAL_EASE,
+            if schedule.ease <= scheduler::MINIMUM_EASE {
+                ", and cannot drop any further"
+            } else {
+                ""
+            }
+        );
+    }
+    if schedule.is_mature() {
+        println!(
+            "Its interval reached {} days, so it counts as mature.",
+            scheduler::MATURE_INTERVAL_DAYS
+        );
+    }
+    if let Some(ease) = overrides.ease {
+        println!(
+            "The deck sets its ease to {:.2}, which grows the intervals in place of its own.",
+            ease
+        );
+    }
+    if let Some(cap) = overrides.interval_cap {
+        println!(
+            "The deck caps its interval at {:.0} days, however well it is answered.",
+            cap
+        );
+    }
+
+    if score.suspended {
+        println!("It is suspended and will not appear until unsuspended with `list --unsuspend`.");
+    } else if schedule.is_buried(now) {
+        println!(
+            "It is buried and will not appear before {}.",
+            stats::format_date(days::day(schedule.buried_until))
+        );
+    } else if due_day <= today {
+        println!("It is due now and will appear in the next session.");
+    } else {
+        println!(
+            "It will appear in sessions from {}, {}.",
+            stats::format_date(due_day),
+            match due_day - today {
+                1 => "tomorrow".to_owned(),
+                days => format!("in {} days", days),
+            }
+        );
+    }
+
+    println!();
+    println!("At the next review:");
+    for (mistakes, outcome) in [
+        (0.0, "no mistakes"),
+        (1.0, "one mistake"),
+        (2.0, "more mistakes"),
+    ] {
+        let next = schedule.review(mistakes, now.max(schedule.due), overrides);
+        println!(
+            "  {:<14} interval {:.1} days, ease {:.2}{}",
+            outcome,
+            next.interval,
+            next.ease,
+            if next.lapses > schedule.lapses {
+                ", a lapse"
+            } else {
+                ""
+            }
+        );
     }
     Ok(())
 }
 
-fn detect_config_directory() -> PathBuf {
-    env::var("XDG_CONFIG_HOME")
-        .map(|config_home| Path::new(&config_home).to_path_buf())
-        .or_else(|_| env::var("HOME").map(|home_dir| Path::new(&home_dir).join(".config")))
-        .unwrap_or_else(|_| env::temp_dir())
-        .join("vocab-trainer")
+fn run_tag(args: &[String]) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let mut deck_path = None;
+    let mut add = Vec::new();
+    let mut remove = Vec::new();
+    let mut conditions = Vec::new();
+    let mut dry_run = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--add" => add.extend(args.next().cloned()),
+            "--remove" => remove.extend(args.next().cloned()),
+            "--where" => {
+                let condition = args.next().map(String::as_str).unwrap_or_default();
+                conditions.push(filter::Condition::parse(condition).map_err(invalid)?);
+            }
+            "--dry-run" => dry_run = true,
+            _ if arg.starts_with('-') => return Err(invalid(format!("unknown option: {}", arg))),
+            _ => deck_path = Some(PathBuf::from(arg)),
+        }
+    }
+    let deck_path = match deck_path {
+        Some(path) if !add.is_empty() || !remove.is_empty() => path,
+        _ => {
+            return Err(invalid(
+                "usage: tag DECK [--add TAG] [--remove TAG] [--where CONDITION] [--dry-run]"
+                    .to_owned(),
+            ))
+        }
+    };
+    if let Some(tag) = add
+        .iter()
+        .find(|tag| tag.is_empty() || tag.contains(char::is_whitespace))
+    {
+        return Err(invalid(format!("invalid tag: {:?}", tag)));
+    }
+    let scores = load_scores(detect_config_directory().join("scores.txt"))?;
+    let never_reviewed = Score::default();
+
+    let source = fs::read_to_string(&deck_path)?;
+    let mut contents = String::with_capacity(source.len());
+    let mut changed = 0;
+    for (n, line) in source.lines().enumerate() {
+        let retagged = match parse_line(line, n)? {
+            Some(entry)
+                if conditions.iter().all(|condition| {
+                    let score = scores.get(entry.key()).unwrap_or(&never_reviewed);
+                    condition.matches(&entry, score)
+                }) =>
+            {
+                retag_line(line, &add, &remove)
+            }
+            _ => line.to_owned(),
+        };
+        if retagged != line {
+            println!("{}", retagged);
+            changed += 1;
+        }
+        contents.push_str(&retagged);
+        contents.push('\n');
+    }
+    if !dry_run && changed > 0 {
+        let mut backup = deck_path.clone().into_os_string();
+        backup.push(".bak");
+        fs::copy(&deck_path, &backup)?;
+        fs::write(&deck_path, contents)?;
+    }
+    println!(
+        "{} {} entries",
+        if dry_run { "Would retag" } else { "Retagged" },
+        changed
+    );
+    Ok(())
 }
 
-fn run_loop(ui: &mut GameUI, state: &mut GameState) -> Result<(), ReadlineError> {
-    'outer: while let Some(question) = state.next_question() {
-        ui.notify_question(&question, &state);
+// Edits the attributes after the last '/' only, leaving the rest of the line
+// as written. Lines without phrases have no place for them, and a '/' there
+// is part of the term.
+fn retag_line(line: &str, add: &[String], remove: &[String]) -> String {
+    if !line.contains(" /") {
+        return line.to_owned();
+    }
+    let (head, attributes) = line.split_at(line.rfind('/').map_or(line.len(), |i| i + 1));
+    let old_tokens: Vec<&str> = attributes.split_whitespace().collect();
+    let mut tokens: Vec<String> = old_tokens
+        .iter()
+        .filter(|token| {
+            token
+                .strip_prefix('#')
+                .is_none_or(|tag| !remove.iter().any(|r| r == tag))
+        })
+        .map(|token| token.to_string())
+        .collect();
+    for tag in add {
+        let token = format!("#{}", tag);
+        if !tokens.contains(&token) {
+            tokens.push(token);
+        }
+    }
+    if tokens == old_tokens {
+        return line.to_owned();
+    }
+    let mut line = head.to_owned();
+    for token in tokens {
+        line.push(' ');
+        line.push_str(&token);
+    }
+    line
+}
 
-        loop {
-            let hint = QuestionHint {
-                entry: question.entry.clone(),
-                mistakes: state.mistakes,
+// Mastered entries are moved to DECK.archive unless other criteria are given
+// with --where or in the [archive] section of the config.
+const ARCHIVE_CRITERIA: [&str; 2] = ["interval>=21", "rate>=90"];
+
+fn run_archive(args: &[String]) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let mut deck_path = None;
+    let mut criteria = Vec::new();
+    let mut restore = false;
+    let mut dry_run = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--where" => criteria.extend(args.next().cloned()),
+            "--restore" => restore = true,
+            "--dry-run" => dry_run = true,
+            _ if arg.starts_with('-') => return Err(invalid(format!("unknown option: {}", arg))),
+            _ => deck_path = Some(PathBuf::from(arg)),
+        }
+    }
+    let deck_path = deck_path.ok_or_else(|| {
+        invalid("usage: archive DECK [--where CONDITION] [--restore] [--dry-run]".to_owned())
+    })?;
+    let config_dir = detect_config_directory();
+    // Restoring brings back every archived entry unless told otherwise.
+    if criteria.is_empty() && !restore {
+        let settings = Settings::load(config_dir.join("config.toml"))?;
+        criteria = settings
+            .get_str_array("archive", "where")
+            .unwrap_or_else(|| ARCHIVE_CRITERIA.map(str::to_owned).to_vec());
+    }
+    let conditions = criteria
+        .iter()
+        .map(|condition| filter::Condition::parse(condition))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(invalid)?;
+    let scores = load_scores(config_dir.join("scores.txt"))?;
+
+    let archive_path = deck_path.with_extension("archive");
+    let (from_path, to_path) = if restore {
+        (&archive_path, &deck_path)
+    } else {
+        (&deck_path, &archive_path)
+    };
+    let source = match fs::read_to_string(from_path) {
+        Ok(source) => source,
+        Err(error) if restore && error.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(error),
+    };
+    let mut kept = String::with_capacity(source.len());
+    let mut moved = String::new();
+    let mut count = 0;
+    for (n, line) in source.lines().enumerate() {
+        let selected = parse_line(line, n)?.is_some_and(|entry| {
+            // Archiving needs scores to go by, unlike restoring.
+            let score = match scores.get(entry.key()) {
+                Some(score) => score,
+                None if restore => &Score::default(),
+                None => return false,
             };
-            match ui.wait_for_input(hint)? {
-                UIResponse::Return(input) => {
-                    if state.answer_question(&question, input) {
-                        ui.notify_correct(&question, &state);
-                        break;
-                    } else {
-                        ui.notify_incorrect(&question, &state);
-                    }
+            conditions
+                .iter()
+                .all(|condition| condition.matches(&entry, score))
+        });
+        let lines = if selected { &mut moved } else { &mut kept };
+        lines.push_str(line);
+        lines.push('\n');
+        if selected {
+            println!("{}", line);
+            count += 1;
+        }
+    }
+    if !dry_run && count > 0 {
+        let mut appended = match fs::read_to_string(to_path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error),
+        };
+        // A file without a final newline would have the first entry moved
+        // joined to its last line.
+        if !appended.is_empty() && !appended.ends_with('\n') {
+            appended.push('\n');
+        }
+        appended.push_str(&moved);
+        // Both files are written aside, and renamed over the old ones only
+        // once both are written, so that an error leaves the entries where
+        // they were rather than in both files or in neither.
+        let temp_path = |path: &Path| {
+            let mut temp_name = path.file_name().unwrap_or_default().to_owned();
+            temp_name.push(".tmp");
+            path.with_file_name(temp_name)
+        };
+        fs::write(temp_path(to_path), appended)?;
+        fs::write(temp_path(from_path), kept)?;
+        fs::rename(temp_path(to_path), to_path)?;
+        fs::rename(temp_path(from_path), from_path)?;
+    }
+    println!(
+        "{} {} entries {} {}",
+        match (dry_run, restore) {
+            (true, true) => "Would restore",
+            (true, false) => "Would archive",
+            (false, true) => "Restored",
+            (false, false) => "Archived",
+        },
+        count,
+        if restore { "to" } else { "in" },
+        to_path.display()
+    );
+    Ok(())
+}
+
+fn run_list(args: &[String]) -> io::Result<()> {
+    let mut suspended_only = false;
+    let mut starred_only = false;
+    let mut unsuspend = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--suspended" => suspended_only = true,
+            "--starred" => starred_only = true,
+            "--unsuspend" => unsuspend.extend(args.next().cloned()),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown option: {}", arg),
+                ))
+            }
+        }
+    }
+    let score_path = detect_config_directory().join("scores.txt");
+    let mut scores = load_scores(&score_path)?;
+    if !unsuspend.is_empty() {
+        for key in &unsuspend {
+            match scores.get_mut(key) {
+                Some(score) if score.suspended => {
+                    score.suspended = false;
+                    println!("Unsuspended {}", key);
                 }
-                UIResponse::Quit => break 'outer,
+                _ => println!("{} is not suspended", key),
             }
         }
+        return save_scores(&score_path, scores);
+    }
+    let mut keys: Vec<&String> = scores
+        .iter()
+        .filter(|(_, score)| !suspended_only || score.suspended)
+        .filter(|(_, score)| !starred_only || score.starred)
+        .map(|(key, _)| key)
+        .collect();
+    keys.sort();
+    for key in keys {
+        let score = &scores[key];
+        println!(
+            "{}\t{}/{}\t{}{}{}",
+            key,
+            score.correct,
+            score.total_tries(),
+            stats::format_date(days::day(score.schedule.due)),
+            if score.suspended { "\tsuspended" } else { "" },
+            if score.starred { "\tstarred" } else { "" }
+        );
     }
     Ok(())
 }
 
-fn main() {
+fn run_import(args: &[String]) -> io::Result<()> {
+    let mut memrise_path = None;
+    let mut anki_path = None;
+    let mut anki_deck = None;
+    let mut fields = (1, 2);
+    let mut output_path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--memrise" => memrise_path = args.next().map(PathBuf::from),
+            "--anki" => anki_path = args.next().map(PathBuf::from),
+            "--deck" => anki_deck = args.next().cloned(),
+            "--fields" => {
+                let value = args.next().map(String::as_str).unwrap_or_default();
+                fields = value
+                    .split_once(',')
+                    .and_then(|(term, phrase)| Some((term.parse().ok()?, phrase.parse().ok()?)))
+                    .filter(|&(term, phrase)| term > 0 && phrase > 0 && term != phrase)
+                    .ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("invalid fields: {} (use TERM,PHRASE, as in 1,2)", value),
+                        )
+                    })?;
+            }
+            "-o" | "--output" => output_path = args.next().map(PathBuf::from),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown option: {}", arg),
+                ))
+            }
+        }
+    }
+    let entries = match (memrise_path, anki_path) {
+        (Some(path), _) => import::import_memrise(&fs::read_to_string(path)?)?,
+        (None, Some(path)) => {
+            let deck = anki_deck.ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "no Anki deck given (use --deck NAME)",
+                )
+            })?;
+            let notes = import::import_anki(&path, &deck, fields)?;
+            seed_scores(&notes)?;
+            notes.into_iter().map(|note| note.entry).collect()
+        }
+        (None, None) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no source given (use --memrise FILE or --anki COLLECTION)",
+            ))
+        }
+    };
+    let mut output: Box<dyn Write> = match &output_path {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout()),
+    };
+    for entry in &entries {
+        writeln!(output, "{}", entry)?;
+    }
+    output.flush()?;
+    if let Some(path) = output_path {
+        eprintln!("Imported {} entries into {}", entries.len(), path.display());
+    }
+    Ok(())
+}
+
+// Terms practiced here already keep their own scores and history, which also
+// keeps the history from being imported twice.
+fn seed_scores(notes: &[import::AnkiNote]) -> io::Result<()> {
+    let config_dir = detect_config_directory();
+    let score_path = config_dir.join("scores.txt");
+    let mut scores = load_scores(&score_path)?;
+    let mut attempts = Vec::new();
+    let mut seeded = 0;
+    let mut kept = 0;
+    for note in notes {
+        let Some((score, reviews)) = &note.review else {
+            continue;
+        };
+        let key = note.entry.key();
+        if scores.contains_key(key) {
+            kept += 1;
+            continue;
+        }
+        scores.insert(key.to_owned(), score.clone());
+        seeded += 1;
+        attempts.extend(
+            reviews
+                .iter()
+                .map(|attempt| (key.to_owned(), attempt.clone())),
+        );
+    }
+    if seeded > 0 {
+        save_scores(&score_path, scores)?;
+        HistoryWriter::default().append(config_dir.join("history.txt"), &attempts)?;
+    }
+    eprintln!(
+        "Seeded the scores of {} terms with {} reviews from Anki",
+        seeded,
+        attempts.len()
+    );
+    if kept > 0 {
+        eprintln!("Kept the scores of {} terms already practiced here", kept);
+    }
+    Ok(())
+}
+
+fn run_generate(args: &[String]) -> io::Result<()> {
+    let mut words_path = None;
+    let mut dictionary_path = None;
+    let mut lang = None;
+    let mut output_path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" => words_path = args.next().map(PathBuf::from),
+            "--dictionary" => dictionary_path = args.next().map(PathBuf::from),
+            "--lang" => lang = args.next().cloned(),
+            "-o" | "--output" => output_path = args.next().map(PathBuf::from),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown option: {}", arg),
+                ))
+            }
+        }
+    }
+    let words_path = words_path.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "usage: generate --from WORDS [--lang LANG] [--dictionary FILE] [-o FILE]",
+        )
+    })?;
+    // Word lists are read like frequency lists, so counts after the words
+    // are dropped.
+    let words = load_frequency_list(words_path.clone())?;
+    let dictionary = match dictionary_path {
+        Some(path) => import::parse_dictionary(&fs::read_to_string(path)?),
+        None => HashMap::new(),
+    };
+    let entries = import::generate_entries(&words, &dictionary, lang.as_deref());
+    let mut output: Box<dyn Write> = match &output_path {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout()),
+    };
+    writeln!(
+        output,
+        "; Generated from {}. Entries tagged #todo need their phrases.",
+        words_path.display()
+    )?;
+    writeln!(output)?;
+    for entry in &entries {
+        writeln!(output, "{}", entry)?;
+    }
+    output.flush()?;
+    let todo = entries
+        .iter()
+        .filter(|entry| entry.tags.iter().any(|tag| &**tag == "todo"))
+        .count();
+    eprintln!(
+        "Generated {} entries, {} of them without phrases",
+        entries.len(),
+        todo
+    );
+    Ok(())
+}
+
+fn run_export(args: &[String]) -> io::Result<()> {
+    let mut deck_path = None;
+    let mut cards_path = None;
+    let mut tags = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--cards" => cards_path = args.next().map(PathBuf::from),
+            "--tag" => tags.extend(args.next().cloned()),
+            _ if arg.starts_with('-') => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown option: {}", arg),
+                ))
+            }
+            _ => deck_path = Some(PathBuf::from(arg)),
+        }
+    }
+    let (deck_path, cards_path) = match (deck_path, cards_path) {
+        (Some(deck_path), Some(cards_path)) => (deck_path, cards_path),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "usage: export DECK --cards OUT.md [--tag TAG]...",
+            ))
+        }
+    };
+    let entries: Vec<Entry> = load_deck(&deck_path)?
+        .into_iter()
+        .filter(|entry| entry.has_any_tag(&tags))
+        .collect();
+    fs::write(&cards_path, export::render_cards(&entries))?;
+    println!("Wrote {} cards to {}", entries.len(), cards_path.display());
+    Ok(())
+}
+
+fn run_pack(args: &[String]) -> io::Result<()> {
+    let mut dir = None;
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = args.next().map(PathBuf::from),
+            _ if arg.starts_with('-') => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown option: {}", arg),
+                ))
+            }
+            _ => dir = Some(PathBuf::from(arg)),
+        }
+    }
+    let dir = dir.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "usage: pack DIR [-o DECK.vtb]")
+    })?;
+    let output = output.unwrap_or_else(|| {
+        let name = dir.file_name().unwrap_or_default().to_string_lossy();
+        PathBuf::from(format!("{}.vtb", name))
+    });
+    let count = archive::pack(&dir, &output)?;
+    println!("Packed {} files into {}", count, output.display());
+    Ok(())
+}
+
+// Moves the scores and history of terms missing from the deck to the entry
+// with the same card id, which happens when a term was renamed.
+fn run_relink(args: &[String]) -> io::Result<()> {
+    let mut deck_path = None;
+    let mut dry_run = false;
+    for arg in args {
+        match arg.as_str() {
+            "--dry-run" => dry_run = true,
+            _ if arg.starts_with('-') => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown option: {}", arg),
+                ))
+            }
+            _ => deck_path = Some(PathBuf::from(arg)),
+        }
+    }
+    let deck_path = deck_path.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "usage: relink DECK [--dry-run]",
+        )
+    })?;
+    let entries = load_deck(&deck_path)?;
     let config_dir = detect_config_directory();
     let score_path = config_dir.join("scores.txt");
-    let entries = load_entries(io::stdin()).expect("load entries");
-    let scores = load_scores(&score_path).expect("load scores");
-    let mut state = GameState::new(entries, scores);
-    let mut ui = GameUI::new();
-    run_loop(&mut ui, &mut state).expect("run loop");
-    save_scores(&score_path, state.scores).expect("save scores");
+    let history_path = config_dir.join("history.txt");
+    let mut scores = load_scores(&score_path)?;
+
+    let keys: HashSet<&str> = entries.iter().map(Entry::key).collect();
+    let mut orphans: HashMap<String, String> = scores
+        .iter()
+        .filter(|(term, _)| !keys.contains(term.as_str()))
+        .map(|(term, score)| {
+            let id = if score.id.is_empty() {
+                hash_term(term)
+            } else {
+                score.id.clone()
+            };
+            (id, term.clone())
+        })
+        .collect();
+    let mut renames = HashMap::new();
+    // A term that has a score, or took one over already, keeps it rather than
+    // having it replaced.
+    let mut relinked = HashSet::new();
+    for entry in &entries {
+        if scores.contains_key(entry.key()) || relinked.contains(entry.key()) {
+            continue;
+        }
+        if let Some(old_term) = orphans.remove(&entry.card_id()) {
+            println!("{} -> {}", old_term, entry.key());
+            relinked.insert(entry.key());
+            renames.insert(old_term, entry.key().to_owned());
+        }
+    }
+    let mut orphans: Vec<(String, String)> = orphans.into_iter().collect();
+    orphans.sort_by(|a, b| a.1.cmp(&b.1));
+    for (id, term) in orphans {
+        println!("orphaned: {} (id={})", term, id);
+    }
+
+    if !dry_run && !renames.is_empty() {
+        for (old_term, new_term) in &renames {
+            if let Some(mut score) = scores.remove(old_term) {
+                if score.id.is_empty() {
+                    score.id = hash_term(old_term);
+                }
+                scores.insert(new_term.clone(), score);
+            }
+        }
+        // The history is written aside first, and renamed into place once
+        // the scores are saved, so that an error leaves the history as it
+        // was rather than renamed for scores that weren't.
+        let temp_path = history_path.with_extension("txt.tmp");
+        let has_history = history_path.exists();
+        if has_history {
+            let mut contents = String::new();
+            for line in fs::read_to_string(&history_path)?.lines() {
+                let mut parts: Vec<&str> = line.split('\t').collect();
+                if let Some(new_term) = parts.get(1).and_then(|term| renames.get(*term)) {
+                    parts[1] = new_term;
+                }
+                contents.push_str(&parts.join("\t"));
+                contents.push('\n');
+            }
+            fs::write(&temp_path, contents)?;
+        }
+        save_scores(&score_path, scores)?;
+        if has_history {
+            fs::rename(&temp_path, &history_path)?;
+        }
+        println!("Relinked {} cards", renames.len());
+    }
+    Ok(())
+}
+
+fn run_client(args: &[String]) -> io::Result<()> {
+    let mut socket_path = None;
+    let mut words = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--socket" => socket_path = args.next().map(PathBuf::from),
+            _ if arg.starts_with("--") => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown option: {}", arg),
+                ))
+            }
+            _ => words.push(arg.as_str()),
+        }
+    }
+    let (command, answer) = match words.as_slice() {
+        [] => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "usage: client [--socket PATH] COMMAND [ANSWER...]",
+            ))
+        }
+        [command] => (command, None),
+        [command, answer @ ..] => (command, Some(answer.join(" "))),
+    };
+    let socket_path = socket_path.unwrap_or_else(detect_socket_path);
+    let response = daemon::send(&socket_path, command, answer.as_deref())?;
+    println!("{}", response);
+    if response.starts_with("{\"error\"") {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+fn run_sync(args: &[String]) -> io::Result<()> {
+    let config_dir = detect_config_directory();
+    let settings = Settings::load(config_dir.join("config.toml"))?;
+    let mut remote = settings.get_str("sync", "remote").map(str::to_owned);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--remote" => remote = args.next().cloned(),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown option: {}", arg),
+                ))
+            }
+        }
+    }
+    let remote = remote.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no remote configured (set [sync] remote in config.toml or pass --remote)",
+        )
+    })?;
+    // The merge base is kept apart from the git checkout, which pulling
+    // overwrites with the remote files. Before it was, the checkout held it.
+    let base_dir = config_dir.join("sync-base");
+    let checkout_dir = config_dir.join("sync");
+    if !base_dir.exists() && checkout_dir.exists() {
+        SyncFiles::load(&checkout_dir)?.save(&base_dir)?;
+    }
+    let mut backend: Box<dyn SyncBackend> = match settings.get_str("sync", "backend") {
+        None | Some("git") => {
+            let branch = settings.get_str("sync", "branch").unwrap_or("main");
+            Box::new(GitBackend::new(
+                checkout_dir.clone(),
+                remote.clone(),
+                branch.to_owned(),
+            ))
+        }
+        #[cfg(feature = "net")]
+        Some("webdav") => Box::new(WebDavBackend::new(
+            &remote,
+            settings.get_str("sync", "username"),
+            settings.get_str("sync", "password"),
+        )),
+        #[cfg(not(feature = "net"))]
+        Some("webdav") => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the webdav backend requires building with the net feature",
+            ))
+        }
+        Some(backend) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown sync backend: {}", backend),
+            ))
+        }
+    };
+    sync::sync(backend.as_mut(), &config_dir, &base_dir)?;
+    println!("Synced with {}", remote);
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("init") => {
+            let lang = args.get(1).map(String::as_str).unwrap_or("example");
+            match init_deck(lang) {
+                Ok(path) => println!(
+                    "Wrote {}. Start a quiz with: vocab-trainer {}",
+                    path.display(),
+                    path.display()
+                ),
+                Err(error) => {
+                    eprintln!("init: {}", error);
+                    process::exit(1);
+                }
+            }
+        }
+        Some("stats") => {
+            if let Err(error) = run_stats(&args[1..]) {
+                eprintln!("stats: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("status") => {
+            if let Err(error) = run_status(&args[1..]) {
+                eprintln!("status: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("explain") => {
+            if let Err(error) = run_explain(&args[1..]) {
+                eprintln!("explain: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("export") => {
+            if let Err(error) = run_export(&args[1..]) {
+                eprintln!("export: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("import") => {
+            if let Err(error) = run_import(&args[1..]) {
+                eprintln!("import: {}", error);
+                process::exit(1);
+            }
+        }
+        #[cfg(feature = "clipboard")]
+        Some("capture") => {
+            if let Err(error) = capture::run(&args[1..]) {
+                eprintln!("capture: {}", error);
+                process::exit(1);
+            }
+        }
+        #[cfg(not(feature = "clipboard"))]
+        Some("capture") => {
+            eprintln!("capture: requires building with the clipboard feature");
+            process::exit(1);
+        }
+        Some("inbox") => {
+            if let Err(error) = inbox::run(&args[1..]) {
+                eprintln!("inbox: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("generate") => {
+            if let Err(error) = run_generate(&args[1..]) {
+                eprintln!("generate: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("list") => {
+            if let Err(error) = run_list(&args[1..]) {
+                eprintln!("list: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("pack") => {
+            if let Err(error) = run_pack(&args[1..]) {
+                eprintln!("pack: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("relink") => {
+            if let Err(error) = run_relink(&args[1..]) {
+                eprintln!("relink: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("tag") => {
+            if let Err(error) = run_tag(&args[1..]) {
+                eprintln!("tag: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("prune") => {
+            if let Err(error) = prune::run(&args[1..]) {
+                eprintln!("prune: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("replay") => {
+            if let Err(error) = replay::run(&args[1..]) {
+                eprintln!("replay: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("sync") => {
+            if let Err(error) = run_sync(&args[1..]) {
+                eprintln!("sync: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("achievements") => {
+            if let Err(error) = run_achievements(&args[1..]) {
+                eprintln!("achievements: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("archive") => {
+            if let Err(error) = run_archive(&args[1..]) {
+                eprintln!("archive: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("host") => {
+            if let Err(error) = classroom::host(&args[1..]) {
+                eprintln!("host: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("join") => {
+            if let Err(error) = classroom::join(&args[1..]) {
+                eprintln!("join: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("duel") => match duel::run(&args[1..]) {
+            Ok(0) => {}
+            Ok(code) => process::exit(code),
+            Err(error) => {
+                eprintln!("duel: {}", error);
+                process::exit(1);
+            }
+        },
+        Some("client") => {
+            if let Err(error) = run_client(&args[1..]) {
+                eprintln!("client: {}", error);
+                process::exit(1);
+            }
+        }
+        #[cfg(feature = "bench")]
+        Some("bench") => bench::run(args.get(1).map(String::as_str)),
+        #[cfg(feature = "fuzz")]
+        Some("fuzz") => {
+            if let Err(error) = fuzz::run(&args[1..]) {
+                eprintln!("fuzz: {}", error);
+                process::exit(1);
+            }
+        }
+        Some("quiz") => match QuizOptions::parse(&args[1..]) {
+            Ok(options) => run_quiz(options),
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        },
+        None if termion::is_tty(&io::stdin()) => match menu::run() {
+            Ok(Some(options)) => run_quiz(options),
+            Ok(None) => {}
+            Err(error) => {
+                eprintln!("menu: {}", error);
+                process::exit(1);
+            }
+        },
+        _ => match QuizOptions::parse(&args) {
+            Ok(options) => run_quiz(options),
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask_term_reveals_letters_in_order() {
+        assert_eq!(
+            mask_term("hello world", 0, Some(Script::Latin)),
+            "_____ _____"
+        );
+        assert_eq!(
+            mask_term("hello world", 6, Some(Script::Latin)),
+            "hello w____"
+        );
+        assert_eq!(mask_term("hello", 9, Some(Script::Latin)), "hello");
+    }
+
+    #[test]
+    fn mask_term_keeps_symbols_and_counts_digits() {
+        assert_eq!(mask_term("o'clock", 1, Some(Script::Latin)), "o'_____");
+        assert_eq!(
+            mask_term("well-being", 4, Some(Script::Latin)),
+            "well-_____"
+        );
+        assert_eq!(mask_term("R2-D2", 2, None), "R2-__");
+    }
+
+    #[test]
+    fn mask_term_reveals_marks_with_their_letter() {
+        // "café" with a combining acute accent.
+        let term = "cafe\u{301}";
+        assert_eq!(mask_term(term, 3, Some(Script::Latin)), "caf_");
+        assert_eq!(mask_term(term, 4, Some(Script::Latin)), term);
+        assert_eq!(mask_term(term, 4, None), term);
+        assert_eq!(mask_term("ё", 0, Some(Script::Cyrillic)), "_");
+        assert_eq!(
+            mask_term("\u{435}\u{308}\u{436}", 1, Some(Script::Cyrillic)),
+            "\u{435}\u{308}_"
+        );
+    }
+
+    #[test]
+    fn mask_term_handles_abugidas() {
+        // न म स ् त े: the virama and the vowel sign go with their consonants.
+        assert_eq!(mask_term("नमस्ते", 3, Some(Script::Devanagari)), "नमस्_");
+        assert_eq!(mask_term("नमस्ते", 4, Some(Script::Devanagari)), "नमस्ते");
+        assert_eq!(mask_term("ที่นี่", 1, Some(Script::Thai)), "ที่_");
+        assert_eq!(mask_term("ที่นี่", 1, None), "ที่_");
+    }
+
+    #[test]
+    fn mask_term_handles_pointed_hebrew() {
+        assert_eq!(mask_term("שָׁלוֹם", 1, Some(Script::Hebrew)), "שָׁ___");
+        assert_eq!(mask_term("שָׁלוֹם", 4, Some(Script::Hebrew)), "שָׁלוֹם");
+    }
+
+    #[test]
+    fn mask_term_counts_syllables_and_characters_as_letters() {
+        assert_eq!(mask_term("안녕하세요", 2, Some(Script::Hangul)), "안녕___");
+        assert_eq!(mask_term("日本語", 1, Some(Script::Han)), "日__");
+        assert_eq!(mask_term("ありがとう", 3, Some(Script::Kana)), "ありが__");
+    }
 }
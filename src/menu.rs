@@ -0,0 +1,125 @@
+// The menu shown when the program is started on a terminal without
+// arguments, which would otherwise wait silently for a deck on the standard
+// input. A deck can still be piped in, or read from the terminal with `-`.
+//
+// The decks practiced lately come first, so that pressing Enter twice starts
+// the last one again.
+
+use std::env;
+use std::fs;
+use std::io::{self, Write as _};
+use std::path::PathBuf;
+
+use crate::{detect_config_directory, load_recent_decks, QuizOptions};
+
+const MODES: &[(&str, &str)] = &[
+    ("review", "questions in the usual order"),
+    ("adaptive", "harder questions as you get them right"),
+    ("batch", "answers checked together at the end"),
+    ("points", "points for every answer, against your high score"),
+];
+
+// Returns the options to start a quiz with, or `None` if nothing was chosen.
+pub fn run() -> io::Result<Option<QuizOptions>> {
+    let recent = load_recent_decks(&detect_config_directory().join("recent.txt"))?;
+    let recent: Vec<PathBuf> = recent.into_iter().filter(|path| path.is_file()).collect();
+    let mut decks = recent.clone();
+    for deck in find_decks()? {
+        if !fs::canonicalize(&deck).is_ok_and(|path| recent.contains(&path)) {
+            decks.push(deck);
+        }
+    }
+    let cwd = env::current_dir()?;
+    if decks.is_empty() {
+        println!("No deck given, and there are no decks here.");
+        println!("Create one with `vocab-trainer init LANG`, or press Enter to leave.");
+    } else {
+        println!("No deck given. Pick one to start a quiz, or type q to leave.");
+    }
+    println!();
+    for (i, deck) in decks.iter().enumerate() {
+        println!(
+            "  {}) {}{}",
+            i + 1,
+            deck.strip_prefix(&cwd).unwrap_or(deck).display(),
+            if i < recent.len() { "  (recent)" } else { "" }
+        );
+    }
+    println!("  or type the path of a deck");
+    let deck = loop {
+        let Some(answer) = prompt(if decks.is_empty() { "Deck" } else { "Deck [1]" })? else {
+            return Ok(None);
+        };
+        let answer = match answer.as_str() {
+            "" if !decks.is_empty() => "1".to_owned(),
+            "" | "q" => return Ok(None),
+            _ => answer,
+        };
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=decks.len()).contains(&n) => break decks[n - 1].clone(),
+            _ if PathBuf::from(&answer).is_file() => break PathBuf::from(answer),
+            _ => println!("No such deck: {}", answer),
+        }
+    };
+
+    println!();
+    for (i, (name, description)) in MODES.iter().enumerate() {
+        println!("  {}) {:<9} {}", i + 1, name, description);
+    }
+    let mode = loop {
+        let Some(answer) = prompt("Mode [1]")? else {
+            return Ok(None);
+        };
+        if answer.is_empty() {
+            break MODES[0].0;
+        }
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=MODES.len()).contains(&n) => break MODES[n - 1].0,
+            _ => match MODES.iter().find(|(name, _)| *name == answer) {
+                Some((name, _)) => break name,
+                None => println!("No such mode: {}", answer),
+            },
+        }
+    };
+    println!();
+
+    let mut options = QuizOptions {
+        deck: Some(deck),
+        ..QuizOptions::default()
+    };
+    match mode {
+        "adaptive" => options.adaptive = true,
+        "batch" => options.batch = true,
+        "points" => options.points = true,
+        _ => {}
+    }
+    Ok(Some(options))
+}
+
+// Returns `None` at the end of the input.
+fn prompt(label: &str) -> io::Result<Option<String>> {
+    print!("{}> ", label);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line)? == 0 {
+        println!();
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_owned()))
+}
+
+// Deck files in the current directory.
+fn find_decks() -> io::Result<Vec<PathBuf>> {
+    let mut decks: Vec<PathBuf> = fs::read_dir(".")?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.is_file()
+                && path.extension().is_some_and(|extension| {
+                    ["txt", "zip", "vtb", "gz", "tgz"].contains(&&*extension.to_string_lossy())
+                })
+        })
+        .map(|path| path.strip_prefix(".").map(PathBuf::from).unwrap_or(path))
+        .collect();
+    decks.sort();
+    Ok(decks)
+}
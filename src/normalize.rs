@@ -0,0 +1,603 @@
+// Differences in spelling that answers are forgiven for, depending on the
+// language of the deck:
+//
+//   [deck]
+//   language = "de"
+//
+// or the language detected from the terms, when the deck doesn't say.
+// Apostrophes are normalized in every language. The others are picked by the
+// language, or listed by name to replace the language's:
+//
+//   [matching]
+//   normalizers = ["apostrophes", "ascii_fold"]
+//
+// Decks of measurements or dates can turn on the ones for numbers, so that
+// "1,000 m" matches "1000m", and dates, so that "3rd March" matches
+// "March 3":
+//
+//   [matching]
+//   normalizers = ["apostrophes", "numbers", "dates"]
+//
+// Terms and answers are normalized alike before being compared, and so are
+// the terms of entries to tell whether they are the same word.
+
+use crate::normalize_whitespace;
+use crate::settings::Settings;
+
+pub trait Normalizer: Send + Sync {
+    // Called after case folding, when case is ignored.
+    fn normalize(&self, text: &str) -> String;
+
+    // Case folding for the languages that `to_lowercase` gets wrong.
+    fn to_lowercase(&self, _text: &str) -> Option<String> {
+        None
+    }
+
+    // Whitespace folding for the normalizers that need some of the spaces
+    // kept apart from the others.
+    fn fold_whitespace(&self, _text: &str) -> Option<String> {
+        None
+    }
+}
+
+// "café" matches "cafe", for languages where accents are often left out.
+struct AsciiFold;
+
+impl Normalizer for AsciiFold {
+    fn normalize(&self, text: &str) -> String {
+        let mut folded = String::with_capacity(text.len());
+        for c in text.chars() {
+            match fold_char(c) {
+                Some(replacement) => folded.push_str(replacement),
+                // Combining diacritical marks.
+                None if ('\u{300}'..='\u{36f}').contains(&c) => {}
+                None => folded.push(c),
+            }
+        }
+        folded
+    }
+}
+
+fn fold_char(c: char) -> Option<&'static str> {
+    let replacement = match c {
+        'à'..='å' | 'ā' | 'ă' | 'ą' => "a",
+        'À'..='Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'ç' | 'ć' | 'ĉ' | 'č' => "c",
+        'Ç' | 'Ć' | 'Ĉ' | 'Č' => "C",
+        'ď' | 'đ' | 'ð' => "d",
+        'Ď' | 'Đ' | 'Ð' => "D",
+        'è'..='ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'È'..='Ë' | 'Ē' | 'Ė' | 'Ę' | 'Ě' => "E",
+        'ğ' | 'ģ' => "g",
+        'Ğ' | 'Ģ' => "G",
+        'ì'..='ï' | 'ī' | 'į' | 'ı' => "i",
+        'Ì'..='Ï' | 'Ī' | 'Į' | 'İ' => "I",
+        'ķ' => "k",
+        'Ķ' => "K",
+        'ĺ' | 'ļ' | 'ľ' | 'ł' => "l",
+        'Ĺ' | 'Ļ' | 'Ľ' | 'Ł' => "L",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'Ñ' | 'Ń' | 'Ņ' | 'Ň' => "N",
+        'ò'..='ö' | 'ø' | 'ō' | 'ő' => "o",
+        'Ò'..='Ö' | 'Ø' | 'Ō' | 'Ő' => "O",
+        'œ' => "oe",
+        'Œ' => "OE",
+        'ŕ' | 'ř' => "r",
+        'Ŕ' | 'Ř' => "R",
+        'ś' | 'ş' | 'š' | 'ș' => "s",
+        'Ś' | 'Ş' | 'Š' | 'Ș' => "S",
+        'ß' => "ss",
+        'ẞ' => "SS",
+        'ţ' | 'ť' | 'ț' => "t",
+        'Ţ' | 'Ť' | 'Ț' => "T",
+        'þ' => "th",
+        'Þ' => "TH",
+        'ù'..='ü' | 'ū' | 'ů' | 'ű' | 'ų' => "u",
+        'Ù'..='Ü' | 'Ū' | 'Ů' | 'Ű' | 'Ų' => "U",
+        'ý' | 'ÿ' => "y",
+        'Ý' | 'Ÿ' => "Y",
+        'ź' | 'ż' | 'ž' => "z",
+        'Ź' | 'Ż' | 'Ž' => "Z",
+        _ => return None,
+    };
+    Some(replacement)
+}
+
+// "Strasse" matches "Straße", as written in Switzerland or without the key.
+struct GermanSharpS;
+
+impl Normalizer for GermanSharpS {
+    fn normalize(&self, text: &str) -> String {
+        text.replace('ß', "ss").replace('ẞ', "SS")
+    }
+}
+
+// Turkish pairs dotted İ with i and dotless I with ı, where `to_lowercase`
+// turns I into i and İ into i followed by a combining dot.
+struct TurkishDottedI;
+
+impl Normalizer for TurkishDottedI {
+    fn normalize(&self, text: &str) -> String {
+        text.replace("i\u{307}", "i")
+    }
+
+    fn to_lowercase(&self, text: &str) -> Option<String> {
+        Some(text.replace('İ', "i").replace('I', "ı").to_lowercase())
+    }
+}
+
+// A sigma at the end of a word is written ς, which is easy to type as σ.
+struct GreekFinalSigma;
+
+impl Normalizer for GreekFinalSigma {
+    fn normalize(&self, text: &str) -> String {
+        text.replace('ς', "σ")
+    }
+}
+
+// Typographic apostrophes as typed on phones, or copied from the deck's
+// source, match the plain one.
+struct Apostrophes;
+
+impl Normalizer for Apostrophes {
+    fn normalize(&self, text: &str) -> String {
+        text.replace(
+            ['\u{2019}', '\u{2018}', '\u{2bc}', '\u{b4}', '`', '\u{2032}'],
+            "'",
+        )
+    }
+}
+
+// Numbers are written without thousands separators or trailing zeros after
+// the decimal point, which is always a point, and units as their symbols right
+// after the number. A comma followed by three digits separates thousands, and
+// otherwise it is a decimal comma. In the languages writing decimal commas,
+// it is always one, and a point followed by three digits separates thousands.
+struct Numbers {
+    decimal_comma: bool,
+}
+
+// Languages writing "1.000,5" for 1000.5.
+const DECIMAL_COMMA_LANGUAGES: &[&str] = &[
+    "bg", "cs", "da", "de", "el", "es", "fi", "fr", "hr", "hu", "id", "it", "nb", "nl", "nn", "no",
+    "pl", "pt", "ro", "ru", "sk", "sl", "sr", "sv", "tr", "uk", "vi",
+];
+
+// The spaces that separate thousands, as in "1 000", unlike the plain one.
+const THOUSANDS_SPACES: [char; 3] = ['\u{a0}', '\u{202f}', '\u{2009}'];
+
+impl Normalizer for Numbers {
+    // Spaces separating thousands are dropped before the others are folded
+    // into plain ones, which would leave the groups apart.
+    fn fold_whitespace(&self, text: &str) -> Option<String> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut folded = String::with_capacity(text.len());
+        for (i, &c) in chars.iter().enumerate() {
+            let is_group = |digits: &[char]| {
+                digits.len() >= 3
+                    && digits[..3].iter().all(char::is_ascii_digit)
+                    && !digits.get(3).is_some_and(char::is_ascii_digit)
+            };
+            let separates_thousands = THOUSANDS_SPACES.contains(&c)
+                && i > 0
+                && chars[i - 1].is_ascii_digit()
+                && is_group(&chars[i + 1..]);
+            if !separates_thousands {
+                folded.push(c);
+            }
+        }
+        Some(normalize_whitespace(&folded))
+    }
+
+    fn normalize(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut normalized = String::with_capacity(text.len());
+        let mut i = 0;
+        while i < chars.len() {
+            let starts_number = chars[i].is_ascii_digit()
+                && (i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '.'));
+            if !starts_number {
+                normalized.push(chars[i]);
+                i += 1;
+                continue;
+            }
+            let (number, end) = read_number(&chars, i, self.decimal_comma);
+            normalized.push_str(&number);
+            i = end;
+            let unit_start = chars[i..]
+                .iter()
+                .position(|&c| c != ' ' && !THOUSANDS_SPACES.contains(&c))
+                .map_or(chars.len(), |n| i + n);
+            // Units are letters, or a symbol such as "%" or "°" and the letters
+            // after it.
+            let letters_from = |start: usize| {
+                chars[start.min(chars.len())..]
+                    .iter()
+                    .position(|c| !c.is_alphabetic())
+                    .map_or(chars.len(), |n| start + n)
+            };
+            let unit_end = match chars.get(unit_start) {
+                Some(&c) if is_unit_symbol(c) => letters_from(unit_start + 1),
+                _ => letters_from(unit_start),
+            };
+            let unit: String = chars[unit_start..unit_end].iter().collect();
+            // "in" is a unit only where no word follows, unlike in "3 in the
+            // box".
+            let is_word = unit.eq_ignore_ascii_case("in")
+                && chars[unit_end..]
+                    .iter()
+                    .find(|c| !c.is_whitespace())
+                    .is_some_and(|c| c.is_alphanumeric());
+            if let Some(symbol) = unit_symbol(&unit.to_lowercase()).filter(|_| !is_word) {
+                normalized.push_str(symbol);
+                i = unit_end;
+            }
+        }
+        normalized
+    }
+}
+
+fn is_unit_symbol(c: char) -> bool {
+    !c.is_alphanumeric() && !c.is_whitespace() && !matches!(c, '.' | ',' | ';' | ':' | '/' | '-')
+}
+
+// Returns the number starting at `start` and where it ends.
+fn read_number(chars: &[char], start: usize, decimal_comma: bool) -> (String, usize) {
+    let digits_from = |i: usize| {
+        chars[i.min(chars.len())..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count()
+    };
+    let mut integer: String = chars[start..start + digits_from(start)].iter().collect();
+    let mut i = start + integer.len();
+    let mut fraction = String::new();
+    while i < chars.len() {
+        let separator = chars[i];
+        let digits = digits_from(i + 1);
+        let group: String = chars[i + 1..i + 1 + digits].iter().collect();
+        let is_thousands = digits == 3
+            && match separator {
+                '\'' | '\u{2019}' | '\u{a0}' | '\u{202f}' | '\u{2009}' => true,
+                ',' => !decimal_comma,
+                '.' if decimal_comma => true,
+                // A point only when the groups go on, as in "1.000.000".
+                '.' => chars.get(i + 4) == Some(&'.') || chars.get(i + 4) == Some(&','),
+                _ => false,
+            };
+        if is_thousands {
+            integer.push_str(&group);
+            i += 1 + digits;
+        } else if matches!(separator, '.' | ',') && digits > 0 {
+            fraction = group;
+            i += 1 + digits;
+            break;
+        } else {
+            break;
+        }
+    }
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        (integer, i)
+    } else {
+        (format!("{}.{}", integer, fraction), i)
+    }
+}
+
+fn unit_symbol(unit: &str) -> Option<&'static str> {
+    let symbol = match unit {
+        "km" | "kilometer" | "kilometers" | "kilometre" | "kilometres" => "km",
+        "m" | "meter" | "meters" | "metre" | "metres" => "m",
+        "cm" | "centimeter" | "centimeters" | "centimetre" | "centimetres" => "cm",
+        "mm" | "millimeter" | "millimeters" | "millimetre" | "millimetres" => "mm",
+        "mi" | "mile" | "miles" => "mi",
+        "ft" | "foot" | "feet" => "ft",
+        "in" | "inch" | "inches" => "in",
+        "kg" | "kilogram" | "kilograms" | "kilogramme" | "kilogrammes" => "kg",
+        "g" | "gram" | "grams" | "gramme" | "grammes" => "g",
+        "lb" | "lbs" | "pound" | "pounds" => "lb",
+        "l" | "liter" | "liters" | "litre" | "litres" => "l",
+        "ml" | "milliliter" | "milliliters" | "millilitre" | "millilitres" => "ml",
+        "s" | "sec" | "secs" | "second" | "seconds" => "s",
+        "min" | "mins" | "minute" | "minutes" => "min",
+        "h" | "hr" | "hrs" | "hour" | "hours" => "h",
+        "°c" | "celsius" => "°C",
+        "°f" | "fahrenheit" => "°F",
+        "%" | "percent" => "%",
+        _ => return None,
+    };
+    Some(symbol)
+}
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+// Dates with the name of the month in English, with or without the year, are
+// written as in ISO 8601: "2021-03-03", or "--03-03" without the year.
+struct Dates;
+
+impl Normalizer for Dates {
+    fn normalize(&self, text: &str) -> String {
+        let mut normalized = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(c) = rest.chars().next() {
+            if !normalized.ends_with(char::is_alphanumeric) {
+                if let Some((date, len)) = read_date(rest) {
+                    normalized.push_str(&date);
+                    rest = &rest[len..];
+                    continue;
+                }
+            }
+            normalized.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        normalized
+    }
+}
+
+struct Cursor<'t> {
+    text: &'t str,
+    pos: usize,
+}
+
+impl<'t> Cursor<'t> {
+    fn rest(&self) -> &'t str {
+        &self.text[self.pos..]
+    }
+
+    fn word(&mut self) -> &'t str {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !c.is_alphanumeric())
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn skip(&mut self, c: char) -> bool {
+        let skipped = self.rest().starts_with(c);
+        if skipped {
+            self.pos += c.len_utf8();
+        }
+        skipped
+    }
+
+    fn spaces(&mut self) -> bool {
+        let rest = self.rest();
+        let len = rest.len() - rest.trim_start().len();
+        self.pos += len;
+        len > 0
+    }
+
+    // ", 2021" or " 2021", which is left alone unless it is a year.
+    fn year(&mut self) -> Option<&'t str> {
+        let start = self.pos;
+        self.skip(',');
+        if self.spaces() {
+            let word = self.word();
+            if word.len() == 4 && word.bytes().all(|b| b.is_ascii_digit()) {
+                return Some(word);
+            }
+        }
+        self.pos = start;
+        None
+    }
+}
+
+// The date at the start of the text, in one of "3rd March 2021", "3 of March",
+// "March 3rd, 2021" and "2021-03-03", and its length.
+fn read_date(text: &str) -> Option<(String, usize)> {
+    let mut cursor = Cursor { text, pos: 0 };
+    let first = cursor.word();
+    if first.len() == 4 && first.bytes().all(|b| b.is_ascii_digit()) && cursor.skip('-') {
+        let month = cursor.word();
+        if cursor.skip('-') {
+            let day = cursor.word();
+            let date = format_date(Some(first), month.parse().ok(), day_number(day)?)?;
+            return Some((date, cursor.pos));
+        }
+        return None;
+    }
+    if let Some(day) = day_number(first) {
+        cursor.skip('.');
+        if !cursor.spaces() {
+            return None;
+        }
+        let start = cursor.pos;
+        if !(cursor.word().eq_ignore_ascii_case("of") && cursor.spaces()) {
+            cursor.pos = start;
+        }
+        let month = month_number(cursor.word());
+        cursor.skip('.');
+        let year = cursor.year();
+        return Some((format_date(year, month, day)?, cursor.pos));
+    }
+    let month = month_number(first)?;
+    cursor.skip('.');
+    if !cursor.spaces() {
+        return None;
+    }
+    let day = day_number(cursor.word())?;
+    let year = cursor.year();
+    Some((format_date(year, Some(month), day)?, cursor.pos))
+}
+
+// "3", "03" or "3rd".
+fn day_number(word: &str) -> Option<u32> {
+    let digits = ["st", "nd", "rd", "th"]
+        .iter()
+        .find_map(|suffix| word.to_lowercase().strip_suffix(suffix).map(str::to_owned))
+        .unwrap_or_else(|| word.to_owned());
+    if digits.is_empty() || digits.len() > 2 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+// The full name or an abbreviation of three letters or more.
+fn month_number(name: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    if name.chars().count() < 3 {
+        return None;
+    }
+    let position = MONTHS.iter().position(|month| month.starts_with(&name))?;
+    Some(position as u32 + 1)
+}
+
+fn format_date(year: Option<&str>, month: Option<u32>, day: u32) -> Option<String> {
+    let month = month.filter(|month| (1..=12).contains(month))?;
+    if !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(match year {
+        Some(year) => format!("{}-{:02}-{:02}", year, month, day),
+        None => format!("--{:02}-{:02}", month, day),
+    })
+}
+
+fn by_name(name: &str, language: &str) -> Option<Box<dyn Normalizer>> {
+    let normalizer: Box<dyn Normalizer> = match name {
+        "ascii_fold" => Box::new(AsciiFold),
+        "sharp_s" => Box::new(GermanSharpS),
+        "dotted_i" => Box::new(TurkishDottedI),
+        "final_sigma" => Box::new(GreekFinalSigma),
+        "apostrophes" => Box::new(Apostrophes),
+        "numbers" => Box::new(Numbers {
+            decimal_comma: DECIMAL_COMMA_LANGUAGES.contains(&language),
+        }),
+        "dates" => Box::new(Dates),
+        _ => return None,
+    };
+    Some(normalizer)
+}
+
+fn language_defaults(language: &str) -> &'static [&'static str] {
+    match language {
+        "de" => &["apostrophes", "sharp_s"],
+        "tr" | "az" => &["apostrophes", "dotted_i"],
+        "el" => &["apostrophes", "final_sigma"],
+        // Loanwords keep their accents in English only some of the time.
+        "en" => &["apostrophes", "ascii_fold"],
+        _ => &["apostrophes"],
+    }
+}
+
+pub struct Normalizers {
+    normalizers: Vec<Box<dyn Normalizer>>,
+}
+
+impl Default for Normalizers {
+    fn default() -> Self {
+        Self::for_language("")
+    }
+}
+
+impl Normalizers {
+    pub fn for_language(language: &str) -> Self {
+        Self {
+            normalizers: language_defaults(language)
+                .iter()
+                .filter_map(|name| by_name(name, language))
+                .collect(),
+        }
+    }
+
+    pub fn from_settings(settings: &Settings, language: Option<&str>) -> Result<Self, String> {
+        match settings.get_str_array("matching", "normalizers") {
+            Some(names) => {
+                let normalizers = names
+                    .iter()
+                    .map(|name| {
+                        by_name(name, language.unwrap_or_default())
+                            .ok_or_else(|| format!("unknown normalizer: {}", name))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(Self { normalizers })
+            }
+            None => Ok(Self::for_language(language.unwrap_or_default())),
+        }
+    }
+
+    pub fn apply(&self, text: &str, ignore_case: bool) -> String {
+        let mut text = self
+            .normalizers
+            .iter()
+            .find_map(|normalizer| normalizer.fold_whitespace(text))
+            .unwrap_or_else(|| normalize_whitespace(text));
+        if ignore_case {
+            text = self
+                .normalizers
+                .iter()
+                .find_map(|normalizer| normalizer.to_lowercase(&text))
+                .unwrap_or_else(|| text.to_lowercase());
+        }
+        for normalizer in &self.normalizers {
+            text = normalizer.normalize(&text);
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalizers(names: &[&str], language: &str) -> Normalizers {
+        Normalizers {
+            normalizers: names
+                .iter()
+                .filter_map(|name| by_name(name, language))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn numbers_drop_thousands_separators() {
+        let numbers = normalizers(&["numbers"], "en");
+        assert_eq!(numbers.apply("1,000", false), "1000");
+        assert_eq!(numbers.apply("1000", false), "1000");
+        assert_eq!(
+            numbers.apply("1,000 m", false),
+            numbers.apply("1000m", false)
+        );
+        assert_eq!(numbers.apply("1,5", false), "1.5");
+    }
+
+    #[test]
+    fn numbers_read_decimal_commas_by_language() {
+        let numbers = normalizers(&["numbers"], "de");
+        assert_eq!(numbers.apply("1.000,5", false), "1000.5");
+        assert_eq!(numbers.apply("1000,50", false), "1000.5");
+        assert_eq!(numbers.apply("1,000", false), "1");
+    }
+
+    #[test]
+    fn dates_match_in_either_order() {
+        let dates = normalizers(&["dates"], "en");
+        assert_eq!(dates.apply("3rd March", false), "--03-03");
+        assert_eq!(dates.apply("March 3", false), "--03-03");
+        assert_eq!(dates.apply("the 3 of March, 2021", false), "the 2021-03-03");
+        assert_eq!(dates.apply("March 3rd, 2021", false), "2021-03-03");
+    }
+
+    #[test]
+    fn words_after_numbers_are_not_taken_for_units() {
+        let both = normalizers(&["numbers", "dates"], "en");
+        assert_eq!(both.apply("3 in the box", false), "3 in the box");
+        assert_eq!(both.apply("3 in", false), "3in");
+    }
+}
@@ -0,0 +1,52 @@
+// Personal notes on cards, taken with `:note TEXT` during a question and kept
+// in notes.txt as one "CARD_ID\tNOTE" line per card, apart from the deck.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug, Default)]
+pub struct Notes {
+    path: PathBuf,
+    notes: HashMap<String, String>,
+}
+
+impl Notes {
+    pub fn load(path: PathBuf) -> io::Result<Self> {
+        let mut notes = HashMap::new();
+        if path.exists() {
+            for line in fs::read_to_string(&path)?.lines() {
+                if let Some((card_id, note)) = line.split_once('\t') {
+                    notes.insert(card_id.to_owned(), note.to_owned());
+                }
+            }
+        }
+        Ok(Self { path, notes })
+    }
+
+    pub fn get(&self, card_id: &str) -> Option<&str> {
+        self.notes.get(card_id).map(String::as_str)
+    }
+
+    // Replaces the note on the card, or removes it if the text is empty. The
+    // file is written right away since notes are taken rarely.
+    pub fn set(&mut self, card_id: &str, text: &str) -> io::Result<()> {
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() {
+            self.notes.remove(card_id);
+        } else {
+            self.notes.insert(card_id.to_owned(), text);
+        }
+        let mut card_ids: Vec<&String> = self.notes.keys().collect();
+        card_ids.sort();
+        let mut contents = String::new();
+        for card_id in card_ids {
+            contents.push_str(&format!("{}\t{}\n", card_id, self.notes[card_id]));
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, contents)
+    }
+}
@@ -0,0 +1,188 @@
+// A warm-up round of matching pairs before the typed questions:
+//
+//   vocab-trainer DECK --warm-up pairs
+//
+// The terms of the first cards of the session are listed with their meanings
+// shuffled beside them, five at a time, and paired up by number and letter,
+// as in "1c 2a 3e 4b 5d", or as the letters alone in the order of the terms,
+// as in "caebd". Each pair counts on its own. The round isn't recorded in the
+// scores, as telling a meaning among five is much easier than recalling the
+// term. How many cards it takes is set with:
+//
+//   [warm_up]
+//   cards = 10
+
+use std::fmt;
+use std::io::{self, Write as _};
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::settings::Settings;
+use crate::{shuffle, AnswerInput, GameState};
+
+const PAIRS_PER_ROUND: usize = 5;
+
+const DEFAULT_CARDS: usize = 10;
+
+const LETTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+
+// Returns whether the warm-up was quit, which ends the session.
+pub fn run(
+    state: &GameState,
+    settings: &Settings,
+    plain: bool,
+    input: &mut AnswerInput,
+) -> io::Result<bool> {
+    let pairs: Vec<(&str, String)> = pick_cards(state, settings)
+        .into_iter()
+        .map(|(i, meaning)| (&*state.deck[i].term, meaning))
+        .collect();
+    // Rounds as even as can be, as a last round of one pair would be a given.
+    let rounds = pairs.len().div_ceil(PAIRS_PER_ROUND);
+    let mut matched = 0;
+    for round in pairs.chunks(pairs.len().div_ceil(rounds.max(1)).max(1)) {
+        match ask_round(round, plain, input)? {
+            Some(count) => matched += count,
+            None => return Ok(true),
+        }
+    }
+    if !pairs.is_empty() {
+        println!("Warm-up: {} of {} pairs", matched, pairs.len());
+        println!();
+    }
+    Ok(false)
+}
+
+// The first cards of the session with their meanings, for warm-ups. Two cards
+// alike on either side couldn't be told apart, so only the first is taken.
+pub fn pick_cards(state: &GameState, settings: &Settings) -> Vec<(usize, String)> {
+    let count = settings
+        .get_integer("warm_up", "cards")
+        .map_or(DEFAULT_CARDS, |cards| cards.max(0) as usize);
+    let mut cards: Vec<(usize, String)> = Vec::new();
+    for &i in state.pending.iter().take(count) {
+        let entry = &state.deck[i];
+        let meaning = entry
+            .phrases
+            .iter()
+            .map(|phrase| phrase.body.as_str())
+            .collect::<Vec<_>>()
+            .join("; ");
+        if !cards
+            .iter()
+            .any(|(j, other)| state.deck[*j].term == entry.term || *other == meaning)
+        {
+            cards.push((i, meaning));
+        }
+    }
+    cards
+}
+
+// Returns how many pairs were right, or `None` to quit.
+fn ask_round(
+    round: &[(&str, String)],
+    plain: bool,
+    input: &mut AnswerInput,
+) -> io::Result<Option<usize>> {
+    let mut order: Vec<usize> = (0..round.len()).collect();
+    if round.len() > 1 {
+        while order.iter().enumerate().all(|(i, &j)| i == j) {
+            shuffle(&mut order);
+        }
+    }
+    let width = round
+        .iter()
+        .map(|(term, _)| term.width())
+        .max()
+        .unwrap_or(0);
+    println!("Match the pairs:");
+    for (i, (term, _)) in round.iter().enumerate() {
+        println!(
+            "  {} {}{}  {} /{}/",
+            i + 1,
+            term,
+            " ".repeat(width - term.width()),
+            LETTERS[i] as char,
+            round[order[i]].1,
+        );
+    }
+    let guesses = loop {
+        print!("> ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim();
+        if matches!(line, ":q" | ":quit") {
+            return Ok(None);
+        }
+        match parse_pairs(line, round.len()) {
+            Some(guesses) => break guesses,
+            None => println!(
+                "Pair each number with a letter, as in \"1{} 2{}\"",
+                LETTERS[0] as char, LETTERS[1] as char,
+            ),
+        }
+    };
+    let ansi = |sequence: &dyn fmt::Display| {
+        if plain {
+            String::new()
+        } else {
+            sequence.to_string()
+        }
+    };
+    let mut matched = 0;
+    for (i, (term, meaning)) in round.iter().enumerate() {
+        let right = guesses[i].is_some_and(|letter| order[letter] == i);
+        if right {
+            matched += 1;
+            continue;
+        }
+        let letter = order.iter().position(|&j| j == i).unwrap_or(i);
+        println!(
+            "  {}{} {} is {} /{}/{}",
+            ansi(&termion::color::Fg(termion::color::LightRed)),
+            i + 1,
+            term,
+            LETTERS[letter] as char,
+            meaning,
+            ansi(&termion::style::Reset),
+        );
+    }
+    println!("{} of {} pairs", matched, round.len());
+    println!();
+    Ok(Some(matched))
+}
+
+// The letter paired with each number, as indices. Numbers left out stay
+// unpaired, which counts as wrong.
+fn parse_pairs(line: &str, count: usize) -> Option<Vec<Option<usize>>> {
+    let letter_index = |c: char| {
+        LETTERS[..count]
+            .iter()
+            .position(|&letter| letter as char == c.to_ascii_lowercase())
+    };
+    let mut guesses = vec![None; count];
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    if compact.chars().count() == count && compact.chars().all(|c| c.is_alphabetic()) {
+        for (i, c) in compact.chars().enumerate() {
+            guesses[i] = Some(letter_index(c)?);
+        }
+        return Some(guesses);
+    }
+    for pair in line.split(|c: char| c.is_whitespace() || c == ',') {
+        if pair.is_empty() {
+            continue;
+        }
+        let digits = pair.find(|c: char| !c.is_ascii_digit())?;
+        let number: usize = pair[..digits].parse().ok()?;
+        let mut letters = pair[digits..].chars();
+        let letter = letter_index(letters.next()?)?;
+        if letters.next().is_some() || number == 0 || number > count {
+            return None;
+        }
+        guesses[number - 1] = Some(letter);
+    }
+    Some(guesses)
+}
@@ -0,0 +1,137 @@
+// Plays sound files and reads sentences out on a thread of its own, one after
+// another, so that a slow TTS command never holds up the input. Whatever comes
+// in while the queue is full is dropped rather than waited for, and `stop`
+// cancels what is playing along with everything queued.
+
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const QUEUE_CAPACITY: usize = 8;
+
+// How often the thread looks whether the command finished, which it can't wait
+// for while `stop` may need to kill it.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+enum Job {
+    Play(String, PathBuf),
+    Speak(String, String),
+}
+
+#[derive(Default)]
+struct Shared {
+    playing: Mutex<Option<Child>>,
+    // Bumped by `stop`, which cancels the jobs queued before.
+    generation: AtomicU64,
+}
+
+pub struct Player {
+    sender: Option<SyncSender<(u64, Job)>>,
+    shared: Arc<Shared>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Player {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<(u64, Job)>(QUEUE_CAPACITY);
+        let shared = Arc::new(Shared::default());
+        let handle = {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                for (generation, job) in receiver {
+                    {
+                        let mut playing = shared.playing.lock().unwrap();
+                        if generation != shared.generation.load(Ordering::SeqCst) {
+                            continue;
+                        }
+                        *playing = job.start();
+                    }
+                    loop {
+                        thread::sleep(POLL_INTERVAL);
+                        let mut playing = shared.playing.lock().unwrap();
+                        match playing.as_mut().map(Child::try_wait) {
+                            Some(Ok(None)) => {}
+                            _ => {
+                                *playing = None;
+                                break;
+                            }
+                        }
+                    }
+                }
+            })
+        };
+        Self {
+            sender: Some(sender),
+            shared,
+            handle: Some(handle),
+        }
+    }
+
+    // The command gets the path of the sound file as its last argument.
+    pub fn play(&self, command: &str, path: &Path) {
+        self.send(Job::Play(command.to_owned(), path.to_path_buf()));
+    }
+
+    // The command gets the text on its standard input.
+    pub fn speak(&self, command: &str, text: &str) {
+        self.send(Job::Speak(command.to_owned(), text.to_owned()));
+    }
+
+    pub fn stop(&self) {
+        let mut playing = self.shared.playing.lock().unwrap();
+        self.shared.generation.fetch_add(1, Ordering::SeqCst);
+        if let Some(mut child) = playing.take() {
+            child.kill().ok();
+            child.wait().ok();
+        }
+    }
+
+    fn send(&self, job: Job) {
+        let generation = self.shared.generation.load(Ordering::SeqCst);
+        if let Some(sender) = &self.sender {
+            sender.try_send((generation, job)).ok();
+        }
+    }
+}
+
+// Lets whatever is queued finish, so that the last sentences of a session are
+// still read out.
+impl Drop for Player {
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+    }
+}
+
+impl Job {
+    fn start(self) -> Option<Child> {
+        let (command, input, path) = match self {
+            Job::Play(command, path) => (command, None, Some(path)),
+            Job::Speak(command, text) => (command, Some(text), None),
+        };
+        let mut words = command.split_whitespace();
+        let mut child = Command::new(words.next()?)
+            .args(words)
+            .args(path)
+            .stdin(if input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+            stdin.write_all(input.as_bytes()).ok();
+        }
+        Some(child)
+    }
+}
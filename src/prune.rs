@@ -0,0 +1,295 @@
+// Keeps the scores and the history from growing without end:
+//
+//   vocab-trainer prune [--unused] [--older-than AGE] [--deck DECK]... [--dry-run]
+//
+// With --unused, the scores and the history of terms that are in none of the
+// decks given with --deck are removed, along with those the decks kept apart
+// when interleaved. Every deck the terms were practiced from has to be given.
+// With --older-than, attempts older than AGE, as in "90d", "8w", "6m" or "1y",
+// are taken out of the history and added up by month in history-monthly.txt,
+// one "YYYY-MM\tTERM\tATTEMPTS\tPERFECT\tMISTAKES" line per term and month,
+// and by day in history-daily.txt, one "YYYY-MM-DD\tREVIEWS\tPERFECT" line per
+// day, which the stats, the streaks and the achievements go on counting. The
+// scores keep the schedule of every card, and the history the latest attempts
+// of each term whatever their age, which the correct rate weighing recent
+// answers goes by, so nothing is forgotten that the questions depend on.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::days;
+use crate::stats::{civil_from_days, format_date, parse_date, DayStats};
+use crate::{
+    detect_config_directory, interleaved_key, load_deck, load_scores, save_scores, unix_time,
+};
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+const KEPT_ATTEMPTS: usize = 10;
+
+const USAGE: &str = "usage: prune [--unused] [--older-than AGE] [--deck DECK]... [--dry-run]";
+
+// Attempts, perfect ones and mistakes of a term in a month.
+type Totals = BTreeMap<(String, String), (u64, u64, u64)>;
+
+// Reviews and perfect ones by day.
+type DailyTotals = BTreeMap<u64, (u64, u64)>;
+
+pub fn run(args: &[String]) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let mut unused = false;
+    let mut older_than = None;
+    let mut deck_paths = Vec::new();
+    let mut dry_run = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--unused" => unused = true,
+            "--older-than" => {
+                let age = args.next().map(String::as_str).unwrap_or_default();
+                older_than =
+                    Some(parse_age(age).ok_or_else(|| invalid(format!("invalid age: {}", age)))?);
+            }
+            "--deck" => deck_paths.extend(args.next().map(PathBuf::from)),
+            "--dry-run" => dry_run = true,
+            _ => return Err(invalid(format!("unknown option: {}", arg))),
+        }
+    }
+    if !unused && older_than.is_none() {
+        return Err(invalid(USAGE.to_owned()));
+    }
+    let config_dir = detect_config_directory();
+    let score_path = config_dir.join("scores.txt");
+    let history_path = config_dir.join("history.txt");
+    let totals_path = config_dir.join("history-monthly.txt");
+    let daily_path = config_dir.join("history-daily.txt");
+    let mut scores = load_scores(&score_path)?;
+    let history = if history_path.exists() {
+        fs::read_to_string(&history_path)?
+    } else {
+        String::new()
+    };
+    let mut totals = load_totals(&totals_path)?;
+    let mut daily_totals = load_daily_totals(&daily_path)?;
+
+    let used = if unused {
+        // The decks practiced lately are not all the decks ever practiced, so
+        // they can't be told for the player.
+        if deck_paths.is_empty() {
+            return Err(invalid(
+                "--unused needs the decks to tell the unused terms by, give them with --deck"
+                    .to_owned(),
+            ));
+        }
+        let mut keys = HashSet::new();
+        for path in &deck_paths {
+            let entries = load_deck(path).map_err(|error| {
+                io::Error::new(error.kind(), format!("{}: {}", path.display(), error))
+            })?;
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            for entry in &entries {
+                keys.insert(entry.key().to_owned());
+                keys.insert(interleaved_key(path, entry.key()));
+                keys.insert(format!("{}:{}", stem, entry.key()));
+            }
+        }
+        Some(keys)
+    } else {
+        None
+    };
+    let is_unused = |term: &str| used.as_ref().is_some_and(|used| !used.contains(term));
+
+    let mut unused_terms: HashSet<String> = scores
+        .keys()
+        .filter(|term| is_unused(term))
+        .cloned()
+        .collect();
+    for line in history.lines() {
+        if let Some(term) = line.split('\t').nth(1).filter(|term| is_unused(term)) {
+            if !unused_terms.contains(term) {
+                unused_terms.insert(term.to_owned());
+            }
+        }
+    }
+    let mut sorted_terms: Vec<&String> = unused_terms.iter().collect();
+    sorted_terms.sort();
+    for term in sorted_terms {
+        println!("unused: {}", term);
+    }
+
+    // The history is in the order the attempts were made, so the latest of
+    // each term are its last lines.
+    let lines: Vec<&str> = history.lines().collect();
+    let mut is_latest = vec![false; lines.len()];
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (i, line) in lines.iter().enumerate().rev() {
+        let count = counts
+            .entry(line.split('\t').nth(1).unwrap_or_default())
+            .or_default();
+        if *count < KEPT_ATTEMPTS {
+            is_latest[i] = true;
+            *count += 1;
+        }
+    }
+
+    let cutoff = older_than.map(|age| unix_time().saturating_sub(age));
+    let mut kept = String::with_capacity(history.len());
+    let mut removed = 0;
+    let mut compacted = 0;
+    for (line, is_latest) in lines.into_iter().zip(is_latest) {
+        let mut parts = line.split('\t');
+        let (timestamp, term, mistakes) = (parts.next(), parts.next(), parts.next());
+        let term = term.unwrap_or_default();
+        if is_unused(term) {
+            removed += 1;
+            continue;
+        }
+        let timestamp: Option<u64> = timestamp.and_then(|part| part.parse().ok());
+        let mistakes: Option<u64> = mistakes.and_then(|part| part.parse().ok());
+        match (timestamp, mistakes, cutoff) {
+            (Some(timestamp), Some(mistakes), Some(cutoff)) if timestamp < cutoff && !is_latest => {
+                let day = days::day(timestamp);
+                let daily_total = daily_totals.entry(day).or_default();
+                daily_total.0 += 1;
+                daily_total.1 += (mistakes == 0) as u64;
+                let (year, month, _) = civil_from_days(day);
+                let month = format!("{:04}-{:02}", year, month);
+                let total = totals.entry((month, term.to_owned())).or_default();
+                total.0 += 1;
+                total.1 += (mistakes == 0) as u64;
+                total.2 += mistakes;
+                compacted += 1;
+            }
+            _ => {
+                kept.push_str(line);
+                kept.push('\n');
+            }
+        }
+    }
+    totals.retain(|(_, term), _| !is_unused(term));
+
+    let verb = |done: &'static str, would: &'static str| if dry_run { would } else { done };
+    if unused {
+        println!(
+            "{} {} unused terms and {} of their attempts",
+            verb("Removed", "Would remove"),
+            unused_terms.len(),
+            removed
+        );
+    }
+    if cutoff.is_some() {
+        println!(
+            "{} {} attempts into monthly totals",
+            verb("Compacted", "Would compact"),
+            compacted
+        );
+    }
+    if dry_run || (unused_terms.is_empty() && compacted == 0) {
+        return Ok(());
+    }
+    scores.retain(|term, _| !is_unused(term));
+    // Every file is written aside, and renamed over the old one only once all
+    // of them are written, so that an error leaves them as they were rather
+    // than with attempts both compacted and kept, or lost from both.
+    let temp_path = |path: &Path| path.with_extension("txt.tmp");
+    save_scores(temp_path(&score_path), scores)?;
+    save_totals(&temp_path(&totals_path), &totals)?;
+    save_daily_totals(&temp_path(&daily_path), &daily_totals)?;
+    fs::write(temp_path(&history_path), kept)?;
+    for path in [&score_path, &totals_path, &daily_path, &history_path] {
+        fs::rename(temp_path(path), path)?;
+    }
+    Ok(())
+}
+
+// Seconds in an age such as "90d", "8w", "6m" or "1y".
+fn parse_age(age: &str) -> Option<u64> {
+    let unit = age.chars().last()?;
+    let count: u64 = age[..age.len() - unit.len_utf8()].parse().ok()?;
+    let days = match unit {
+        'd' => 1,
+        'w' => 7,
+        'm' => 30,
+        'y' => 365,
+        _ => return None,
+    };
+    count.checked_mul(days)?.checked_mul(SECS_PER_DAY)
+}
+
+fn load_totals(path: &Path) -> io::Result<Totals> {
+    let mut totals = Totals::new();
+    if !path.exists() {
+        return Ok(totals);
+    }
+    for line in fs::read_to_string(path)?.lines() {
+        let parts: Vec<&str> = line.split('\t').collect();
+        if let [month, term, attempts, perfect, mistakes] = parts[..] {
+            if let (Ok(attempts), Ok(perfect), Ok(mistakes)) =
+                (attempts.parse(), perfect.parse(), mistakes.parse())
+            {
+                totals.insert(
+                    (month.to_owned(), term.to_owned()),
+                    (attempts, perfect, mistakes),
+                );
+            }
+        }
+    }
+    Ok(totals)
+}
+
+fn save_totals(path: &Path, totals: &Totals) -> io::Result<()> {
+    let mut contents = String::new();
+    for ((month, term), (attempts, perfect, mistakes)) in totals {
+        contents.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\n",
+            month, term, attempts, perfect, mistakes
+        ));
+    }
+    fs::write(path, contents)
+}
+
+// The reviews of each day compacted out of the history, for the stats.
+pub fn load_compacted_days(config_dir: &Path) -> io::Result<Vec<DayStats>> {
+    let days = load_daily_totals(&config_dir.join("history-daily.txt"))?
+        .into_iter()
+        .map(|(day, (reviews, perfect))| DayStats {
+            day,
+            reviews: reviews as usize,
+            perfect: perfect as usize,
+        })
+        .collect();
+    Ok(days)
+}
+
+fn load_daily_totals(path: &Path) -> io::Result<DailyTotals> {
+    let mut totals = DailyTotals::new();
+    if !path.exists() {
+        return Ok(totals);
+    }
+    for line in fs::read_to_string(path)?.lines() {
+        let parts: Vec<&str> = line.split('\t').collect();
+        if let [date, reviews, perfect] = parts[..] {
+            if let (Some(day), Ok(reviews), Ok(perfect)) =
+                (parse_date(date), reviews.parse(), perfect.parse())
+            {
+                totals.insert(day, (reviews, perfect));
+            }
+        }
+    }
+    Ok(totals)
+}
+
+fn save_daily_totals(path: &Path, totals: &DailyTotals) -> io::Result<()> {
+    let mut contents = String::new();
+    for (day, (reviews, perfect)) in totals {
+        contents.push_str(&format!(
+            "{}\t{}\t{}\n",
+            format_date(*day),
+            reviews,
+            perfect
+        ));
+    }
+    fs::write(path, contents)
+}
@@ -0,0 +1,227 @@
+// Plays back past sessions from the history:
+//
+//   vocab-trainer replay                     lists the recent sessions
+//   vocab-trainer replay ID|ULID|last [--speed X] [--deck DECK]
+//
+// Sessions are told apart by the ULID recorded with each attempt. Older
+// attempts without one are taken to be one session when less than half an
+// hour apart. Each question is shown with the wrong answers given to it and
+// the time it took, played back at X times the original pace. With a deck,
+// the questions are shown as they were asked rather than by their terms.
+
+use std::collections::HashMap;
+use std::io::{self, Write as _};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use crate::days;
+use crate::stats::{self, format_date};
+use crate::ulid;
+use crate::{
+    detect_config_directory, format_diff, load_deck, load_history, Attempt, Entry, History,
+};
+
+const SESSION_GAP_SECS: u64 = 30 * 60;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+// The longest pause for a single question, however long it took.
+const MAX_PAUSE: Duration = Duration::from_secs(10);
+
+const LISTED_SESSIONS: usize = 20;
+
+type Session<'a> = Vec<(&'a str, &'a Attempt)>;
+
+pub fn run(args: &[String]) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let mut id = None;
+    let mut speed = 1.0;
+    let mut deck_path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--speed" => {
+                let value = args.next().map(String::as_str).unwrap_or_default();
+                speed = value
+                    .parse()
+                    .ok()
+                    .filter(|&speed: &f64| speed > 0.0)
+                    .ok_or_else(|| invalid(format!("invalid speed: {}", value)))?;
+            }
+            "--deck" => deck_path = args.next().map(PathBuf::from),
+            _ if arg.starts_with('-') => return Err(invalid(format!("unknown option: {}", arg))),
+            _ => id = Some(arg.clone()),
+        }
+    }
+
+    let history = load_history(detect_config_directory().join("history.txt"))?;
+    let sessions = sessions(&history);
+    let id = match id {
+        Some(id) => id,
+        None => {
+            print_sessions(&sessions);
+            return Ok(());
+        }
+    };
+    let index = match id.as_str() {
+        "last" => sessions.len().checked_sub(1),
+        id if ulid::is_valid(id) => sessions
+            .iter()
+            .position(|session| session_id(session) == Some(id)),
+        id => id.parse::<usize>().ok().and_then(|id| id.checked_sub(1)),
+    };
+    let session = index
+        .and_then(|index| sessions.get(index))
+        .ok_or_else(|| invalid(format!("no such session: {}", id)))?;
+    let entries = match deck_path {
+        Some(path) => load_deck(&path)?,
+        None => Vec::new(),
+    };
+    let entries: HashMap<&str, &Entry> = entries.iter().map(|entry| (entry.key(), entry)).collect();
+    replay(session, &entries, speed);
+    Ok(())
+}
+
+fn sessions(history: &History) -> Vec<Session<'_>> {
+    let mut attempts: Session = history
+        .iter()
+        .flat_map(|(term, attempts)| attempts.iter().map(move |attempt| (term.as_str(), attempt)))
+        .collect();
+    attempts.sort_by_key(|(_, attempt)| attempt.timestamp);
+    let mut sessions: Vec<Session> = Vec::new();
+    // Sessions synced from several machines may overlap, so those with an ID
+    // are looked up by it.
+    let mut by_id: HashMap<&str, usize> = HashMap::new();
+    let mut unnamed = None;
+    let mut last = None;
+    for attempt in attempts {
+        let id = attempt.1.session.as_deref();
+        let index = match id {
+            Some(id) => by_id.get(id).copied(),
+            None => unnamed
+                .filter(|_| last.is_some_and(|last| attempt.1.timestamp < last + SESSION_GAP_SECS)),
+        };
+        match index {
+            Some(index) => sessions[index].push(attempt),
+            None => {
+                match id {
+                    Some(id) => by_id.insert(id, sessions.len()),
+                    None => unnamed.replace(sessions.len()),
+                };
+                sessions.push(vec![attempt]);
+            }
+        }
+        if id.is_none() {
+            last = Some(attempt.1.timestamp);
+        }
+    }
+    sessions
+}
+
+fn session_id<'a>(session: &Session<'a>) -> Option<&'a str> {
+    session[0].1.session.as_deref()
+}
+
+fn format_time(timestamp: u64) -> String {
+    let timestamp = days::local(timestamp);
+    let secs = timestamp % SECS_PER_DAY;
+    format!(
+        "{} {:02}:{:02}",
+        format_date(timestamp / SECS_PER_DAY),
+        secs / 3600,
+        secs % 3600 / 60
+    )
+}
+
+fn print_sessions(sessions: &[Session]) {
+    let skipped = sessions.len().saturating_sub(LISTED_SESSIONS);
+    for (i, session) in sessions.iter().enumerate().skip(skipped) {
+        let perfect = session
+            .iter()
+            .filter(|(_, attempt)| attempt.mistakes == 0)
+            .count();
+        println!(
+            "{:>4}  {}  {} questions, {:.0}% perfect  {}{}{}",
+            i + 1,
+            format_time(session[0].1.timestamp),
+            session.len(),
+            stats::percentage(perfect, session.len()),
+            termion::color::Fg(termion::color::LightBlack),
+            session_id(session).unwrap_or_default(),
+            termion::style::Reset,
+        );
+    }
+}
+
+fn replay(session: &Session, entries: &HashMap<&str, &Entry>, speed: f64) {
+    println!(
+        "{}Session of {}{}",
+        termion::style::Bold,
+        format_time(session[0].1.timestamp),
+        termion::style::Reset
+    );
+    for (i, (term, attempt)) in session.iter().enumerate() {
+        let label = format!("Q{}", i + 1);
+        match entries.get(term) {
+            Some(entry) => {
+                let phrases: Vec<&str> = entry
+                    .phrases
+                    .iter()
+                    .map(|phrase| phrase.body.as_str())
+                    .collect();
+                println!(
+                    "{}{}{} /{}/",
+                    termion::color::Fg(termion::color::LightYellow),
+                    label,
+                    termion::style::Reset,
+                    phrases.join("/")
+                );
+            }
+            None => println!(
+                "{}{}{} {}",
+                termion::color::Fg(termion::color::LightYellow),
+                label,
+                termion::style::Reset,
+                term
+            ),
+        }
+        // The time the question took is spread over the answers given to it.
+        let elapsed = Duration::from_millis(attempt.elapsed_ms.unwrap_or(0)).div_f64(speed);
+        let pause = elapsed.min(MAX_PAUSE) / (attempt.answers.len() as u32 + 1);
+        let answer = entries.get(term).map_or(*term, |entry| &*entry.term);
+        for wrong in &attempt.answers {
+            pause_for(pause);
+            println!("  \u{2717} {}", format_diff(wrong, answer, false));
+        }
+        let unrecorded = attempt.mistakes.saturating_sub(attempt.answers.len());
+        if unrecorded > 0 {
+            println!(
+                "  {}\u{2717} ({} other wrong answers){}",
+                termion::color::Fg(termion::color::LightBlack),
+                unrecorded,
+                termion::style::Reset
+            );
+        }
+        pause_for(pause);
+        println!(
+            "  {}\u{2713}{} {} {}({}, {:.1}s){}",
+            termion::color::Fg(termion::color::LightGreen),
+            termion::style::Reset,
+            answer,
+            termion::color::Fg(termion::color::LightBlack),
+            match attempt.mistakes {
+                0 => "perfect".to_owned(),
+                1 => "1 mistake".to_owned(),
+                mistakes => format!("{} mistakes", mistakes),
+            },
+            attempt.elapsed_ms.unwrap_or(0) as f64 / 1000.0,
+            termion::style::Reset
+        );
+    }
+}
+
+fn pause_for(duration: Duration) {
+    io::stdout().flush().ok();
+    thread::sleep(duration);
+}
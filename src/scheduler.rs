@@ -0,0 +1,212 @@
+// Scheduling state of a card, updated with the SM-2 algorithm.
+
+use crate::days;
+use crate::settings::{Settings, Value};
+
+pub const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+pub const INITIAL_EASE: f64 = 2.5;
+
+pub const MINIMUM_EASE: f64 = 1.3;
+
+// Cards whose interval reached this many days are considered mature.
+pub const MATURE_INTERVAL_DAYS: f64 = 21.0;
+
+// Cards recalled fewer times in a row than this, since they were introduced
+// or last forgotten, are still being learned.
+pub const LEARNING_REPETITIONS: u32 = 2;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schedule {
+    pub repetitions: u32,
+    pub interval: f64,
+    pub ease: f64,
+    pub lapses: u32,
+    pub due: u64,
+    pub buried_until: u64,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            repetitions: 0,
+            interval: 0.0,
+            ease: INITIAL_EASE,
+            lapses: 0,
+            due: 0,
+            buried_until: 0,
+        }
+    }
+}
+
+impl Schedule {
+    pub fn review(&self, mistakes: f64, now: u64, overrides: Overrides) -> Self {
+        let quality = quality(mistakes);
+        let mut schedule = self.clone();
+        if quality >= 3.0 {
+            let ease = overrides.ease.unwrap_or(self.ease);
+            schedule.interval = next_interval(self.repetitions, self.interval, ease);
+            schedule.repetitions += 1;
+        } else {
+            schedule.interval = 1.0;
+            schedule.repetitions = 0;
+            schedule.lapses += 1;
+        }
+        schedule.ease =
+            (self.ease + 0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02)).max(MINIMUM_EASE);
+        if let Some(cap) = overrides.interval_cap {
+            schedule.interval = schedule.interval.min(cap);
+        }
+        schedule.due = now + (schedule.interval * SECS_PER_DAY as f64) as u64;
+        schedule
+    }
+
+    // Buried cards are left out of sessions until the start of the next day.
+    pub fn bury(&mut self, now: u64) {
+        self.buried_until = days::start(days::day(now) + 1);
+    }
+
+    pub fn is_buried(&self, now: u64) -> bool {
+        now < self.buried_until
+    }
+
+    pub fn is_mature(&self) -> bool {
+        self.interval >= MATURE_INTERVAL_DAYS
+    }
+
+    pub fn is_learning(&self) -> bool {
+        self.repetitions < LEARNING_REPETITIONS
+    }
+
+    // Expected number of days until the card becomes mature, given the
+    // probability of answering it without mistakes. Each review either grows
+    // the interval or resets it to a day, so the expected interval is their
+    // weighted mean. Returns `None` if the interval never reaches maturity.
+    pub fn days_until_mature(&self, success_rate: f64, now: u64) -> Option<f64> {
+        if self.is_mature() {
+            return Some(0.0);
+        }
+        let mut days = self.due.saturating_sub(now) as f64 / SECS_PER_DAY as f64;
+        let mut interval = self.interval;
+        for repetitions in self.repetitions..self.repetitions + 100 {
+            let success = next_interval(repetitions, interval, self.ease);
+            let expected = success_rate * success + (1.0 - success_rate);
+            if expected >= MATURE_INTERVAL_DAYS {
+                return Some(days);
+            }
+            if repetitions > 1 && expected <= interval {
+                return None;
+            }
+            days += expected;
+            interval = expected;
+        }
+        None
+    }
+}
+
+// Scheduling set by the deck for cards known to need more or less review,
+// on the cards themselves:
+//
+//   brechen /to break/ #irregular ease=1.8 interval-cap=30d
+//
+// or on every card with a tag:
+//
+//   [schedule.irregular]
+//   ease = 1.8
+//   interval_cap = "30d"
+//
+// The ease grows the intervals in place of the one the answers led to, which
+// is still kept for when the override goes, and the intervals grow no longer
+// than the cap. What a card sets takes precedence over its tags, and earlier
+// tags over later ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Overrides {
+    pub ease: Option<f64>,
+    // In days.
+    pub interval_cap: Option<f64>,
+}
+
+impl Overrides {
+    // Takes the attribute if it is one of the overrides, and returns whether
+    // it was.
+    pub fn parse_attribute(&mut self, token: &str) -> Result<bool, String> {
+        if let Some(ease) = token.strip_prefix("ease=") {
+            self.ease = Some(
+                ease.parse()
+                    .ok()
+                    .and_then(valid_ease)
+                    .ok_or_else(|| format!("invalid ease: {}", ease))?,
+            );
+        } else if let Some(cap) = token.strip_prefix("interval-cap=") {
+            self.interval_cap =
+                Some(parse_days(cap).ok_or_else(|| format!("invalid interval-cap: {}", cap))?);
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    pub fn from_settings(settings: &Settings, tag: &str) -> Result<Self, String> {
+        let section = format!("schedule.{}", tag);
+        let mut overrides = Self::default();
+        if let Some(ease) = settings.get(&section, "ease") {
+            let ease = match ease {
+                Value::Float(ease) => Some(*ease),
+                Value::Integer(ease) => Some(*ease as f64),
+                _ => None,
+            };
+            overrides.ease = Some(
+                ease.and_then(valid_ease)
+                    .ok_or_else(|| format!("invalid ease in [{}]", section))?,
+            );
+        }
+        if let Some(cap) = settings.get_str(&section, "interval_cap") {
+            overrides.interval_cap = Some(
+                parse_days(cap)
+                    .ok_or_else(|| format!("invalid interval_cap in [{}]: {}", section, cap))?,
+            );
+        }
+        Ok(overrides)
+    }
+
+    // These overrides, with what they leave unset taken from `other`.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            ease: self.ease.or(other.ease),
+            interval_cap: self.interval_cap.or(other.interval_cap),
+        }
+    }
+}
+
+// Eases below the minimum would shrink the intervals, so they are raised to
+// it.
+fn valid_ease(ease: f64) -> Option<f64> {
+    (ease.is_finite() && ease > 0.0).then(|| ease.max(MINIMUM_EASE))
+}
+
+// Days in a duration such as "30d", "8w", "6m" or "1y".
+fn parse_days(s: &str) -> Option<f64> {
+    let unit = s.chars().last()?;
+    let count: f64 = s[..s.len() - unit.len_utf8()].parse().ok()?;
+    let days = match unit {
+        'd' => 1.0,
+        'w' => 7.0,
+        'm' => 30.0,
+        'y' => 365.0,
+        _ => return None,
+    };
+    Some(count * days).filter(|days| days.is_finite() && *days >= 1.0)
+}
+
+// Half a mistake, for an answer with one wrong word, is between none and one.
+fn quality(mistakes: f64) -> f64 {
+    (4.0 - mistakes).max(2.0)
+}
+
+fn next_interval(repetitions: u32, interval: f64, ease: f64) -> f64 {
+    match repetitions {
+        0 => 1.0,
+        1 => 6.0,
+        _ => interval * ease,
+    }
+}
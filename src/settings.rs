@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::Chars;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+// A subset of TOML: `[section]` headers and `key = value` pairs, where a value
+// is a string, a number, a boolean or a single-line array of those.
+#[derive(Debug, Default)]
+pub struct Settings {
+    sections: HashMap<String, HashMap<String, Value>>,
+}
+
+impl Settings {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        if path.as_ref().exists() {
+            Self::parse(&fs::read_to_string(path)?)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn parse(source: &str) -> io::Result<Self> {
+        let mut settings = Self::default();
+        let mut section = String::new();
+        for (n, line) in source.lines().enumerate() {
+            let invalid = |message: &str| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("config line {}: {}", n + 1, message),
+                )
+            };
+            let mut input = line.chars().peekable();
+            skip_whitespace(&mut input);
+            match input.peek() {
+                None | Some('#') => {}
+                Some('[') => {
+                    input.next();
+                    let name: String = input.by_ref().take_while(|&c| c != ']').collect();
+                    section = name.trim().to_owned();
+                    settings.sections.entry(section.clone()).or_default();
+                }
+                Some(&quote) => {
+                    // Quoted keys may hold any character but the quote, such
+                    // as the '=' of an alias.
+                    let key: String = if quote == '"' {
+                        input.next();
+                        let key: String = input.by_ref().take_while(|&c| c != '"').collect();
+                        skip_whitespace(&mut input);
+                        if input.next() != Some('=') {
+                            return Err(invalid("expected key = value"));
+                        }
+                        key
+                    } else {
+                        let key: String = input.by_ref().take_while(|&c| c != '=').collect();
+                        key.trim().to_owned()
+                    };
+                    let key = key.as_str();
+                    if key.is_empty() {
+                        return Err(invalid("expected key = value"));
+                    }
+                    let value = parse_value(&mut input).map_err(|message| invalid(&message))?;
+                    skip_whitespace(&mut input);
+                    if !matches!(input.peek(), None | Some('#')) {
+                        return Err(invalid("unexpected trailing characters"));
+                    }
+                    settings
+                        .sections
+                        .entry(section.clone())
+                        .or_default()
+                        .insert(key.to_owned(), value);
+                }
+            }
+        }
+        Ok(settings)
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<&Value> {
+        self.sections.get(section)?.get(key)
+    }
+
+    pub fn get_str(&self, section: &str, key: &str) -> Option<&str> {
+        self.get(section, key)?.as_str()
+    }
+
+    pub fn has_section(&self, section: &str) -> bool {
+        self.sections.contains_key(section)
+    }
+
+    // The keys set in a section, in no particular order.
+    pub fn keys(&self, section: &str) -> impl Iterator<Item = &str> {
+        self.sections
+            .get(section)
+            .into_iter()
+            .flat_map(|values| values.keys().map(String::as_str))
+    }
+
+    pub fn get_integer(&self, section: &str, key: &str) -> Option<i64> {
+        self.get(section, key)?.as_integer()
+    }
+
+    pub fn get_bool(&self, section: &str, key: &str) -> Option<bool> {
+        self.get(section, key)?.as_bool()
+    }
+
+    pub fn get_str_array(&self, section: &str, key: &str) -> Option<Vec<String>> {
+        let values = self.get(section, key)?.as_array()?;
+        values
+            .iter()
+            .map(|value| value.as_str().map(str::to_owned))
+            .collect()
+    }
+
+    // Drops the sections `keep` rejects, and returns their names, sorted.
+    pub fn retain_sections(&mut self, keep: impl Fn(&str) -> bool) -> Vec<String> {
+        let mut dropped: Vec<String> = self
+            .sections
+            .keys()
+            .filter(|name| !keep(name))
+            .cloned()
+            .collect();
+        dropped.sort();
+        for name in &dropped {
+            self.sections.remove(name);
+        }
+        dropped
+    }
+
+    // Values in `other` take precedence over the ones already set.
+    pub fn merge(&mut self, other: Settings) {
+        for (name, values) in other.sections {
+            self.sections.entry(name).or_default().extend(values);
+        }
+    }
+}
+
+fn skip_whitespace(input: &mut Peekable<Chars>) {
+    while input.peek().is_some_and(|c| c.is_whitespace()) {
+        input.next();
+    }
+}
+
+fn parse_value(input: &mut Peekable<Chars>) -> Result<Value, String> {
+    skip_whitespace(input);
+    match input.peek() {
+        Some('"') => {
+            input.next();
+            let mut s = String::new();
+            loop {
+                match input.next() {
+                    Some('"') => break,
+                    Some('\\') => match input.next() {
+                        Some('n') => s.push('\n'),
+                        Some('t') => s.push('\t'),
+                        Some(c) => s.push(c),
+                        None => return Err("unterminated string".to_owned()),
+                    },
+                    Some(c) => s.push(c),
+                    None => return Err("unterminated string".to_owned()),
+                }
+            }
+            Ok(Value::String(s))
+        }
+        Some('[') => {
+            input.next();
+            let mut values = Vec::new();
+            loop {
+                skip_whitespace(input);
+                match input.peek() {
+                    Some(']') => {
+                        input.next();
+                        break;
+                    }
+                    Some(',') => {
+                        input.next();
+                    }
+                    Some(_) => values.push(parse_value(input)?),
+                    None => return Err("unterminated array".to_owned()),
+                }
+            }
+            Ok(Value::Array(values))
+        }
+        Some(_) => {
+            let mut word = String::new();
+            while let Some(&c) = input.peek() {
+                if c == ',' || c == ']' || c == '#' || c.is_whitespace() {
+                    break;
+                }
+                word.push(c);
+                input.next();
+            }
+            match word.as_str() {
+                "true" => Ok(Value::Boolean(true)),
+                "false" => Ok(Value::Boolean(false)),
+                _ => {
+                    let number = word.replace('_', "");
+                    if let Ok(n) = number.parse() {
+                        Ok(Value::Integer(n))
+                    } else if let Ok(n) = number.parse() {
+                        Ok(Value::Float(n))
+                    } else {
+                        Err(format!("invalid value: {}", word))
+                    }
+                }
+            }
+        }
+        None => Err("missing value".to_owned()),
+    }
+}
@@ -0,0 +1,432 @@
+// Reads the rows of tables from SQLite database files, enough to import from
+// other tools that keep their data in them, such as Anki:
+//
+//   https://www.sqlite.org/fileformat2.html
+//
+// The whole file is read into memory, and only tables with rowids, in UTF-8
+// databases, can be read. Changes still in a write-ahead log next to the file
+// aren't seen, so the tool that wrote it should be closed first.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const HEADER: &[u8] = b"SQLite format 3\0";
+
+// Deeper trees than this would hold more pages than a file can have, so the
+// file is corrupt and likely has a loop in it.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl Value {
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(value) => Some(*value),
+            Value::Real(value) => Some(*value as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+pub struct Database {
+    data: Vec<u8>,
+    page_size: usize,
+    usable_size: usize,
+    // The name, root page and SQL of each table, read from the schema once.
+    tables: Vec<(String, i64, String)>,
+}
+
+pub struct Table {
+    columns: Vec<String>,
+    // The column declared INTEGER PRIMARY KEY, whose values are the rowids
+    // and aren't stored in the records.
+    rowid_column: Option<usize>,
+    rows: Vec<(i64, Vec<Value>)>,
+}
+
+pub struct Row<'a> {
+    table: &'a Table,
+    rowid: i64,
+    values: &'a [Value],
+}
+
+fn corrupt() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed database file")
+}
+
+impl Database {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let wal_path = path.with_file_name(format!(
+            "{}-wal",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        if fs::metadata(&wal_path).is_ok_and(|metadata| metadata.len() > 0) {
+            return Err(io::Error::other(format!(
+                "{} has changes not written to it yet, close the program using it first",
+                path.display()
+            )));
+        }
+        let data = fs::read(path)?;
+        if !data.starts_with(HEADER) || data.len() < 100 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not an SQLite database", path.display()),
+            ));
+        }
+        let page_size = match u16::from_be_bytes([data[16], data[17]]) {
+            1 => 65536,
+            size => size as usize,
+        };
+        if page_size < 512 || !page_size.is_power_of_two() {
+            return Err(corrupt());
+        }
+        if u32::from_be_bytes([data[56], data[57], data[58], data[59]]) > 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "only UTF-8 databases can be read",
+            ));
+        }
+        let usable_size = page_size - data[20] as usize;
+        let mut database = Self {
+            data,
+            page_size,
+            usable_size,
+            tables: Vec::new(),
+        };
+        let mut schema = Vec::new();
+        database.read_tree(1, &mut schema, 0)?;
+        for (_, payload) in schema {
+            // The schema has the type, name, table name, root page and SQL of
+            // each table, index, view and trigger.
+            let values = decode_record(&payload).ok_or_else(corrupt)?;
+            let [kind, name, _, root_page, sql] = &values[..] else {
+                continue;
+            };
+            if let (Some("table"), Some(name)) = (kind.as_str(), name.as_str()) {
+                database.tables.push((
+                    name.to_owned(),
+                    root_page.as_i64().ok_or_else(corrupt)?,
+                    sql.as_str().unwrap_or_default().to_owned(),
+                ));
+            }
+        }
+        Ok(database)
+    }
+
+    // The table of the given name, or None if there is no such table.
+    pub fn table(&self, name: &str) -> io::Result<Option<Table>> {
+        let Some((_, root_page, sql)) = self
+            .tables
+            .iter()
+            .find(|(table_name, _, _)| table_name.eq_ignore_ascii_case(name))
+        else {
+            return Ok(None);
+        };
+        if sql.to_ascii_uppercase().contains("WITHOUT ROWID") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("table {} has no rowids and can't be read", name),
+            ));
+        }
+        let (columns, rowid_column) = parse_columns(sql);
+        let mut records = Vec::new();
+        self.read_tree(*root_page as usize, &mut records, 0)?;
+        let rows = records
+            .into_iter()
+            .map(|(rowid, payload)| Some((rowid, decode_record(&payload)?)))
+            .collect::<Option<_>>()
+            .ok_or_else(corrupt)?;
+        Ok(Some(Table {
+            columns,
+            rowid_column,
+            rows,
+        }))
+    }
+
+    fn page(&self, number: usize) -> io::Result<&[u8]> {
+        let start = number.checked_sub(1).ok_or_else(corrupt)? * self.page_size;
+        self.data
+            .get(start..start + self.page_size)
+            .ok_or_else(corrupt)
+    }
+
+    // Collects the rowids and the records of a table's B-tree, in order.
+    fn read_tree(
+        &self,
+        number: usize,
+        rows: &mut Vec<(i64, Vec<u8>)>,
+        depth: usize,
+    ) -> io::Result<()> {
+        if depth > MAX_DEPTH {
+            return Err(corrupt());
+        }
+        let page = self.page(number)?;
+        // The first page starts with the header of the file.
+        let header = if number == 1 { 100 } else { 0 };
+        let kind = page[header];
+        let cell_count = read_u16(page, header + 3)? as usize;
+        let pointers = header + if kind == 0x05 { 12 } else { 8 };
+        for i in 0..cell_count {
+            let cell = read_u16(page, pointers + i * 2)? as usize;
+            match kind {
+                // An interior page: each cell points to the page of the rows
+                // with rowids up to its key.
+                0x05 => {
+                    let child = read_u32(page, cell)? as usize;
+                    self.read_tree(child, rows, depth + 1)?;
+                }
+                0x0d => {
+                    let (size, n) =
+                        varint(page.get(cell..).ok_or_else(corrupt)?).ok_or_else(corrupt)?;
+                    let (rowid, m) =
+                        varint(page.get(cell + n..).ok_or_else(corrupt)?).ok_or_else(corrupt)?;
+                    // No record is larger than the file it is in.
+                    let size = usize::try_from(size)
+                        .ok()
+                        .filter(|&size| size <= self.data.len())
+                        .ok_or_else(corrupt)?;
+                    let payload = self.read_payload(page, cell + n + m, size)?;
+                    rows.push((rowid, payload));
+                }
+                _ => return Err(corrupt()),
+            }
+        }
+        if kind == 0x05 {
+            let right = read_u32(page, header + 8)? as usize;
+            self.read_tree(right, rows, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    // Records too large for their page continue on a chain of overflow pages,
+    // which can't be longer than the file has pages unless it loops.
+    fn read_payload(&self, page: &[u8], start: usize, size: usize) -> io::Result<Vec<u8>> {
+        let usable = self.usable_size;
+        let max_local = usable - 35;
+        let local = if size <= max_local {
+            size
+        } else {
+            let min_local = (usable - 12) * 32 / 255 - 23;
+            let local = min_local + (size - min_local) % (usable - 4);
+            if local <= max_local {
+                local
+            } else {
+                min_local
+            }
+        };
+        let mut payload = page.get(start..start + local).ok_or_else(corrupt)?.to_vec();
+        if local == size {
+            return Ok(payload);
+        }
+        let mut next = read_u32(page, start + local)? as usize;
+        let mut pages = 0;
+        while payload.len() < size {
+            pages += 1;
+            if next == 0 || pages > self.data.len() / self.page_size {
+                return Err(corrupt());
+            }
+            let overflow = self.page(next)?;
+            let length = (size - payload.len()).min(usable - 4);
+            payload.extend_from_slice(overflow.get(4..4 + length).ok_or_else(corrupt)?);
+            next = read_u32(overflow, 0)? as usize;
+        }
+        Ok(payload)
+    }
+}
+
+impl Table {
+    pub fn rows(&self) -> impl Iterator<Item = Row<'_>> {
+        self.rows.iter().map(move |(rowid, values)| Row {
+            table: self,
+            rowid: *rowid,
+            values,
+        })
+    }
+}
+
+impl Row<'_> {
+    // Columns added to the table after a row was written read as NULL in it,
+    // like those it doesn't have at all.
+    pub fn get(&self, column: &str) -> Value {
+        let Some(i) = self.table.columns.iter().position(|name| name == column) else {
+            return Value::Null;
+        };
+        if self.table.rowid_column == Some(i) {
+            return Value::Integer(self.rowid);
+        }
+        self.values.get(i).cloned().unwrap_or(Value::Null)
+    }
+}
+
+// The names of the columns in a CREATE TABLE statement, and which of them is
+// the INTEGER PRIMARY KEY.
+fn parse_columns(sql: &str) -> (Vec<String>, Option<usize>) {
+    let (Some(start), Some(end)) = (sql.find('('), sql.rfind(')')) else {
+        return (Vec::new(), None);
+    };
+    let mut definitions = Vec::new();
+    let mut depth = 0;
+    let mut definition = String::new();
+    for c in sql[start + 1..end].chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                definitions.push(std::mem::take(&mut definition));
+                continue;
+            }
+            _ => {}
+        }
+        definition.push(c);
+    }
+    definitions.push(definition);
+    let mut columns = Vec::new();
+    let mut rowid_column = None;
+    for definition in definitions {
+        let words: Vec<String> = definition
+            .split_whitespace()
+            .map(str::to_ascii_uppercase)
+            .collect();
+        let Some(name) = definition.split_whitespace().next() else {
+            continue;
+        };
+        if ["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"].contains(&words[0].as_str()) {
+            continue;
+        }
+        if words.len() >= 4 && words[1] == "INTEGER" && words[2] == "PRIMARY" && words[3] == "KEY" {
+            rowid_column = Some(columns.len());
+        }
+        columns.push(name.trim_matches(['"', '`', '[', ']']).to_owned());
+    }
+    (columns, rowid_column)
+}
+
+fn decode_record(payload: &[u8]) -> Option<Vec<Value>> {
+    let (header_size, mut offset) = varint(payload)?;
+    let header_size = header_size as usize;
+    let mut body = header_size;
+    let mut values = Vec::new();
+    while offset < header_size {
+        let (serial_type, n) = varint(payload.get(offset..header_size)?)?;
+        offset += n;
+        let size = match serial_type {
+            0 | 8 | 9 => 0,
+            1..=4 => serial_type as usize,
+            5 => 6,
+            6 | 7 => 8,
+            12.. => (serial_type as usize - 12) / 2,
+            _ => return None,
+        };
+        let bytes = payload.get(body..body + size)?;
+        body += size;
+        values.push(match serial_type {
+            0 => Value::Null,
+            1..=6 => {
+                let sign = if bytes[0] & 0x80 != 0 { -1 } else { 0 };
+                Value::Integer(
+                    bytes
+                        .iter()
+                        .fold(sign, |value, &byte| (value << 8) | byte as i64),
+                )
+            }
+            7 => Value::Real(f64::from_be_bytes(bytes.try_into().ok()?)),
+            8 => Value::Integer(0),
+            9 => Value::Integer(1),
+            _ if serial_type % 2 == 0 => Value::Blob(bytes.to_vec()),
+            _ => Value::Text(String::from_utf8_lossy(bytes).into_owned()),
+        });
+    }
+    Some(values)
+}
+
+// Big-endian groups of 7 bits, with the high bit set on all but the last,
+// except that a ninth byte has 8.
+fn varint(bytes: &[u8]) -> Option<(i64, usize)> {
+    let mut value = 0u64;
+    for i in 0..9 {
+        let byte = *bytes.get(i)?;
+        if i == 8 {
+            return Some((((value << 8) | byte as u64) as i64, 9));
+        }
+        value = (value << 7) | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return Some((value as i64, i + 1));
+        }
+    }
+    None
+}
+
+fn read_u16(page: &[u8], offset: usize) -> io::Result<u16> {
+    let bytes = page.get(offset..offset + 2).ok_or_else(corrupt)?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(page: &[u8], offset: usize) -> io::Result<u32> {
+    let bytes = page.get(offset..offset + 4).ok_or_else(corrupt)?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A collection written by SQLite itself with pages of 1024 bytes, so that
+    // its tables take several pages and one of its records overflows.
+    fn fixture() -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/collection.anki2")
+    }
+
+    #[test]
+    fn reads_rows_in_rowid_order() {
+        let database = Database::open(&fixture()).unwrap();
+        let notes = database.table("NOTES").unwrap().unwrap();
+        let rows: Vec<Row> = notes.rows().collect();
+        assert_eq!(rows.len(), 61);
+        assert_eq!(rows[0].get("id"), Value::Integer(1001));
+        assert_eq!(rows[0].get("guid"), Value::Text("g0".to_owned()));
+        assert_eq!(
+            rows[0].get("flds"),
+            Value::Text("<b>perro0</b>\x1fdog\x1fnote 0".to_owned())
+        );
+        assert_eq!(rows[0].get("no_such_column"), Value::Null);
+        assert!(rows.windows(2).all(|pair| pair[0].rowid < pair[1].rowid));
+        assert!(database.table("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn reads_records_from_overflow_pages() {
+        let database = Database::open(&fixture()).unwrap();
+        let notes = database.table("notes").unwrap().unwrap();
+        let long = notes.rows().last().unwrap().get("flds");
+        assert_eq!(long, Value::Text(format!("largo\x1f{}", "ab".repeat(1500))));
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let data = fs::read(fixture()).unwrap();
+        let path = std::env::temp_dir().join(format!("truncated-{}.anki2", std::process::id()));
+        fs::write(&path, &data[..data.len() - 1024]).unwrap();
+        let result = Database::open(&path).and_then(|database| {
+            database.table("notes")?;
+            database.table("cards")?;
+            database.table("revlog")
+        });
+        fs::remove_file(&path).unwrap();
+        assert_eq!(result.err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+}
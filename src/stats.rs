@@ -0,0 +1,811 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::iter;
+
+use crate::days;
+use crate::language::Script;
+use crate::{normalize_whitespace, unix_time, Attempt, Entry, History, Scores};
+
+#[derive(Clone, Debug, Default)]
+pub struct DayStats {
+    pub day: u64,
+    pub reviews: usize,
+    pub perfect: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct TermStats {
+    pub term: String,
+    pub reviews: u32,
+    pub correct_rate: f32,
+    pub response_times: Option<ResponseTimes>,
+}
+
+// Upper bounds of the buckets of response times, in milliseconds. The last
+// bucket holds the slower ones.
+pub const RESPONSE_BUCKETS_MS: [u64; 5] = [1000, 2000, 4000, 8000, 16000];
+
+// How long a term takes to answer, which tells the terms known instantly from
+// the ones eventually remembered.
+#[derive(Clone, Debug)]
+pub struct ResponseTimes {
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub histogram: [usize; RESPONSE_BUCKETS_MS.len() + 1],
+}
+
+impl ResponseTimes {
+    // Attempts from before response times were recorded are left out.
+    pub fn new(attempts: &[Attempt]) -> Option<Self> {
+        let mut times: Vec<u64> = attempts
+            .iter()
+            .filter_map(|attempt| attempt.elapsed_ms)
+            .collect();
+        if times.is_empty() {
+            return None;
+        }
+        times.sort_unstable();
+        let mut histogram = [0; RESPONSE_BUCKETS_MS.len() + 1];
+        for &ms in &times {
+            histogram[RESPONSE_BUCKETS_MS
+                .iter()
+                .filter(|&&bound| ms >= bound)
+                .count()] += 1;
+        }
+        Some(Self {
+            p50_ms: percentile(&times, 50),
+            p95_ms: percentile(&times, 95),
+            histogram,
+        })
+    }
+
+    pub fn sparkline(&self) -> String {
+        const BARS: [char; 9] = [
+            '\u{b7}', '\u{2581}', '\u{2582}', '\u{2583}', '\u{2584}', '\u{2585}', '\u{2586}',
+            '\u{2587}', '\u{2588}',
+        ];
+        let max = self.histogram.iter().copied().max().unwrap_or(0).max(1);
+        self.histogram
+            .iter()
+            .map(|&count| BARS[(count * 8).div_ceil(max)])
+            .collect()
+    }
+}
+
+// The nearest-rank percentile of sorted values.
+fn percentile(sorted: &[u64], p: usize) -> u64 {
+    let rank = (sorted.len() * p).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+// Days are those of days.rs, counted from the Unix epoch. Days without
+// reviews are filled in so that the result can be plotted directly.
+pub fn daily_stats(history: &History, compacted: &[DayStats]) -> Vec<DayStats> {
+    let mut days: BTreeMap<u64, DayStats> = compacted
+        .iter()
+        .map(|stats| (stats.day, stats.clone()))
+        .collect();
+    for attempt in history.values().flatten() {
+        let day = days::day(attempt.timestamp);
+        let stats = days.entry(day).or_insert_with(|| DayStats {
+            day,
+            ..DayStats::default()
+        });
+        stats.reviews += 1;
+        if attempt.mistakes == 0 {
+            stats.perfect += 1;
+        }
+    }
+    let (first, last) = match (days.keys().next(), days.keys().next_back()) {
+        (Some(&first), Some(&last)) => (first, last),
+        _ => return Vec::new(),
+    };
+    (first..=last)
+        .map(|day| {
+            days.remove(&day).unwrap_or(DayStats {
+                day,
+                ..DayStats::default()
+            })
+        })
+        .collect()
+}
+
+pub fn term_stats(scores: &Scores, history: &History) -> Vec<TermStats> {
+    let now = unix_time();
+    let mut terms: Vec<TermStats> = scores
+        .iter()
+        .map(|(term, score)| {
+            let attempts = history.get(term).map(Vec::as_slice).unwrap_or(&[]);
+            TermStats {
+                term: term.clone(),
+                reviews: score.total_tries(),
+                correct_rate: score.decayed_correct_rate(attempts, now),
+                response_times: ResponseTimes::new(attempts),
+            }
+        })
+        .collect();
+    terms.sort_by(|a, b| {
+        a.correct_rate
+            .total_cmp(&b.correct_rate)
+            .then_with(|| a.term.cmp(&b.term))
+    });
+    terms
+}
+
+// Wrong answers that were given more than once, the most frequent first.
+pub fn common_typos(scores: &Scores) -> Vec<(&str, &str, u32)> {
+    let mut typos: Vec<(&str, &str, u32)> = scores
+        .iter()
+        .flat_map(|(term, score)| {
+            score
+                .typos
+                .iter()
+                .filter(|&&(_, count)| count > 1)
+                .map(move |(typo, count)| (term.as_str(), typo.as_str(), *count))
+        })
+        .collect();
+    typos.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(b.0)));
+    typos
+}
+
+pub fn print_summary(scores: &Scores, history: &History, compacted: &[DayStats]) {
+    let days = daily_stats(history, compacted);
+    let terms = term_stats(scores, history);
+    let reviews: usize = days.iter().map(|day| day.reviews).sum();
+    let perfect: usize = days.iter().map(|day| day.perfect).sum();
+    let practiced = days.iter().filter(|day| day.reviews > 0).count();
+
+    println!(
+        "{}Reviews:{} {} ({:.0}% perfect)",
+        termion::style::Bold,
+        termion::style::Reset,
+        reviews,
+        percentage(perfect, reviews),
+    );
+    if let (Some(first), Some(last)) = (days.first(), days.last()) {
+        println!(
+            "{}Days practiced:{} {} ({} to {})",
+            termion::style::Bold,
+            termion::style::Reset,
+            practiced,
+            format_date(first.day),
+            format_date(last.day),
+        );
+    }
+    println!(
+        "{}Terms:{} {}",
+        termion::style::Bold,
+        termion::style::Reset,
+        terms.len()
+    );
+
+    if !days.is_empty() {
+        println!();
+        print_heatmap(&days);
+    }
+
+    if !terms.is_empty() {
+        println!();
+        println!(
+            "{}Weakest terms:{}",
+            termion::style::Bold,
+            termion::style::Reset
+        );
+        for term in terms.iter().take(10) {
+            println!(
+                "  {:>4.0}%  {} {}({} tries){}",
+                term.correct_rate * 100.0,
+                term.term,
+                termion::color::Fg(termion::color::LightBlack),
+                term.reviews,
+                termion::style::Reset,
+            );
+        }
+    }
+    let mut timed: Vec<(&TermStats, &ResponseTimes)> = terms
+        .iter()
+        .filter_map(|term| Some((term, term.response_times.as_ref()?)))
+        .collect();
+    if !timed.is_empty() {
+        timed.sort_by(|a, b| {
+            b.1.p50_ms
+                .cmp(&a.1.p50_ms)
+                .then_with(|| a.0.term.cmp(&b.0.term))
+        });
+        println!();
+        println!(
+            "{}Slowest terms:{} {}(median, 95th percentile, under 1s to over 16s){}",
+            termion::style::Bold,
+            termion::style::Reset,
+            termion::color::Fg(termion::color::LightBlack),
+            termion::style::Reset,
+        );
+        for (term, times) in timed.iter().take(10) {
+            println!(
+                "  {:>5.1}s {:>5.1}s  {}{}{}  {}",
+                times.p50_ms as f64 / 1000.0,
+                times.p95_ms as f64 / 1000.0,
+                termion::color::Fg(termion::color::LightBlue),
+                times.sparkline(),
+                termion::style::Reset,
+                term.term,
+            );
+        }
+    }
+    let typos = common_typos(scores);
+    if !typos.is_empty() {
+        println!();
+        println!(
+            "{}Common typos:{}",
+            termion::style::Bold,
+            termion::style::Reset
+        );
+        for (term, typo, count) in typos.iter().take(10) {
+            println!(
+                "  {} for {} {}({} times){}",
+                typo,
+                term,
+                termion::color::Fg(termion::color::LightBlack),
+                count,
+                termion::style::Reset,
+            );
+        }
+    }
+}
+
+// The script of a character, to tell which writing systems a deck's terms
+// use.
+fn script(c: char) -> &'static str {
+    match Script::of(c) {
+        Some(script) => script.name(),
+        None if c.is_ascii_digit() => "Digits",
+        None => "Other",
+    }
+}
+
+// How much of a frequency list, ordered from the most frequent word, the
+// deck's terms cover.
+pub struct Coverage {
+    // (rank, words within that rank found in the deck)
+    pub tiers: Vec<(usize, usize)>,
+    pub terms_in_list: usize,
+    pub missing: Vec<String>,
+}
+
+pub fn frequency_coverage(entries: &[Entry], words: &[String]) -> Coverage {
+    let entry_terms: Vec<Vec<String>> = entries
+        .iter()
+        .map(|entry| {
+            iter::once(&*entry.term)
+                .chain(entry.alternatives.iter().map(String::as_str))
+                .map(|term| normalize_whitespace(&term.to_lowercase()))
+                .collect()
+        })
+        .collect();
+    let terms: HashSet<&String> = entry_terms.iter().flatten().collect();
+    let words: Vec<String> = words
+        .iter()
+        .map(|word| normalize_whitespace(&word.to_lowercase()))
+        .collect();
+    let mut tiers = Vec::new();
+    let mut rank = 100;
+    while rank < words.len() {
+        tiers.push((
+            rank,
+            words[..rank].iter().filter(|w| terms.contains(*w)).count(),
+        ));
+        rank *= 10;
+    }
+    tiers.push((
+        words.len(),
+        words.iter().filter(|w| terms.contains(*w)).count(),
+    ));
+    let listed: HashSet<&String> = words.iter().collect();
+    Coverage {
+        tiers,
+        terms_in_list: entry_terms
+            .iter()
+            .filter(|terms| terms.iter().any(|term| listed.contains(term)))
+            .count(),
+        missing: words
+            .iter()
+            .filter(|word| !terms.contains(*word))
+            .take(10)
+            .cloned()
+            .collect(),
+    }
+}
+
+pub fn print_deck_summary(entries: &[Entry], frequency_list: Option<&[String]>) {
+    let phrases: usize = entries.iter().map(|entry| entry.phrases.len()).sum();
+    let max_phrases = entries.iter().map(|entry| entry.phrases.len()).max();
+    println!(
+        "{}Entries:{} {}",
+        termion::style::Bold,
+        termion::style::Reset,
+        entries.len()
+    );
+    println!(
+        "{}Phrases per entry:{} {:.1} on average, {} at most",
+        termion::style::Bold,
+        termion::style::Reset,
+        phrases as f32 / entries.len().max(1) as f32,
+        max_phrases.unwrap_or(0),
+    );
+
+    let mut tags: HashMap<&str, usize> = HashMap::new();
+    for entry in entries {
+        for tag in &entry.tags {
+            *tags.entry(tag).or_default() += 1;
+        }
+    }
+    let untagged = entries.iter().filter(|entry| entry.tags.is_empty()).count();
+    let mut tags: Vec<(&str, usize)> = tags.into_iter().collect();
+    tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    println!();
+    println!("{}Tags:{}", termion::style::Bold, termion::style::Reset);
+    for (tag, count) in tags.iter().chain([("(untagged)", untagged)].iter()) {
+        if *count > 0 {
+            println!(
+                "  {:>5}  {:>3.0}%  {}",
+                count,
+                percentage(*count, entries.len()),
+                tag
+            );
+        }
+    }
+
+    // Distinct characters and their total occurrences in terms, per script.
+    let mut scripts: BTreeMap<&str, (HashSet<char>, usize)> = BTreeMap::new();
+    for c in entries
+        .iter()
+        .flat_map(|entry| entry.term.chars())
+        .filter(|c| !c.is_whitespace())
+    {
+        let (distinct, occurrences) = scripts.entry(script(c)).or_default();
+        distinct.insert(c);
+        *occurrences += 1;
+    }
+    let characters: usize = scripts.values().map(|(_, occurrences)| occurrences).sum();
+    println!();
+    println!(
+        "{}Characters in terms:{}",
+        termion::style::Bold,
+        termion::style::Reset
+    );
+    for (name, (distinct, occurrences)) in &scripts {
+        println!(
+            "  {:>3.0}%  {} ({} distinct)",
+            percentage(*occurrences, characters),
+            name,
+            distinct.len()
+        );
+    }
+
+    if let Some(words) = frequency_list {
+        let coverage = frequency_coverage(entries, words);
+        println!();
+        println!(
+            "{}Frequency list coverage:{}",
+            termion::style::Bold,
+            termion::style::Reset
+        );
+        for (rank, found) in &coverage.tiers {
+            println!(
+                "  top {:<6} {:>3.0}%  ({} words)",
+                rank,
+                percentage(*found, *rank),
+                found
+            );
+        }
+        println!(
+            "  {} of {} terms are in the list",
+            coverage.terms_in_list,
+            entries.len()
+        );
+        if !coverage.missing.is_empty() {
+            println!("  most frequent missing: {}", coverage.missing.join(", "));
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatusFormat {
+    Plain,
+    Tmux,
+    Polybar,
+    Json,
+}
+
+impl StatusFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "plain" => Some(StatusFormat::Plain),
+            "tmux" => Some(StatusFormat::Tmux),
+            "polybar" => Some(StatusFormat::Polybar),
+            "json" => Some(StatusFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+// The number of consecutive days with reviews up to today. A streak is still
+// alive until a whole day passes without reviews. Since the days are filled
+// in, it is the run of practiced days at the end.
+pub fn streak(days: &[DayStats], today: u64) -> usize {
+    match days.last() {
+        Some(last) if last.day + 1 >= today => {
+            days.iter().rev().take_while(|day| day.reviews > 0).count()
+        }
+        _ => 0,
+    }
+}
+
+// A one-line summary for status bars. Colors use the markup of each bar, and
+// JSON follows the i3bar protocol.
+pub fn status_line(
+    scores: &Scores,
+    history: &History,
+    compacted: &[DayStats],
+    format: StatusFormat,
+) -> String {
+    let today = days::today();
+    // Cards due later in the day are due today as well.
+    let due = scores
+        .values()
+        .filter(|score| !score.suspended && days::day(score.schedule.due) <= today)
+        .count();
+    let days = daily_stats(history, compacted);
+    let reviews = days
+        .last()
+        .filter(|day| day.day == today)
+        .map_or(0, |day| day.reviews);
+    let text = format!(
+        "{} due, {}d streak, {} today",
+        due,
+        streak(&days, today),
+        reviews
+    );
+    match format {
+        StatusFormat::Plain => text,
+        StatusFormat::Tmux if due > 0 => format!("#[fg=yellow]{}#[default]", text),
+        StatusFormat::Polybar if due > 0 => format!("%{{F#e5c07b}}{}%{{F-}}", text),
+        StatusFormat::Tmux | StatusFormat::Polybar => text,
+        StatusFormat::Json => {
+            let mut json = format!("{{\"full_text\":{}", json_string(&text));
+            if due > 0 {
+                json.push_str(",\"color\":\"#e5c07b\"");
+            }
+            json.push('}');
+            json
+        }
+    }
+}
+
+const HEATMAP_COLORS: [u8; 5] = [236, 22, 28, 34, 40];
+
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+pub fn print_heatmap(days: &[DayStats]) {
+    let reviews: HashMap<u64, usize> = days.iter().map(|day| (day.day, day.reviews)).collect();
+    let width = termion::terminal_size()
+        .map(|(width, _)| width as usize)
+        .unwrap_or(80);
+    let weeks = ((width.saturating_sub(4)) / 2).clamp(1, 53) as u64;
+    let today = days::today();
+    let start = today - weekday(today) - (weeks - 1) * 7;
+    let max = (start..=today)
+        .filter_map(|day| reviews.get(&day))
+        .max()
+        .copied()
+        .unwrap_or(0);
+
+    let mut header = String::from("    ");
+    let mut last_month = None;
+    let mut week = 0;
+    while week < weeks {
+        let (_, month, _) = civil_from_days(start + week * 7);
+        if last_month != Some(month) && week + 2 <= weeks {
+            header.push_str(MONTH_NAMES[month as usize - 1]);
+            header.push(' ');
+            last_month = Some(month);
+            week += 2;
+        } else {
+            header.push_str("  ");
+            week += 1;
+        }
+    }
+    println!("{}", header.trim_end());
+
+    for row in 0..7 {
+        let label = match row {
+            1 => "Mon",
+            3 => "Wed",
+            5 => "Fri",
+            _ => "",
+        };
+        print!("{:<4}", label);
+        for week in 0..weeks {
+            let day = start + week * 7 + row;
+            if day > today {
+                break;
+            }
+            let count = reviews.get(&day).copied().unwrap_or(0);
+            print!(
+                "{}\u{25a0}{} ",
+                termion::color::Fg(termion::color::AnsiValue(
+                    HEATMAP_COLORS[heat_level(count, max)]
+                )),
+                termion::style::Reset,
+            );
+        }
+        println!();
+    }
+}
+
+fn heat_level(count: usize, max: usize) -> usize {
+    if count == 0 || max == 0 {
+        0
+    } else {
+        (count * 4).div_ceil(max).clamp(1, 4)
+    }
+}
+
+// 0 is Sunday; the Unix epoch was a Thursday.
+fn weekday(days: u64) -> u64 {
+    (days + 4) % 7
+}
+
+pub fn render_html(scores: &Scores, history: &History, compacted: &[DayStats]) -> String {
+    let days = daily_stats(history, compacted);
+    let terms = term_stats(scores, history);
+
+    // The heatmap ends on the day of days.rs, whatever the browser's clock.
+    let mut data = format!("{{\"today\":\"{}\",\"days\":[", format_date(days::today()));
+    for (i, day) in days.iter().enumerate() {
+        if i > 0 {
+            data.push(',');
+        }
+        write!(
+            data,
+            "{{\"date\":\"{}\",\"reviews\":{},\"perfect\":{}}}",
+            format_date(day.day),
+            day.reviews,
+            day.perfect
+        )
+        .unwrap();
+    }
+    data.push_str("],\"terms\":[");
+    for (i, term) in terms.iter().enumerate() {
+        if i > 0 {
+            data.push(',');
+        }
+        write!(
+            data,
+            "{{\"term\":{},\"reviews\":{},\"rate\":{:.4}}}",
+            json_string(&term.term),
+            term.reviews,
+            term.correct_rate
+        )
+        .unwrap();
+    }
+    data.push_str("]}");
+
+    HTML_TEMPLATE.replace("/*DATA*/", &data)
+}
+
+pub fn percentage(part: usize, total: usize) -> f32 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f32 * 100.0 / total as f32
+    }
+}
+
+// Converts days since the Unix epoch to a proleptic Gregorian date.
+pub fn civil_from_days(days: u64) -> (i64, u32, u32) {
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// Converts a proleptic Gregorian date to days since the Unix epoch.
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    (era * 146_097 + doe - 719_468).max(0) as u64
+}
+
+// The days since the Unix epoch of a "YYYY-MM-DD" date.
+pub fn parse_date(date: &str) -> Option<u64> {
+    let mut parts = date.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts
+        .next()?
+        .parse()
+        .ok()
+        .filter(|month| (1..=12).contains(month))?;
+    let day = parts
+        .next()?
+        .parse()
+        .ok()
+        .filter(|day| (1..=31).contains(day))?;
+    Some(days_from_civil(year, month, day))
+}
+
+pub fn format_date(days: u64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+pub fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\t' => json.push_str("\\t"),
+            // Keeps "</script>" in a term from closing the data block.
+            '<' => json.push_str("\\u003c"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+const HTML_TEMPLATE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>vocab-trainer statistics</title>
+<style>
+body { font-family: sans-serif; margin: 2em auto; max-width: 960px; color: #222; }
+h2 { margin-top: 2em; font-size: 1.1em; }
+canvas { width: 100%; height: 240px; border: 1px solid #ddd; }
+#tooltip { position: fixed; pointer-events: none; background: #222; color: #fff; padding: 2px 6px; font-size: 12px; border-radius: 3px; display: none; }
+#terms { width: 100%; border-collapse: collapse; font-size: 14px; }
+#terms td { padding: 2px 6px; }
+#terms .bar { background: #4a90d9; height: 10px; }
+#heatmap { display: grid; grid-auto-flow: column; grid-template-rows: repeat(7, 11px); gap: 2px; }
+#heatmap div { width: 11px; height: 11px; border-radius: 2px; }
+#filter { margin-bottom: 0.5em; }
+</style>
+</head>
+<body>
+<h1>vocab-trainer statistics</h1>
+<h2>Accuracy over time</h2>
+<canvas id="accuracy"></canvas>
+<h2>Reviews per day</h2>
+<canvas id="reviews"></canvas>
+<h2>Activity</h2>
+<div id="heatmap"></div>
+<h2>Mastery per term</h2>
+<input id="filter" type="search" placeholder="Filter terms">
+<table id="terms"></table>
+<div id="tooltip"></div>
+<script>
+const data = /*DATA*/;
+const tooltip = document.getElementById("tooltip");
+
+function setup(canvas) {
+  const ratio = window.devicePixelRatio || 1;
+  canvas.width = canvas.clientWidth * ratio;
+  canvas.height = canvas.clientHeight * ratio;
+  const ctx = canvas.getContext("2d");
+  ctx.scale(ratio, ratio);
+  return { ctx, width: canvas.clientWidth, height: canvas.clientHeight };
+}
+
+function chart(id, values, max, draw, label) {
+  const canvas = document.getElementById(id);
+  const { ctx, width, height } = setup(canvas);
+  const pad = 24;
+  const step = (width - pad * 2) / Math.max(values.length, 1);
+  const y = v => height - pad - (height - pad * 2) * (max > 0 ? v / max : 0);
+  ctx.strokeStyle = "#ccc";
+  ctx.beginPath();
+  ctx.moveTo(pad, height - pad);
+  ctx.lineTo(width - pad, height - pad);
+  ctx.stroke();
+  draw(ctx, step, y, pad);
+  canvas.addEventListener("mousemove", event => {
+    const rect = canvas.getBoundingClientRect();
+    const i = Math.floor((event.clientX - rect.left - pad) / step);
+    if (i >= 0 && i < values.length) {
+      tooltip.textContent = data.days[i].date + ": " + label(i);
+      tooltip.style.left = event.clientX + 12 + "px";
+      tooltip.style.top = event.clientY + 12 + "px";
+      tooltip.style.display = "block";
+    } else {
+      tooltip.style.display = "none";
+    }
+  });
+  canvas.addEventListener("mouseleave", () => tooltip.style.display = "none");
+}
+
+const reviews = data.days.map(d => d.reviews);
+const accuracy = data.days.map(d => d.reviews > 0 ? d.perfect / d.reviews : null);
+
+chart("accuracy", accuracy, 1, (ctx, step, y, pad) => {
+  ctx.strokeStyle = "#3a3";
+  ctx.lineWidth = 2;
+  ctx.beginPath();
+  let started = false;
+  accuracy.forEach((v, i) => {
+    if (v === null) return;
+    const x = pad + step * (i + 0.5);
+    if (started) ctx.lineTo(x, y(v)); else ctx.moveTo(x, y(v));
+    started = true;
+  });
+  ctx.stroke();
+}, i => accuracy[i] === null ? "no reviews" : Math.round(accuracy[i] * 100) + "% perfect");
+
+chart("reviews", reviews, Math.max(...reviews, 1), (ctx, step, y, pad) => {
+  ctx.fillStyle = "#4a90d9";
+  reviews.forEach((v, i) => {
+    ctx.fillRect(pad + step * i + 1, y(v), Math.max(step - 2, 1), y(0) - y(v));
+  });
+}, i => reviews[i] + " reviews");
+
+function renderHeatmap() {
+  const colors = ["#ebedf0", "#9be9a8", "#40c463", "#30a14e", "#216e39"];
+  const counts = new Map(data.days.map(d => [d.date, d.reviews]));
+  const day = 24 * 60 * 60 * 1000;
+  // Dates are counted as UTC midnights, which only stand for the days.
+  const today = new Date(data.today + "T00:00:00Z");
+  const start = new Date(today.getTime() - (today.getUTCDay() + 52 * 7) * day);
+  let max = 0;
+  for (let t = start.getTime(); t <= today.getTime(); t += day) {
+    max = Math.max(max, counts.get(new Date(t).toISOString().slice(0, 10)) || 0);
+  }
+  const heatmap = document.getElementById("heatmap");
+  for (let t = start.getTime(); t <= today.getTime(); t += day) {
+    const date = new Date(t).toISOString().slice(0, 10);
+    const count = counts.get(date) || 0;
+    const level = count === 0 || max === 0 ? 0 : Math.min(4, Math.max(1, Math.ceil(count * 4 / max)));
+    const cell = document.createElement("div");
+    cell.style.background = colors[level];
+    cell.title = date + ": " + count + " reviews";
+    heatmap.appendChild(cell);
+  }
+}
+renderHeatmap();
+
+function renderTerms() {
+  const query = document.getElementById("filter").value.toLowerCase();
+  const table = document.getElementById("terms");
+  table.innerHTML = "";
+  data.terms.filter(t => t.term.toLowerCase().includes(query)).forEach(t => {
+    const row = table.insertRow();
+    row.insertCell().textContent = t.term;
+    row.insertCell().textContent = Math.round(t.rate * 100) + "%";
+    row.insertCell().textContent = t.reviews + " tries";
+    const bar = document.createElement("div");
+    bar.className = "bar";
+    bar.style.width = Math.round(t.rate * 100) + "%";
+    row.insertCell().appendChild(bar);
+  });
+}
+document.getElementById("filter").addEventListener("input", renderTerms);
+renderTerms();
+</script>
+</body>
+</html>
+"##;
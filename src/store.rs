@@ -0,0 +1,58 @@
+use std::sync::{Arc, RwLock};
+
+use crate::{Score, Scores};
+
+// The scores shared between the quiz loop, the autosave thread and the
+// daemon. Cloning a store gives another handle on the same scores.
+//
+// Every update runs under a single lock, so other threads see either none or
+// all of its changes. The version counts the updates, which tells the autosave
+// thread whether there is anything new to write.
+#[derive(Clone, Debug, Default)]
+pub struct ScoreStore {
+    inner: Arc<RwLock<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    scores: Scores,
+    version: u64,
+}
+
+impl ScoreStore {
+    pub fn new(scores: Scores) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(Inner { scores, version: 0 })),
+        }
+    }
+
+    pub fn get(&self, term: &str) -> Option<Score> {
+        self.read(|scores| scores.get(term).cloned())
+    }
+
+    pub fn contains(&self, term: &str) -> bool {
+        self.read(|scores| scores.contains_key(term))
+    }
+
+    // Holds the lock for the whole of `f`, so that it sees the scores as they
+    // were after a single update.
+    pub fn read<R>(&self, f: impl FnOnce(&Scores) -> R) -> R {
+        f(&self.inner.read().unwrap().scores)
+    }
+
+    pub fn update<R>(&self, f: impl FnOnce(&mut Scores) -> R) -> R {
+        let mut inner = self.inner.write().unwrap();
+        inner.version += 1;
+        f(&mut inner.scores)
+    }
+
+    pub fn version(&self) -> u64 {
+        self.inner.read().unwrap().version
+    }
+
+    // A copy of the scores along with the version they are at.
+    pub fn snapshot(&self) -> (Scores, u64) {
+        let inner = self.inner.read().unwrap();
+        (inner.scores.clone(), inner.version)
+    }
+}
@@ -0,0 +1,94 @@
+// Display tweaks a deck can suggest in its settings, such as a DECK.toml next
+// to it:
+//
+//   [style]
+//   separator = "|"
+//   comments = "deferred"
+//   letter_spacing = 1
+//   line_spacing = 1
+//
+// The separator goes between the phrases of a question instead of '/'.
+// Comments are shown "inline" after their phrases, "deferred" until the
+// question is answered, or "hidden". Letter spacing puts spaces between the
+// characters of phrases and shown terms, which makes dense scripts like
+// Chinese easier to read at a glance, and line spacing blank lines before
+// each question.
+
+use std::borrow::Cow;
+
+use crate::language;
+use crate::settings::Settings;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Comments {
+    #[default]
+    Inline,
+    Deferred,
+    Hidden,
+}
+
+#[derive(Debug)]
+pub struct Style {
+    pub separator: String,
+    pub comments: Comments,
+    pub letter_spacing: usize,
+    pub line_spacing: usize,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            separator: "/".to_owned(),
+            comments: Comments::default(),
+            letter_spacing: 0,
+            line_spacing: 0,
+        }
+    }
+}
+
+impl Style {
+    pub fn from_settings(settings: &Settings) -> Result<Self, String> {
+        let mut style = Self::default();
+        if let Some(separator) = settings.get_str("style", "separator") {
+            style.separator = separator.to_owned();
+        }
+        if let Some(comments) = settings.get_str("style", "comments") {
+            style.comments = match comments {
+                "inline" => Comments::Inline,
+                "deferred" => Comments::Deferred,
+                "hidden" => Comments::Hidden,
+                _ => return Err(format!("unknown placement of comments: {}", comments)),
+            };
+        }
+        let spacing = |key| {
+            settings
+                .get_integer("style", key)
+                .map_or(0, |spacing| spacing.max(0) as usize)
+        };
+        style.letter_spacing = spacing("letter_spacing");
+        style.line_spacing = spacing("line_spacing");
+        Ok(style)
+    }
+
+    // Marks stay with the letter they sit on, and spaces already there aren't
+    // widened.
+    pub fn spaced<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.letter_spacing == 0 {
+            return Cow::Borrowed(text);
+        }
+        let gap = " ".repeat(self.letter_spacing);
+        let mut spaced = String::with_capacity(text.len() * (1 + self.letter_spacing));
+        let mut previous: Option<char> = None;
+        for c in text.chars() {
+            if previous.is_some_and(|previous| !previous.is_whitespace())
+                && !c.is_whitespace()
+                && !language::is_combining_mark(None, c)
+            {
+                spaced.push_str(&gap);
+            }
+            spaced.push(c);
+            previous = Some(c);
+        }
+        Cow::Owned(spaced)
+    }
+}
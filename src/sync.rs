@@ -0,0 +1,347 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufRead as _, BufReader, Write as _};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::{load_scores, save_scores, unix_time, Score, Scores, TYPO_LIMIT};
+#[cfg(feature = "net")]
+use crate::{read_scores, write_scores};
+
+#[derive(Clone, Debug, Default)]
+pub struct SyncFiles {
+    pub scores: Scores,
+    pub history: Vec<String>,
+}
+
+impl SyncFiles {
+    pub fn load(dir: &Path) -> io::Result<Self> {
+        Ok(Self {
+            scores: load_scores(dir.join("scores.txt"))?,
+            history: read_lines(&dir.join("history.txt"))?,
+        })
+    }
+
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        save_scores(dir.join("scores.txt"), self.scores.clone())?;
+        write_lines(&dir.join("history.txt"), &self.history)
+    }
+}
+
+pub trait SyncBackend {
+    fn pull(&mut self) -> io::Result<SyncFiles>;
+
+    fn push(&mut self, files: &SyncFiles) -> io::Result<()>;
+
+    // The base is the state of the last sync, so counts added on both sides
+    // since then are summed up instead of conflicting.
+    fn resolve(&self, base: &SyncFiles, local: &SyncFiles, remote: &SyncFiles) -> SyncFiles {
+        SyncFiles {
+            scores: merge_scores(&base.scores, &local.scores, &remote.scores),
+            history: merge_lines(&local.history, &remote.history),
+        }
+    }
+}
+
+// The last synced state is kept in `base_dir`, which must not be where a
+// backend keeps its own copy of the remote files. Nothing is saved until the
+// push went through, so that a failed sync leaves the local files and the
+// base as they were, to be merged again the next time.
+pub fn sync(backend: &mut dyn SyncBackend, config_dir: &Path, base_dir: &Path) -> io::Result<()> {
+    let base = SyncFiles::load(base_dir)?;
+    let local = SyncFiles::load(config_dir)?;
+    let remote = backend.pull()?;
+    let merged = backend.resolve(&base, &local, &remote);
+    backend.push(&merged)?;
+    merged.save(config_dir)?;
+    fs::create_dir_all(base_dir)?;
+    merged.save(base_dir)
+}
+
+pub struct GitBackend {
+    dir: PathBuf,
+    remote: String,
+    branch: String,
+}
+
+impl GitBackend {
+    pub fn new(dir: PathBuf, remote: String, branch: String) -> Self {
+        Self {
+            dir,
+            remote,
+            branch,
+        }
+    }
+
+    fn git(&self, args: &[&str]) -> io::Result<()> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!(
+                "git {} failed: {}",
+                args.first().unwrap_or(&""),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )))
+        }
+    }
+}
+
+impl SyncBackend for GitBackend {
+    fn pull(&mut self) -> io::Result<SyncFiles> {
+        if !self.dir.join(".git").exists() {
+            fs::create_dir_all(&self.dir)?;
+            self.git(&["init", "-q"])?;
+            self.git(&[
+                "symbolic-ref",
+                "HEAD",
+                &format!("refs/heads/{}", self.branch),
+            ])?;
+            self.git(&["remote", "add", "origin", &self.remote])?;
+        } else {
+            self.git(&["remote", "set-url", "origin", &self.remote])?;
+        }
+        self.git(&["fetch", "-q", "origin"])?;
+        let remote_ref = format!("origin/{}", self.branch);
+        if self
+            .git(&["rev-parse", "-q", "--verify", &remote_ref])
+            .is_ok()
+        {
+            self.git(&["checkout", "-q", "-f", "-B", &self.branch, &remote_ref])?;
+        }
+        SyncFiles::load(&self.dir)
+    }
+
+    fn push(&mut self, files: &SyncFiles) -> io::Result<()> {
+        files.save(&self.dir)?;
+        self.git(&["add", "--", "scores.txt", "history.txt"])?;
+        if self.git(&["diff", "--cached", "--quiet"]).is_err() {
+            let message = format!("Sync scores at {}", unix_time());
+            self.git(&["commit", "-q", "-m", &message])?;
+        }
+        self.git(&["push", "-q", "-u", "origin", &self.branch])
+    }
+}
+
+#[cfg(feature = "net")]
+pub struct WebDavBackend {
+    url: String,
+    authorization: Option<String>,
+    etags: std::collections::HashMap<&'static str, String>,
+}
+
+#[cfg(feature = "net")]
+impl WebDavBackend {
+    pub fn new(url: &str, username: Option<&str>, password: Option<&str>) -> Self {
+        let authorization = username.map(|username| {
+            let credentials = format!("{}:{}", username, password.unwrap_or_default());
+            format!("Basic {}", base64(credentials.as_bytes()))
+        });
+        Self {
+            url: url.trim_end_matches('/').to_owned(),
+            authorization,
+            etags: std::collections::HashMap::new(),
+        }
+    }
+
+    fn request(&self, method: &str, name: &str) -> ureq::Request {
+        let request = ureq::request(method, &format!("{}/{}", self.url, name));
+        match &self.authorization {
+            Some(authorization) => request.set("Authorization", authorization),
+            None => request,
+        }
+    }
+
+    fn get(&mut self, name: &'static str) -> io::Result<String> {
+        match self.request("GET", name).call() {
+            Ok(response) => {
+                if let Some(etag) = response.header("ETag") {
+                    self.etags.insert(name, etag.to_owned());
+                }
+                response.into_string()
+            }
+            Err(ureq::Error::Status(404, _)) => {
+                self.etags.remove(name);
+                Ok(String::new())
+            }
+            Err(error) => Err(io::Error::other(error.to_string())),
+        }
+    }
+
+    // Uploads with the ETag seen when pulling, so that a concurrent sync from
+    // another device is detected instead of silently overwritten.
+    fn put(&self, name: &'static str, body: &str) -> io::Result<()> {
+        let request = match self.etags.get(name) {
+            Some(etag) => self.request("PUT", name).set("If-Match", etag),
+            None => self.request("PUT", name).set("If-None-Match", "*"),
+        };
+        match request.send_string(body) {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(412, _)) => Err(io::Error::other(
+                "remote changed during sync, please sync again",
+            )),
+            Err(error) => Err(io::Error::other(error.to_string())),
+        }
+    }
+}
+
+#[cfg(feature = "net")]
+impl SyncBackend for WebDavBackend {
+    fn pull(&mut self) -> io::Result<SyncFiles> {
+        let scores = self.get("scores.txt")?;
+        let history = self.get("history.txt")?;
+        Ok(SyncFiles {
+            scores: read_scores(scores.as_bytes())?,
+            history: history
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(str::to_owned)
+                .collect(),
+        })
+    }
+
+    fn push(&mut self, files: &SyncFiles) -> io::Result<()> {
+        let mut scores = Vec::new();
+        write_scores(&mut scores, &files.scores)?;
+        let mut history = String::new();
+        for line in &files.history {
+            history.push_str(line);
+            history.push('\n');
+        }
+        self.put("scores.txt", &String::from_utf8_lossy(&scores))?;
+        self.put("history.txt", &history)
+    }
+}
+
+#[cfg(feature = "net")]
+fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+fn merge_scores(base: &Scores, local: &Scores, remote: &Scores) -> Scores {
+    let terms: HashSet<&String> = local.keys().chain(remote.keys()).collect();
+    terms
+        .into_iter()
+        .map(|term| {
+            let base = base.get(term).cloned().unwrap_or_default();
+            let local = local.get(term).cloned().unwrap_or_default();
+            let remote = remote.get(term).cloned().unwrap_or_default();
+            let schedule = if local.schedule == base.schedule {
+                remote.schedule.clone()
+            } else if remote.schedule == base.schedule || local.schedule.due > remote.schedule.due {
+                local.schedule.clone()
+            } else {
+                remote.schedule.clone()
+            };
+            let score = Score {
+                correct: (remote.correct + local.correct).saturating_sub(base.correct),
+                incorrect: (remote.incorrect + local.incorrect).saturating_sub(base.incorrect),
+                schedule,
+                id: if local.id.is_empty() {
+                    remote.id
+                } else {
+                    local.id
+                },
+                suspended: if local.suspended == base.suspended {
+                    remote.suspended
+                } else {
+                    local.suspended
+                },
+                starred: if local.starred == base.starred {
+                    remote.starred
+                } else {
+                    local.starred
+                },
+                typos: merge_typos(&base.typos, &local.typos, &remote.typos),
+            };
+            (term.clone(), score)
+        })
+        .collect()
+}
+
+// Counts are merged like the correct and incorrect ones.
+fn merge_typos(
+    base: &[(String, u32)],
+    local: &[(String, u32)],
+    remote: &[(String, u32)],
+) -> Vec<(String, u32)> {
+    let count = |typos: &[(String, u32)], typo: &str| {
+        typos
+            .iter()
+            .find(|(other, _)| other == typo)
+            .map_or(0, |&(_, count)| count)
+    };
+    let forms: BTreeSet<&String> = local.iter().chain(remote).map(|(typo, _)| typo).collect();
+    let mut typos: Vec<(String, u32)> = forms
+        .into_iter()
+        .map(|typo| {
+            let merged =
+                (count(local, typo) + count(remote, typo)).saturating_sub(count(base, typo));
+            (typo.clone(), merged)
+        })
+        .filter(|&(_, count)| count > 0)
+        .collect();
+    typos.sort_by_key(|&(_, count)| Reverse(count));
+    // Typos added on both sides can add up to more than a dictionary holds,
+    // of which the most frequent are kept.
+    typos.truncate(TYPO_LIMIT);
+    typos
+}
+
+// History records are unique lines, so merging them is a sorted union.
+fn merge_lines(local: &[String], remote: &[String]) -> Vec<String> {
+    let lines: BTreeSet<&String> = local.iter().chain(remote).collect();
+    let mut lines: Vec<String> = lines.into_iter().cloned().collect();
+    lines.sort_by_key(|line| {
+        line.split('\t')
+            .next()
+            .and_then(|timestamp| timestamp.parse::<u64>().ok())
+            .unwrap_or(0)
+    });
+    lines
+}
+
+fn read_lines(path: &Path) -> io::Result<Vec<String>> {
+    let mut lines = Vec::new();
+    if path.exists() {
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if !line.is_empty() {
+                lines.push(line);
+            }
+        }
+    }
+    Ok(lines)
+}
+
+fn write_lines(path: &Path, lines: &[String]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = io::BufWriter::new(File::create(path)?);
+    for line in lines {
+        writeln!(file, "{}", line)?;
+    }
+    file.flush()
+}
@@ -0,0 +1,95 @@
+// A warm-up round of recognition before the typed questions:
+//
+//   vocab-trainer DECK --warm-up true-false
+//
+// The terms of the first cards of the session are shown with a meaning each,
+// which for about half of them is another card's, and answered with y if it
+// is theirs and n if it isn't. Cards not told right are asked first of the
+// typed questions, and cards told right that were answered before are left
+// out of them, as they are known well enough for this session. New cards are
+// always asked. Like the matching pairs, the round isn't recorded in the
+// scores, and takes as many cards as `[warm_up] cards` is set to.
+
+use std::fmt;
+use std::io::{self, Write as _};
+
+use crate::settings::Settings;
+use crate::{pairs, shuffle, AnswerInput, GameState};
+
+// Returns whether the warm-up was quit, which ends the session.
+pub fn run(
+    state: &mut GameState,
+    settings: &Settings,
+    plain: bool,
+    input: &mut AnswerInput,
+) -> io::Result<bool> {
+    let cards = pairs::pick_cards(state, settings);
+    if cards.is_empty() {
+        return Ok(false);
+    }
+    let mut order: Vec<usize> = (0..cards.len()).collect();
+    shuffle(&mut order);
+    // Every mismatched card is shown the meaning of the next one, so none gets
+    // its own. A single card can't be mismatched with another.
+    let mismatched = cards.len() / 2;
+    let mut shown: Vec<usize> = (0..cards.len()).collect();
+    for (k, &i) in order[..mismatched].iter().enumerate() {
+        shown[i] = order[(k + 1) % mismatched];
+    }
+    if mismatched == 1 {
+        shown[order[0]] = order[1];
+    }
+
+    let ansi = |sequence: &dyn fmt::Display| {
+        if plain {
+            String::new()
+        } else {
+            sequence.to_string()
+        }
+    };
+    println!("Is it the right meaning? y or n");
+    let mut right = 0;
+    let mut missed: Vec<usize> = Vec::new();
+    let mut known: Vec<usize> = Vec::new();
+    for (i, (entry, _)) in cards.iter().enumerate() {
+        let matches = shown[i] == i;
+        let answer = loop {
+            print!("{} /{}/ ", state.deck[*entry].term, cards[shown[i]].1);
+            io::stdout().flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(true);
+            }
+            match line.trim().to_lowercase().as_str() {
+                "y" | "yes" => break true,
+                "n" | "no" => break false,
+                ":q" | ":quit" => return Ok(true),
+                _ => println!("y or n"),
+            }
+        };
+        if answer == matches {
+            right += 1;
+            if state.scores.contains(state.deck[*entry].key()) {
+                known.push(*entry);
+            }
+        } else {
+            missed.push(*entry);
+            println!(
+                "{}  no, {} is /{}/{}",
+                ansi(&termion::color::Fg(termion::color::LightRed)),
+                state.deck[*entry].term,
+                cards[i].1,
+                ansi(&termion::style::Reset),
+            );
+        }
+    }
+    state
+        .pending
+        .retain(|entry| !known.contains(entry) && !missed.contains(entry));
+    for &entry in missed.iter().rev() {
+        state.pending.push_front(entry);
+    }
+    println!("Warm-up: {} of {} right", right, cards.len());
+    println!();
+    Ok(false)
+}
@@ -0,0 +1,42 @@
+// ULIDs identify sessions in the history: 48 bits of milliseconds since the
+// epoch and 80 random bits, written as 26 characters of Crockford's base 32.
+// They sort by the time the session started, whichever machine it was on.
+
+use std::fs::File;
+use std::io::Read as _;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::Xorshift;
+
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+pub fn generate() -> String {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let millis = elapsed.as_millis() & ((1 << 48) - 1);
+    let value = (millis << 80) | (random_bits(elapsed.as_nanos() as u64) & ((1 << 80) - 1));
+    (0..26)
+        .rev()
+        .map(|i| ALPHABET[(value >> (i * 5)) as usize & 31] as char)
+        .collect()
+}
+
+pub fn is_valid(s: &str) -> bool {
+    s.len() == 26 && s.bytes().all(|b| ALPHABET.contains(&b))
+}
+
+// Read from the system where there is a source, or else mixed from the clock
+// and the process ID, which still keeps two sessions apart.
+fn random_bits(nanos: u64) -> u128 {
+    let mut bytes = [0; 16];
+    if File::open("/dev/urandom")
+        .and_then(|mut file| file.read_exact(&mut bytes))
+        .is_ok()
+    {
+        return u128::from_le_bytes(bytes);
+    }
+    let mut random = Xorshift::new(nanos ^ ((process::id() as u64) << 32));
+    ((random.next() as u128) << 64) | random.next() as u128
+}